@@ -1,172 +1,197 @@
 use std::{
     io::Write,
     process::{Command, Stdio},
+    sync::mpsc::{self, Receiver, TryRecvError},
     task::Poll,
-};
-
-use crossterm::{
-    handle_command,
-    style::{Print, SetForegroundColor},
-    Result,
+    thread,
 };
 
 use crate::{
     async_process::{AsyncChild, ChildOutput, Executor},
-    tui_util::{AvailableSize, LOG_COLORS},
+    vcs_error::VcsError,
 };
 
 pub type ActionResult = ChildOutput;
 
-#[derive(PartialEq, Eq, Hash, Clone, Copy)]
-pub enum ActionKind {
-    Quit,
-    Help,
-    Status,
-    Log,
-    LogCount,
-    CurrentFullRevision,
-    CurrentDiffAll,
-    CurrentDiffSelected,
-    RevisionChanges,
-    RevisionDiffAll,
-    RevisionDiffSelected,
-    CommitAll,
-    CommitSelected,
-    Update,
-    Merge,
-    RevertAll,
-    RevertSelected,
-    UnresolvedConflicts,
-    MergeTakingOther,
-    MergeTakingLocal,
-    Fetch,
-    Pull,
-    Push,
-    NewTag,
-    ListBranches,
-    NewBranch,
-    DeleteBranch,
-    CustomAction,
-}
-
-impl ActionKind {
-    pub fn name(self) -> &'static str {
-        match self {
-            Self::Quit => "quit",
-            Self::Help => "help",
-            Self::Status => "status",
-            Self::Log => "log",
-            Self::LogCount => "log count",
-            Self::CurrentFullRevision => "revision full contents",
-            Self::CurrentDiffAll => "current diff all",
-            Self::CurrentDiffSelected => "current diff selected",
-            Self::RevisionChanges => "revision changes",
-            Self::RevisionDiffAll => "revision diff all",
-            Self::RevisionDiffSelected => "revision diff selected",
-            Self::CommitAll => "commit all",
-            Self::CommitSelected => "commit selected",
-            Self::Update => "update/checkout",
-            Self::Merge => "merge",
-            Self::RevertAll => "revert all",
-            Self::RevertSelected => "revert selected",
-            Self::UnresolvedConflicts => "unresolved conflicts",
-            Self::MergeTakingOther => "merge taking other",
-            Self::MergeTakingLocal => "merge taking local",
-            Self::Fetch => "fetch",
-            Self::Pull => "pull",
-            Self::Push => "push",
-            Self::NewTag => "new tag",
-            Self::ListBranches => "list branches",
-            Self::NewBranch => "new branch",
-            Self::DeleteBranch => "delete branch",
-            Self::CustomAction => "custom action",
+pub trait ActionTask: Send {
+    fn poll(&mut self, executor: &mut Executor) -> Poll<ActionResult>;
+}
+
+pub enum CommandTask {
+    Waiting(Command),
+    /// Waiting to run a command that reads `input` from its stdin
+    WaitingWithInput(Command, String),
+    Running(AsyncChild),
+}
+
+impl ActionTask for CommandTask {
+    fn poll(&mut self, executor: &mut Executor) -> Poll<ActionResult> {
+        let (command, input) = match self {
+            CommandTask::Waiting(command) => (command, None),
+            CommandTask::WaitingWithInput(command, input) => {
+                (command, Some(std::mem::take(input)))
+            }
+            CommandTask::Running(child) => return child.poll(),
+        };
+
+        let stdin = if input.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        };
+        let child = command
+            .stdin(stdin)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn();
+        match child {
+            Ok(mut child) => {
+                if let (Some(input), Some(mut stdin)) =
+                    (input, child.stdin.take())
+                {
+                    // write from another thread so a child that fills its
+                    // output before reading all of its input can't block us
+                    thread::spawn(move || {
+                        let _ = stdin.write_all(input.as_bytes());
+                    });
+                }
+                let async_child = executor.run_child_async(child);
+                *self = CommandTask::Running(async_child);
+                Poll::Pending
+            }
+            Err(e) => Poll::Ready(ActionResult::from_error(
+                VcsError::from_spawn(command.get_program(), e),
+            )),
         }
     }
+}
 
-    pub fn can_select_output(self) -> bool {
-        match self {
-            Self::Log | Self::LogCount | Self::ListBranches => true,
-            _ => false,
-        }
+pub struct ReadyTask(ActionResult);
+
+impl ActionTask for ReadyTask {
+    fn poll(&mut self, _executor: &mut Executor) -> Poll<ActionResult> {
+        Poll::Ready(self.0.clone())
     }
+}
 
-    pub fn line_formatter<W>(
-        self,
-    ) -> fn(&mut W, &str, AvailableSize) -> Result<()>
-    where
-        W: Write,
-    {
-        match self {
-            Self::Log | Self::LogCount => |write, line, available_size| {
-                let mut slice_end = line
-                    .char_indices()
-                    .take(available_size.width - 1)
-                    .last()
-                    .map(|(i, _)| i + 1)
-                    .unwrap_or(0);
-                while !line.is_char_boundary(slice_end) {
-                    slice_end += 1;
-                }
+pub fn ready(result: ActionResult) -> Box<dyn ActionTask> {
+    Box::new(ReadyTask(result))
+}
 
-                let line = &line[..slice_end];
-                for (part, color) in
-                    line.splitn(LOG_COLORS.len(), '\x1e').zip(LOG_COLORS.iter())
-                {
-                    handle_command!(write, SetForegroundColor(*color))?;
-                    handle_command!(write, Print(part))?;
-                    handle_command!(write, Print(' '))?;
-                }
-                Ok(())
-            },
-            _ => |write, line, _available_size| {
-                handle_command!(write, Print(line))
-            },
+struct LazyTask<F>
+where
+    F: 'static + Send + FnOnce() -> ActionResult,
+{
+    run: Option<F>,
+    result: Option<ActionResult>,
+}
+
+impl<F> ActionTask for LazyTask<F>
+where
+    F: 'static + Send + FnOnce() -> ActionResult,
+{
+    fn poll(&mut self, _executor: &mut Executor) -> Poll<ActionResult> {
+        if let Some(run) = self.run.take() {
+            self.result = Some(run());
+        }
+        match &self.result {
+            Some(result) => Poll::Ready(result.clone()),
+            None => Poll::Pending,
         }
     }
+}
+
+/// Runs `run` in-process when the task is first polled, which inside
+/// `serial` is only after the tasks before it have finished
+pub fn lazy<F>(run: F) -> Box<dyn ActionTask>
+where
+    F: 'static + Send + FnOnce() -> ActionResult,
+{
+    Box::new(LazyTask {
+        run: Some(run),
+        result: None,
+    })
+}
+
+enum BackgroundTask<F>
+where
+    F: 'static + Send + FnOnce() -> ActionResult,
+{
+    Waiting(Option<F>),
+    Running(Receiver<ActionResult>),
+    Done(ActionResult),
+}
 
-    pub fn parse_target(self, line: &str) -> Option<&str> {
+impl<F> ActionTask for BackgroundTask<F>
+where
+    F: 'static + Send + FnOnce() -> ActionResult,
+{
+    fn poll(&mut self, _executor: &mut Executor) -> Poll<ActionResult> {
+        if let BackgroundTask::Waiting(run) = self {
+            if let Some(run) = run.take() {
+                let (sender, receiver) = mpsc::channel();
+                thread::spawn(move || {
+                    let _ = sender.send(run());
+                });
+                *self = BackgroundTask::Running(receiver);
+            }
+        }
+        if let BackgroundTask::Running(receiver) = self {
+            let result = match receiver.try_recv() {
+                Ok(result) => result,
+                Err(TryRecvError::Empty) => return Poll::Pending,
+                Err(TryRecvError::Disconnected) => ActionResult::from_err(
+                    String::from("the background task panicked"),
+                ),
+            };
+            *self = BackgroundTask::Done(result);
+        }
         match self {
-            Self::Log | Self::LogCount => line.split('\x1e').nth(1),
-            Self::ListBranches => Some(line),
-            _ => None,
+            BackgroundTask::Done(result) => Poll::Ready(result.clone()),
+            _ => Poll::Pending,
         }
     }
 }
 
-pub trait ActionTask: Send {
-    fn poll(&mut self, executor: &mut Executor) -> Poll<ActionResult>;
+/// Like `lazy` but runs `run` on its own thread so in-process work doesn't
+/// block the ui
+pub fn background<F>(run: F) -> Box<dyn ActionTask>
+where
+    F: 'static + Send + FnOnce() -> ActionResult,
+{
+    Box::new(BackgroundTask::Waiting(Some(run)))
 }
 
-pub enum CommandTask {
-    Waiting(Command),
-    Running(AsyncChild),
+struct MapOutputTask<F>
+where
+    F: 'static + Send + FnMut(String) -> String,
+{
+    task: Box<dyn ActionTask>,
+    map: F,
 }
 
-impl ActionTask for CommandTask {
+impl<F> ActionTask for MapOutputTask<F>
+where
+    F: 'static + Send + FnMut(String) -> String,
+{
     fn poll(&mut self, executor: &mut Executor) -> Poll<ActionResult> {
-        match self {
-            CommandTask::Waiting(command) => {
-                let child = command
-                    .stdin(Stdio::null())
-                    .stdout(Stdio::piped())
-                    .stderr(Stdio::piped())
-                    .spawn();
-                match child {
-                    Ok(child) => {
-                        let async_child = executor.run_child_async(child);
-                        *self = CommandTask::Running(async_child);
-                        Poll::Pending
-                    }
-                    Err(e) => {
-                        Poll::Ready(ActionResult::from_err(e.to_string()))
-                    }
-                }
+        match self.task.poll(executor) {
+            Poll::Ready(result) if result.success => {
+                Poll::Ready(ActionResult::from_ok((self.map)(result.output)))
             }
-            CommandTask::Running(child) => child.poll(),
+            poll => poll,
         }
     }
 }
 
+/// Transforms the output of `task` with `map` only if it succeeds
+pub fn map_output<F>(task: Box<dyn ActionTask>, map: F) -> Box<dyn ActionTask>
+where
+    F: 'static + Send + FnMut(String) -> String,
+{
+    Box::new(MapOutputTask { task, map })
+}
+
 pub fn task_vec() -> Vec<Box<dyn ActionTask>> {
     Vec::new()
 }
@@ -183,6 +208,16 @@ pub fn serial(tasks: Vec<Box<dyn ActionTask>>) -> Box<dyn ActionTask> {
     Box::new(SerialTasks {
         tasks,
         cached_results: Vec::new(),
+        stop_on_failure: false,
+    })
+}
+
+/// Like `serial` but stops at the first task that fails
+pub fn chain(tasks: Vec<Box<dyn ActionTask>>) -> Box<dyn ActionTask> {
+    Box::new(SerialTasks {
+        tasks,
+        cached_results: Vec::new(),
+        stop_on_failure: true,
     })
 }
 
@@ -219,16 +254,24 @@ impl ActionTask for ParallelTasks {
 struct SerialTasks {
     tasks: Vec<Box<dyn ActionTask>>,
     cached_results: Vec<ActionResult>,
+    stop_on_failure: bool,
 }
 
 impl ActionTask for SerialTasks {
     fn poll(&mut self, executor: &mut Executor) -> Poll<ActionResult> {
-        match self.tasks[self.cached_results.len()].poll(executor) {
-            Poll::Ready(result) => self.cached_results.push(result),
+        let failed = match self.tasks[self.cached_results.len()].poll(executor)
+        {
+            Poll::Ready(result) => {
+                let failed = !result.success;
+                self.cached_results.push(result);
+                failed
+            }
             Poll::Pending => return Poll::Pending,
-        }
+        };
 
-        if self.cached_results.len() == self.tasks.len() {
+        if self.cached_results.len() == self.tasks.len()
+            || (failed && self.stop_on_failure)
+        {
             Poll::Ready(aggregate_results(self.cached_results.drain(..)))
         } else {
             Poll::Pending
@@ -242,8 +285,10 @@ where
 {
     let mut all_success = true;
     let mut aggregated = String::new();
+    let mut error = None;
     for result in iter {
         all_success = all_success && result.success;
+        error = error.or(result.error);
         let result = result.output;
         aggregated.push('\n');
         aggregated.push_str(&result[..]);
@@ -251,5 +296,6 @@ where
     ActionResult {
         success: all_success,
         output: aggregated,
+        error,
     }
 }
@@ -0,0 +1,262 @@
+use verco::version_control_actions::Capability;
+
+#[derive(PartialEq, Eq, Hash, Clone, Copy)]
+pub enum ActionKind {
+    Quit,
+    Help,
+    Status,
+    Log,
+    LogCount,
+    Reflog,
+    CurrentFullRevision,
+    CurrentDiffAll,
+    CurrentDiffSelected,
+    RevisionChanges,
+    RevisionDiffAll,
+    RevisionDiffSelected,
+    Blame,
+    BlameParent,
+    CommitAll,
+    CommitSelected,
+    CommitHunks,
+    Amend,
+    Update,
+    Merge,
+    Rebase,
+    RebaseContinue,
+    RebaseSkip,
+    RebaseAbort,
+    EditHistory,
+    CherryPick,
+    CherryPickContinue,
+    CherryPickAbort,
+    BisectStart,
+    BisectGood,
+    BisectBad,
+    BisectSkip,
+    BisectRun,
+    BisectReset,
+    RevertAll,
+    RevertSelected,
+    Backout,
+    UnresolvedConflicts,
+    MergeTakingOther,
+    MergeTakingLocal,
+    Fetch,
+    Pull,
+    Push,
+    ListRemotes,
+    NewRemote,
+    RenameRemote,
+    RemoveRemote,
+    SetRemoteUrl,
+    ListTags,
+    NewTag,
+    DeleteTag,
+    ListSubmodules,
+    InitSubmodules,
+    UpdateSubmodules,
+    SyncSubmodules,
+    EnterSubmodule,
+    LeaveSubmodule,
+    ListWorktrees,
+    NewWorktree,
+    RemoveWorktree,
+    PruneWorktrees,
+    SwitchWorktree,
+    ListBranches,
+    NewBranch,
+    NewBranchAt,
+    DeleteBranch,
+    ListStashes,
+    StashAll,
+    StashSelected,
+    ShowStash,
+    ApplyStash,
+    PopStash,
+    DropStash,
+    CustomAction,
+}
+
+impl ActionKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Quit => "quit",
+            Self::Help => "help",
+            Self::Status => "status",
+            Self::Log => "log",
+            Self::LogCount => "log count",
+            Self::Reflog => "reflog",
+            Self::CurrentFullRevision => "revision full contents",
+            Self::CurrentDiffAll => "current diff all",
+            Self::CurrentDiffSelected => "current diff selected",
+            Self::RevisionChanges => "revision changes",
+            Self::RevisionDiffAll => "revision diff all",
+            Self::RevisionDiffSelected => "revision diff selected",
+            Self::Blame => "blame",
+            Self::BlameParent => "blame parent",
+            Self::CommitAll => "commit all",
+            Self::CommitSelected => "commit selected",
+            Self::CommitHunks => "commit hunks",
+            Self::Amend => "amend last commit",
+            Self::Update => "update/checkout",
+            Self::Merge => "merge",
+            Self::Rebase => "rebase",
+            Self::RebaseContinue => "rebase continue",
+            Self::RebaseSkip => "rebase skip",
+            Self::RebaseAbort => "rebase abort",
+            Self::EditHistory => "edit history",
+            Self::CherryPick => "cherry-pick",
+            Self::CherryPickContinue => "cherry-pick continue",
+            Self::CherryPickAbort => "cherry-pick abort",
+            Self::BisectStart => "bisect start",
+            Self::BisectGood => "bisect good",
+            Self::BisectBad => "bisect bad",
+            Self::BisectSkip => "bisect skip",
+            Self::BisectRun => "bisect run",
+            Self::BisectReset => "bisect reset",
+            Self::RevertAll => "revert all",
+            Self::RevertSelected => "revert selected",
+            Self::Backout => "back out revision",
+            Self::UnresolvedConflicts => "unresolved conflicts",
+            Self::MergeTakingOther => "merge taking other",
+            Self::MergeTakingLocal => "merge taking local",
+            Self::Fetch => "fetch",
+            Self::Pull => "pull",
+            Self::Push => "push",
+            Self::ListRemotes => "list remotes",
+            Self::NewRemote => "new remote",
+            Self::RenameRemote => "rename remote",
+            Self::RemoveRemote => "remove remote",
+            Self::SetRemoteUrl => "set remote url",
+            Self::ListTags => "list tags",
+            Self::NewTag => "new tag",
+            Self::DeleteTag => "delete tag",
+            Self::ListSubmodules => "list submodules",
+            Self::InitSubmodules => "init submodules",
+            Self::UpdateSubmodules => "update submodules",
+            Self::SyncSubmodules => "sync submodules",
+            Self::EnterSubmodule => "enter submodule",
+            Self::LeaveSubmodule => "leave submodule",
+            Self::ListWorktrees => "list worktrees",
+            Self::NewWorktree => "new worktree",
+            Self::RemoveWorktree => "remove worktree",
+            Self::PruneWorktrees => "prune worktrees",
+            Self::SwitchWorktree => "switch worktree",
+            Self::ListBranches => "list branches",
+            Self::NewBranch => "new branch",
+            Self::NewBranchAt => "new branch at revision",
+            Self::DeleteBranch => "delete branch",
+            Self::ListStashes => "list stashes",
+            Self::StashAll => "stash all",
+            Self::StashSelected => "stash selected",
+            Self::ShowStash => "show stash",
+            Self::ApplyStash => "apply stash",
+            Self::PopStash => "pop stash",
+            Self::DropStash => "drop stash",
+            Self::CustomAction => "custom action",
+        }
+    }
+
+    pub fn can_select_output(self) -> bool {
+        matches!(
+            self,
+            Self::Log
+                | Self::LogCount
+                | Self::Reflog
+                | Self::Blame
+                | Self::BlameParent
+                | Self::ListRemotes
+                | Self::ListTags
+                | Self::ListSubmodules
+                | Self::ListWorktrees
+                | Self::ListBranches
+                | Self::ListStashes
+        )
+    }
+
+    pub fn is_log(self) -> bool {
+        matches!(self, Self::Log | Self::LogCount)
+    }
+
+    pub fn is_blame(self) -> bool {
+        matches!(self, Self::Blame | Self::BlameParent)
+    }
+
+    /// The backend capability this action depends on, if any
+    pub fn capability(self) -> Option<Capability> {
+        match self {
+            Self::Blame | Self::BlameParent => Some(Capability::Blame),
+            Self::CommitHunks => Some(Capability::CommitHunks),
+            Self::Amend => Some(Capability::Amend),
+            Self::Merge => Some(Capability::Merge),
+            Self::Rebase
+            | Self::RebaseContinue
+            | Self::RebaseSkip
+            | Self::RebaseAbort => Some(Capability::Rebase),
+            Self::EditHistory => Some(Capability::EditHistory),
+            Self::CherryPick
+            | Self::CherryPickContinue
+            | Self::CherryPickAbort => Some(Capability::CherryPick),
+            Self::Backout => Some(Capability::Backout),
+            Self::BisectStart
+            | Self::BisectGood
+            | Self::BisectBad
+            | Self::BisectSkip
+            | Self::BisectRun
+            | Self::BisectReset => Some(Capability::Bisect),
+            Self::MergeTakingOther => Some(Capability::TakeOther),
+            Self::MergeTakingLocal => Some(Capability::TakeLocal),
+            Self::Fetch => Some(Capability::Fetch),
+            Self::Pull => Some(Capability::Pull),
+            Self::Push => Some(Capability::Push),
+            Self::ListRemotes
+            | Self::NewRemote
+            | Self::RenameRemote
+            | Self::RemoveRemote
+            | Self::SetRemoteUrl => Some(Capability::Remotes),
+            Self::ListTags => Some(Capability::ListTags),
+            Self::NewTag => Some(Capability::CreateTag),
+            Self::DeleteTag => Some(Capability::DeleteTag),
+            Self::ListSubmodules
+            | Self::InitSubmodules
+            | Self::UpdateSubmodules
+            | Self::SyncSubmodules
+            | Self::EnterSubmodule
+            | Self::LeaveSubmodule => Some(Capability::Submodules),
+            Self::ListWorktrees
+            | Self::NewWorktree
+            | Self::RemoveWorktree
+            | Self::PruneWorktrees
+            | Self::SwitchWorktree => Some(Capability::Worktrees),
+            Self::ListBranches => Some(Capability::ListBranches),
+            Self::NewBranch | Self::NewBranchAt => {
+                Some(Capability::CreateBranch)
+            }
+            Self::Reflog => Some(Capability::Reflog),
+            Self::DeleteBranch => Some(Capability::CloseBranch),
+            Self::ListStashes
+            | Self::StashAll
+            | Self::StashSelected
+            | Self::ShowStash
+            | Self::ApplyStash
+            | Self::PopStash
+            | Self::DropStash => Some(Capability::Stash),
+            _ => None,
+        }
+    }
+
+    pub fn parse_target(self, line: &str) -> Option<&str> {
+        match self {
+            Self::ListBranches => line.split_whitespace().next(),
+            // the branch or revision is the last word so paths keep spaces
+            Self::ListWorktrees => line.rsplit_once(' ').map(|(path, _)| path),
+            Self::ListSubmodules => line.rsplitn(4, ' ').nth(3),
+            Self::ListStashes
+            | Self::Reflog
+            | Self::ListRemotes
+            | Self::ListTags => line.split_whitespace().next(),
+            _ => None,
+        }
+    }
+}
@@ -1,12 +1,13 @@
 use std::{collections::HashMap, task::Poll};
 
-use crate::{
-    action::{ActionKind, ActionResult, ActionTask},
+use verco::{
+    action::{ActionResult, ActionTask},
     async_process::Executor,
-    custom_actions::CustomAction,
     version_control_actions::VersionControlActions,
 };
 
+use crate::{action_kind::ActionKind, custom_actions::CustomAction};
+
 pub struct ActionFuture {
     pub kind: ActionKind,
     pub task: Box<dyn 'static + ActionTask>,
@@ -15,8 +16,13 @@ pub struct ActionFuture {
 pub struct Application {
     pub version_control: Box<dyn 'static + VersionControlActions>,
     pub custom_actions: Vec<CustomAction>,
+    /// The upstream shown in the header as of the last finished action
+    pub upstream: Option<String>,
 
     executor: Executor,
+    upstream_task: Option<Box<dyn 'static + ActionTask>>,
+    /// Whether an action finished since `upstream_task` started
+    upstream_outdated: bool,
     pending_actions: Vec<ActionFuture>,
     action_results: HashMap<ActionKind, ActionResult>,
 }
@@ -29,7 +35,10 @@ impl Application {
         Self {
             version_control,
             custom_actions,
+            upstream: None,
             executor: Executor::new(2),
+            upstream_task: None,
+            upstream_outdated: true,
             pending_actions: Vec::new(),
             action_results: HashMap::new(),
         }
@@ -39,6 +48,7 @@ impl Application {
         static EMPTY_ACTION_RESULT: ActionResult = ActionResult {
             success: true,
             output: String::new(),
+            error: None,
         };
 
         match self.action_results.get(&kind) {
@@ -47,6 +57,13 @@ impl Application {
         }
     }
 
+    /// Forgets every finished result, like when moving to another root
+    pub fn clear_action_results(&mut self) {
+        self.action_results.clear();
+        self.upstream = None;
+        self.upstream_outdated = true;
+    }
+
     pub fn poll_and_check_action(&mut self, kind: ActionKind) -> bool {
         let mut just_finished = false;
         for i in (0..self.pending_actions.len()).rev() {
@@ -58,12 +75,46 @@ impl Application {
                     just_finished = true;
                 }
                 self.action_results.insert(action.kind, result);
+
+                // any finished action might have committed, fetched or
+                // switched branches
+                self.upstream_outdated = true;
             }
         }
 
         just_finished
     }
 
+    /// Polls the refresh of `upstream`, starting a new one if it is
+    /// outdated, and returns whether it changed
+    pub fn poll_upstream(&mut self) -> bool {
+        // a running task can't be dropped so an outdated one is let finish
+        // and its result ignored
+        let task = match &mut self.upstream_task {
+            Some(task) => task,
+            None if self.upstream_outdated => {
+                self.upstream_outdated = false;
+                self.upstream_task.insert(self.version_control.upstream())
+            }
+            None => return false,
+        };
+        let result = match task.poll(&mut self.executor) {
+            Poll::Ready(result) => result,
+            Poll::Pending => return false,
+        };
+        self.upstream_task = None;
+        if self.upstream_outdated {
+            return false;
+        }
+
+        let upstream = Some(result.output.trim())
+            .filter(|u| result.success && !u.is_empty())
+            .map(String::from);
+        let changed = upstream != self.upstream;
+        self.upstream = upstream;
+        changed
+    }
+
     pub fn run_action(&mut self, action: ActionFuture) {
         for i in (0..self.pending_actions.len()).rev() {
             if self.pending_actions[i].kind == action.kind {
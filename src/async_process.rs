@@ -7,8 +7,10 @@ use std::{
     thread::{self, JoinHandle},
 };
 
+use crate::vcs_error::VcsError;
+
 struct ExecutorThread {
-    pub handle: JoinHandle<()>,
+    pub _handle: JoinHandle<()>,
     pub async_child_executor_sender: Sender<AsyncChildExecutor>,
 }
 
@@ -34,7 +36,7 @@ impl Executor {
                 }
             });
             thread_pool.push(ExecutorThread {
-                handle,
+                _handle: handle,
                 async_child_executor_sender,
             });
         }
@@ -66,6 +68,8 @@ impl Executor {
 pub struct ChildOutput {
     pub success: bool,
     pub output: String,
+    /// Why the child failed, if it did and the reason is known
+    pub error: Option<VcsError>,
 }
 
 impl ChildOutput {
@@ -73,6 +77,7 @@ impl ChildOutput {
         Self {
             success: true,
             output,
+            error: None,
         }
     }
 
@@ -80,34 +85,49 @@ impl ChildOutput {
         Self {
             success: false,
             output,
+            error: None,
+        }
+    }
+
+    pub fn from_error(error: VcsError) -> Self {
+        Self {
+            success: false,
+            output: error.to_string(),
+            error: Some(error),
         }
     }
 
     pub fn from_child(child: Child) -> Self {
-        let mut success;
         let output = match child.wait_with_output() {
-            Ok(output) => {
-                success = output.status.success();
-                let bytes = if success {
-                    output.stdout
-                } else {
-                    output.stderr
-                };
-                match String::from_utf8(bytes) {
-                    Ok(output) => output,
-                    Err(error) => {
-                        success = false;
-                        error.to_string()
-                    }
-                }
-            }
+            Ok(output) => output,
             Err(error) => {
-                success = false;
-                error.to_string()
+                return Self::from_error(VcsError::CommandFailed {
+                    code: None,
+                    stderr: error.to_string(),
+                })
             }
         };
 
-        Self { success, output }
+        let success = output.status.success();
+        // some commands, like `git merge`, report why they failed on stdout
+        let bytes =
+            if success || output.stderr.iter().all(u8::is_ascii_whitespace) {
+                output.stdout
+            } else {
+                output.stderr
+            };
+        match String::from_utf8(bytes) {
+            Ok(text) if success => Self::from_ok(text),
+            Ok(text) => Self {
+                success: false,
+                output: text.clone(),
+                error: Some(VcsError::CommandFailed {
+                    code: output.status.code(),
+                    stderr: text,
+                }),
+            },
+            Err(error) => Self::from_error(VcsError::from_utf8(error)),
+        }
     }
 }
 
@@ -0,0 +1,104 @@
+use crate::log_entry::FIELD_SEPARATOR;
+
+/// The revision that last changed a line of a file
+///
+/// Backends emit one record per line as
+/// `revision US short_revision US author US date US line_number US text`
+/// where `US` is `FIELD_SEPARATOR`.
+#[derive(Clone, Default)]
+pub struct BlameLine {
+    pub revision: String,
+    pub short_revision: String,
+    pub author: String,
+    pub date: String,
+    pub line_number: usize,
+    pub text: String,
+}
+
+impl BlameLine {
+    pub fn parse(line: &str) -> Option<Self> {
+        let mut fields = line.splitn(6, FIELD_SEPARATOR);
+        let mut next = || fields.next();
+
+        Some(Self {
+            revision: String::from(next()?.trim()),
+            short_revision: String::from(next()?.trim()),
+            author: String::from(next()?.trim()),
+            date: String::from(next()?.trim()),
+            line_number: next()?.trim().parse().ok()?,
+            text: String::from(next()?),
+        })
+    }
+
+    pub fn parse_all(output: &str) -> Vec<Self> {
+        output.lines().filter_map(Self::parse).collect()
+    }
+
+    /// Formats the record in the format expected by `parse` for backends
+    /// whose output needs converting
+    pub fn format_record(&self) -> String {
+        let line_number = self.line_number.to_string();
+        let fields = [
+            &self.revision[..],
+            &self.short_revision[..],
+            &self.author[..],
+            &self.date[..],
+            &line_number[..],
+            &self.text[..],
+        ];
+
+        let mut record = String::new();
+        for (i, field) in fields.iter().enumerate() {
+            if i > 0 {
+                record.push(FIELD_SEPARATOR);
+            }
+            record.push_str(field);
+        }
+        record
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_round_trip() {
+        let line = BlameLine {
+            revision: String::from("0123456789abcdef"),
+            short_revision: String::from("0123456"),
+            author: String::from("Alice"),
+            date: String::from("2024-01-02"),
+            line_number: 42,
+            text: String::from("  text\twith a tab"),
+        };
+
+        let parsed = BlameLine::parse(&line.format_record()).unwrap();
+        assert_eq!(parsed.revision, line.revision);
+        assert_eq!(parsed.short_revision, line.short_revision);
+        assert_eq!(parsed.author, line.author);
+        assert_eq!(parsed.date, line.date);
+        assert_eq!(parsed.line_number, 42);
+        assert_eq!(parsed.text, line.text);
+    }
+
+    #[test]
+    fn malformed_records_are_skipped() {
+        let record = BlameLine {
+            line_number: 1,
+            text: String::from("kept"),
+            ..Default::default()
+        }
+        .format_record();
+        let missing_text =
+            ["rev", "r", "a", "d", "2"].join(&FIELD_SEPARATOR.to_string());
+        let bad_line_number = ["rev", "r", "a", "d", "two", "t"]
+            .join(&FIELD_SEPARATOR.to_string());
+
+        let output =
+            [&record[..], &missing_text, &bad_line_number, ""].join("\n");
+        let lines = BlameLine::parse_all(&output);
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0].text, "kept");
+    }
+}
@@ -0,0 +1,63 @@
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader},
+    path::Path,
+};
+
+#[derive(Default, Clone, Copy, PartialEq, Eq)]
+pub enum GitBackend {
+    #[default]
+    Cli,
+    Libgit2,
+}
+
+#[derive(Default)]
+pub struct Config {
+    pub git_backend: GitBackend,
+}
+
+impl Config {
+    /// Loads `.verco/config.txt` from the repository at `root`
+    pub fn load(root: &str) -> Config {
+        Self::try_load(root).unwrap_or_default()
+    }
+
+    fn try_load(root: &str) -> io::Result<Config> {
+        let mut config = Config::default();
+
+        let path = Path::new(root).join(".verco/config.txt");
+        if !path.exists() {
+            return Ok(config);
+        }
+
+        let file = File::open(path)?;
+        let mut reader = BufReader::new(file);
+
+        let mut line = String::new();
+        while next_line(&mut reader, &mut line) {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut it = line.splitn(2, ' ');
+            let key = it.next().unwrap_or("");
+            let value = it.next().unwrap_or("").trim();
+
+            if key == "git_backend" {
+                match value {
+                    "cli" => config.git_backend = GitBackend::Cli,
+                    "libgit2" => config.git_backend = GitBackend::Libgit2,
+                    _ => (),
+                }
+            }
+        }
+
+        Ok(config)
+    }
+}
+
+fn next_line<R: BufRead>(reader: &mut R, line: &mut String) -> bool {
+    line.clear();
+    reader.read_line(line).unwrap_or(0) > 0
+}
@@ -5,7 +5,7 @@ use std::{
     process::Command,
 };
 
-use crate::action::ActionResult;
+use verco::action::ActionResult;
 
 pub struct CustomAction {
     pub shortcut: String,
@@ -15,7 +15,7 @@ pub struct CustomAction {
 
 impl CustomAction {
     pub fn load_custom_actions() -> Vec<CustomAction> {
-        Self::try_load_custom_actions().unwrap_or(Vec::new())
+        Self::try_load_custom_actions().unwrap_or_default()
     }
 
     fn try_load_custom_actions() -> io::Result<Vec<CustomAction>> {
@@ -32,7 +32,7 @@ impl CustomAction {
         let mut line = String::new();
         while next_line(&mut reader, &mut line) {
             let line = line.trim();
-            if line.len() == 0 {
+            if line.is_empty() {
                 continue;
             }
 
@@ -0,0 +1,902 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    action::{
+        chain, lazy, map_output, parallel, serial, task_vec, ActionResult,
+        ActionTask,
+    },
+    history_edit::PlanEntry,
+    log_entry::LogEntry,
+    patch::FilePatch,
+    select::{Entry, State},
+    vcs_error::{ErrorMessages, VcsError},
+    version_control_actions::{
+        task, unsupported, unsupported_error, Capability, Support,
+        VersionControlActions,
+    },
+};
+
+/// Files that mark the root of a fossil checkout
+pub const CHECKOUT_FILES: &[&str] = &[".fslckout", "_FOSSIL_"];
+
+/// Where the message of a backout stopped on conflicts is kept until it is
+/// committed, a dotfile so that `fossil extras` and `fossil clean` skip it
+const BACKOUT_MESSAGE_FILE: &str = ".verco-backout-message";
+
+fn str_to_state(s: &str) -> State {
+    match s {
+        "EDITED"
+        | "CHANGED"
+        | "MODIFIED"
+        | "UPDATED_BY_MERGE"
+        | "UPDATED_BY_INTEGRATE"
+        | "EXECUTABLE"
+        | "SYMLINK"
+        | "UNEXEC"
+        | "UNLINK" => State::Modified,
+        "ADDED" | "ADDED_BY_MERGE" | "ADDED_BY_INTEGRATE" => State::Added,
+        "DELETED" | "REMOVED" => State::Deleted,
+        "RENAMED" => State::Renamed,
+        "MISSING" | "NOT_A_FILE" => State::Missing,
+        "CONFLICT" => State::Unmerged,
+        _ => State::Unmodified,
+    }
+}
+
+/// Parses lines in the `STATUS    path` format of `fossil changes`
+fn parse_changes(output: &str) -> Vec<Entry> {
+    output
+        .lines()
+        .map(|e| e.trim())
+        .filter_map(|e| {
+            let index = e.find(char::is_whitespace)?;
+            let (state, filename) = e.split_at(index);
+            Some(Entry {
+                filename: String::from(filename.trim()),
+                selected: false,
+                state: str_to_state(state),
+            })
+        })
+        .collect()
+}
+
+/// Completes the records output by the custom `fossil timeline` format
+/// used by `log`
+fn format_timeline(output: String) -> String {
+    let mut log = String::new();
+    for mut entry in output.lines().filter_map(LogEntry::parse) {
+        entry.graph = String::from("o");
+        entry.date.truncate(10);
+        log.push_str(&entry.format_record());
+        log.push('\n');
+    }
+    log
+}
+
+const ERROR_MESSAGES: ErrorMessages = ErrorMessages {
+    not_a_repository: &["not within an open check"],
+    merge_conflict: &["merge conflicts", "unresolved merge conflicts"],
+    auth_failure: &["login failed", "not authorized"],
+};
+
+/// Joins the entries of `fossil stash list`, whose comments are on the
+/// lines below them, into one line per stash starting with its id
+fn format_stash_list(output: String) -> String {
+    let mut stashes: Vec<String> = Vec::new();
+    for line in output.lines().map(|l| l.trim()).filter(|l| !l.is_empty()) {
+        let is_header = line
+            .split_once(':')
+            .map(|(id, _)| id.chars().all(|c| c.is_ascii_digit()))
+            .unwrap_or(false);
+
+        match stashes.last_mut() {
+            Some(stash) if !is_header => {
+                stash.push(' ');
+                stash.push_str(line);
+            }
+            _ => stashes.push(line.replacen(':', "", 1)),
+        }
+    }
+
+    let mut list = stashes.join("\n");
+    list.push('\n');
+    list
+}
+
+pub struct FossilActions {
+    pub current_dir: String,
+}
+
+impl FossilActions {
+    fn backout_message_path(&self) -> PathBuf {
+        Path::new(&self.current_dir).join(BACKOUT_MESSAGE_FILE)
+    }
+}
+
+impl VersionControlActions for FossilActions {
+    fn executable_name(&self) -> &'static str {
+        "fossil"
+    }
+
+    fn current_dir(&self) -> &str {
+        &self.current_dir[..]
+    }
+
+    fn classify_error(&self, error: VcsError) -> VcsError {
+        error.classify(&ERROR_MESSAGES)
+    }
+
+    fn support(&self, capability: Capability) -> Support {
+        match capability {
+            Capability::TakeOther | Capability::TakeLocal => {
+                Support::Unsupported("fossil leaves conflicts to be edited")
+            }
+            Capability::CommitHunks => {
+                Support::Unsupported("fossil can only commit whole files")
+            }
+            Capability::Amend => Support::Unsupported(
+                "check-ins are immutable and fossil amend only annotates them",
+            ),
+            Capability::Blame => {
+                Support::Unsupported("verco can't read fossil annotations yet")
+            }
+            Capability::ListTags => Support::Differs("only lists the tag names"),
+            Capability::CreateTag => {
+                Support::Differs("stores the message as the tag's value")
+            }
+            Capability::Remotes => Support::Unsupported(
+                "fossil syncs with the single url set with fossil remote",
+            ),
+            Capability::Submodules => {
+                Support::Unsupported("fossil has no submodules")
+            }
+            Capability::Worktrees => Support::Unsupported(
+                "checkouts of the same repository are independent",
+            ),
+            Capability::Reflog => Support::Unsupported(
+                "fossil keeps no history of checkouts, see fossil undo",
+            ),
+            Capability::Bisect => Support::Differs(
+                "can't run a test command and the header doesn't show the bisect state",
+            ),
+            Capability::CherryPick => Support::Differs(
+                "leaves the changes uncommitted and conflicts to be edited",
+            ),
+            Capability::Backout => {
+                Support::Differs("leaves conflicts to be edited")
+            }
+            Capability::Rebase | Capability::EditHistory => {
+                Support::Unsupported("fossil deliberately has no rebase")
+            }
+            Capability::Fetch => Support::Differs(
+                "runs fossil pull which does not update the working directory",
+            ),
+            _ => Support::Supported,
+        }
+    }
+
+    fn set_root(&mut self) -> Result<(), VcsError> {
+        let root = Path::new(&self.current_dir)
+            .ancestors()
+            .find(|d| CHECKOUT_FILES.iter().any(|f| d.join(f).is_file()));
+
+        match root.and_then(|r| r.to_str()) {
+            Some(root) => {
+                self.current_dir = root.to_owned();
+                Ok(())
+            }
+            None => Err(VcsError::NotARepository(String::from(
+                "not within a fossil checkout",
+            ))),
+        }
+    }
+
+    fn get_root(&self) -> &str {
+        &self.current_dir[..]
+    }
+
+    fn switch_root(&mut self, _dir: &str) -> Result<(), VcsError> {
+        Err(unsupported_error(self, "switching worktrees"))
+    }
+
+    fn get_current_changed_files(&self) -> Result<Vec<Entry>, VcsError> {
+        let changes = self.run_command(self.command().arg("changes"))?;
+        let extras = self.run_command(self.command().arg("extras"))?;
+
+        let mut files = parse_changes(&changes);
+        files.extend(
+            extras
+                .lines()
+                .map(|e| e.trim())
+                .filter(|e| !e.is_empty())
+                .map(|e| Entry {
+                    filename: String::from(e),
+                    selected: false,
+                    state: State::Untracked,
+                }),
+        );
+        Ok(files)
+    }
+
+    fn get_tracked_files(&self) -> Result<Vec<Entry>, VcsError> {
+        let output = self.run_command(self.command().arg("ls"))?;
+        let files = output
+            .lines()
+            .filter(|f| !f.is_empty())
+            .map(|f| Entry {
+                filename: String::from(f),
+                selected: false,
+                state: State::Clean,
+            })
+            .collect();
+        Ok(files)
+    }
+
+    fn get_revision_changed_files(
+        &self,
+        target: &str,
+    ) -> Result<Vec<Entry>, VcsError> {
+        let output = self.run_command(
+            self.command()
+                .arg("diff")
+                .arg("--brief")
+                .arg("--checkin")
+                .arg(target),
+        )?;
+        Ok(parse_changes(&output))
+    }
+
+    fn version(&self) -> Result<String, VcsError> {
+        self.run_command(self.command().arg("version"))
+    }
+
+    fn status(&self) -> Box<dyn ActionTask> {
+        let mut tasks = task_vec();
+        tasks.push(task(self, |command| {
+            command.arg("status");
+        }));
+        tasks.push(task(self, |command| {
+            command.arg("extras");
+        }));
+        parallel(tasks)
+    }
+
+    fn reflog(&self) -> Box<dyn ActionTask> {
+        unsupported(self, "listing previous checkouts")
+    }
+
+    fn current_export(&self) -> Box<dyn ActionTask> {
+        let mut tasks = task_vec();
+        tasks.push(task(self, |command| {
+            command.args(["info", "current"]);
+        }));
+        tasks.push(task(self, |command| {
+            command.args(["diff", "--checkin", "current"]);
+        }));
+        serial(tasks)
+    }
+
+    fn log(&self, count: usize) -> Box<dyn ActionTask> {
+        let timeline = task(self, |command| {
+            let count_str = format!("{}", count);
+            command
+                .arg("timeline")
+                .arg("-t")
+                .arg("ci")
+                .arg("-W")
+                .arg("0")
+                .arg("-n")
+                .arg(&count_str)
+                .arg("-F")
+                .arg("\x1e%H\x1f%h\x1f\x1f%a\x1f%d\x1f%t\x1f%b\x1f%c");
+        });
+        map_output(timeline, format_timeline)
+    }
+
+    fn blame(
+        &self,
+        _filename: &str,
+        _revision: Option<&str>,
+    ) -> Box<dyn ActionTask> {
+        unsupported(self, "blame")
+    }
+
+    fn get_parent_revision(&self, _revision: &str) -> Result<String, VcsError> {
+        Err(unsupported_error(self, "blame"))
+    }
+
+    fn current_diff_all(&self) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.arg("diff");
+        })
+    }
+
+    fn current_diff_selected(&self, entries: &[Entry]) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.arg("diff");
+            for e in entries.iter().filter(|e| e.selected) {
+                command.arg(&e.filename);
+            }
+        })
+    }
+
+    fn revision_changes(&self, target: &str) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command
+                .arg("diff")
+                .arg("--brief")
+                .arg("--checkin")
+                .arg(target);
+        })
+    }
+
+    fn revision_diff_all(&self, target: &str) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.arg("diff").arg("--checkin").arg(target);
+        })
+    }
+
+    fn revision_diff_selected(
+        &self,
+        target: &str,
+        entries: &[Entry],
+    ) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.arg("diff").arg("--checkin").arg(target);
+            for e in entries.iter().filter(|e| e.selected) {
+                command.arg(&e.filename);
+            }
+        })
+    }
+
+    fn commit_all(&self, message: &str) -> Box<dyn ActionTask> {
+        let mut tasks = task_vec();
+        tasks.push(task(self, |command| {
+            command.arg("addremove");
+        }));
+        tasks.push(task(self, |command| {
+            command
+                .arg("commit")
+                .arg("--no-warnings")
+                .arg("-m")
+                .arg(message);
+        }));
+        serial(tasks)
+    }
+
+    fn commit_selected(
+        &self,
+        message: &str,
+        entries: &[Entry],
+    ) -> Box<dyn ActionTask> {
+        let mut tasks = task_vec();
+        let mut files_to_commit = Vec::new();
+        for e in entries.iter().filter(|e| e.selected) {
+            match e.state {
+                State::Missing => tasks.push(task(self, |command| {
+                    command.arg("rm").arg(&e.filename);
+                })),
+                State::Untracked => tasks.push(task(self, |command| {
+                    command.arg("add").arg(&e.filename);
+                })),
+                _ => (),
+            }
+            files_to_commit.push(&e.filename);
+        }
+        tasks.push(task(self, |command| {
+            command
+                .arg("commit")
+                .arg("--no-warnings")
+                .arg("-m")
+                .arg(message);
+            for file in files_to_commit {
+                command.arg(file);
+            }
+        }));
+        serial(tasks)
+    }
+
+    fn get_current_patch(
+        &self,
+        _entries: &[Entry],
+    ) -> Result<Vec<FilePatch>, VcsError> {
+        Err(unsupported_error(self, "committing hunks"))
+    }
+
+    fn commit_hunks(
+        &self,
+        _message: &str,
+        _files: &[FilePatch],
+    ) -> Box<dyn ActionTask> {
+        unsupported(self, "committing hunks")
+    }
+
+    fn get_last_commit_message(&self) -> Result<String, VcsError> {
+        Err(unsupported_error(self, "amending commits"))
+    }
+
+    fn is_last_commit_published(&self) -> Result<bool, VcsError> {
+        Err(unsupported_error(self, "amending commits"))
+    }
+
+    fn amend(&self, _message: &str, _entries: &[Entry]) -> Box<dyn ActionTask> {
+        unsupported(self, "amending commits")
+    }
+
+    fn revert_all(&self) -> Box<dyn ActionTask> {
+        let mut tasks = task_vec();
+        tasks.push(task(self, |command| {
+            command.arg("revert");
+        }));
+        tasks.push(task(self, |command| {
+            command.args(["clean", "--force"]);
+        }));
+        serial(tasks)
+    }
+
+    fn revert_selected(&self, entries: &[Entry]) -> Box<dyn ActionTask> {
+        let mut tasks = task_vec();
+        let mut files_to_revert = Vec::new();
+        for e in entries.iter().filter(|e| e.selected) {
+            match e.state {
+                State::Untracked => tasks.push(task(self, |command| {
+                    command.arg("clean").arg("--force").arg(&e.filename);
+                })),
+                _ => files_to_revert.push(&e.filename),
+            }
+        }
+        if !files_to_revert.is_empty() {
+            tasks.push(task(self, |command| {
+                command.arg("revert");
+                for file in files_to_revert {
+                    command.arg(file);
+                }
+            }));
+        }
+        parallel(tasks)
+    }
+
+    fn update(&self, target: &str) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.arg("update").arg(target);
+        })
+    }
+
+    fn merge(&self, target: &str) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.arg("merge").arg(target);
+        })
+    }
+
+    fn rebase(&self, _target: &str) -> Box<dyn ActionTask> {
+        unsupported(self, "rebasing")
+    }
+
+    fn rebase_continue(&self) -> Box<dyn ActionTask> {
+        unsupported(self, "rebasing")
+    }
+
+    fn rebase_skip(&self) -> Box<dyn ActionTask> {
+        unsupported(self, "rebasing")
+    }
+
+    fn rebase_abort(&self) -> Box<dyn ActionTask> {
+        unsupported(self, "rebasing")
+    }
+
+    fn cherry_pick(&self, revisions: &[String]) -> Box<dyn ActionTask> {
+        let mut tasks = task_vec();
+        for revision in revisions {
+            tasks.push(task(self, |command| {
+                command.args(["merge", "--cherrypick"]).arg(revision);
+            }));
+        }
+        chain(tasks)
+    }
+
+    fn cherry_pick_continue(&self) -> Box<dyn ActionTask> {
+        unsupported(self, "continuing a cherry-pick")
+    }
+
+    fn cherry_pick_abort(&self) -> Box<dyn ActionTask> {
+        unsupported(self, "aborting a cherry-pick")
+    }
+
+    fn backout(&self, revision: &str, message: &str) -> Box<dyn ActionTask> {
+        let path = self.backout_message_path();
+        let pending_message = format!("{}\n", message);
+        let mut tasks = task_vec();
+        tasks.push(lazy(move || match fs::write(path, pending_message) {
+            Ok(()) => ActionResult::from_ok(String::new()),
+            Err(error) => ActionResult::from_err(format!(
+                "could not save the backout message: {}",
+                error
+            )),
+        }));
+        tasks.push(task(self, |command| {
+            command.args(["merge", "--backout"]).arg(revision);
+        }));
+        tasks.push(task(self, |command| {
+            command.arg("commit").arg("-m").arg(message);
+        }));
+        let path = self.backout_message_path();
+        tasks.push(lazy(move || {
+            let _ = fs::remove_file(path);
+            ActionResult::from_ok(String::new())
+        }));
+        chain(tasks)
+    }
+
+    fn is_backout_in_progress(&self) -> bool {
+        // the merge is only looked up when a backout left its message
+        // behind, and committing or reverting forgets it
+        self.backout_message_path().exists()
+            && self
+                .run_command(self.command().arg("status"))
+                .map(|status| status.lines().any(|l| l.starts_with("BACKOUT")))
+                .unwrap_or(false)
+    }
+
+    fn get_backout_message(&self) -> Option<String> {
+        if !self.is_backout_in_progress() {
+            return None;
+        }
+        let message = fs::read_to_string(self.backout_message_path()).ok()?;
+        Some(String::from(message.trim())).filter(|m| !m.is_empty())
+    }
+
+    fn bisect_start(&self, bad: &str, good: &str) -> Box<dyn ActionTask> {
+        let mut tasks = task_vec();
+        tasks.push(task(self, |command| {
+            command.args(["bisect", "reset"]);
+        }));
+        tasks.push(task(self, |command| {
+            command.args(["bisect", "bad", bad]);
+        }));
+        tasks.push(task(self, |command| {
+            command.args(["bisect", "good", good]);
+        }));
+        chain(tasks)
+    }
+
+    fn bisect_good(&self) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.args(["bisect", "good"]);
+        })
+    }
+
+    fn bisect_bad(&self) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.args(["bisect", "bad"]);
+        })
+    }
+
+    fn bisect_skip(&self) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.args(["bisect", "skip"]);
+        })
+    }
+
+    fn bisect_run(
+        &self,
+        _command: &str,
+        _args: &[String],
+    ) -> Box<dyn ActionTask> {
+        unsupported(self, "running a bisect test command")
+    }
+
+    fn bisect_reset(&self) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.args(["bisect", "reset"]);
+        })
+    }
+
+    fn get_history(&self, _base: &str) -> Result<Vec<PlanEntry>, VcsError> {
+        Err(unsupported_error(self, "editing history"))
+    }
+
+    fn edit_history(
+        &self,
+        _base: &str,
+        _plan: &[PlanEntry],
+    ) -> Box<dyn ActionTask> {
+        unsupported(self, "editing history")
+    }
+
+    fn conflicts(&self) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.args(["changes", "--conflict"]);
+        })
+    }
+
+    fn take_other(&self) -> Box<dyn ActionTask> {
+        unsupported(self, "resolving conflicts by taking other")
+    }
+
+    fn take_local(&self) -> Box<dyn ActionTask> {
+        unsupported(self, "resolving conflicts by taking local")
+    }
+
+    fn fetch(&self, _remote: Option<&str>) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.arg("pull");
+        })
+    }
+
+    fn pull(&self, remote: Option<&str>) -> Box<dyn ActionTask> {
+        let mut tasks = task_vec();
+        tasks.push(self.fetch(remote));
+        tasks.push(task(self, |command| {
+            command.arg("update");
+        }));
+        serial(tasks)
+    }
+
+    fn push(&self, _remote: Option<&str>) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.arg("push");
+        })
+    }
+
+    fn list_tags(&self) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.args(["tag", "list"]);
+        })
+    }
+
+    fn get_tags(&self) -> Result<Vec<String>, VcsError> {
+        let output = self.run_command(self.command().args(["tag", "list"]))?;
+        Ok(output.lines().map(String::from).collect())
+    }
+
+    fn create_tag(
+        &self,
+        name: &str,
+        message: Option<&str>,
+        force: bool,
+        _remote: Option<&str>,
+    ) -> Box<dyn ActionTask> {
+        let mut tasks = task_vec();
+        if force {
+            tasks.push(self.delete_tag(name, None));
+        }
+        tasks.push(task(self, |command| {
+            command.arg("tag").arg("add").arg(name).arg("current");
+            if let Some(message) = message {
+                command.arg(message);
+            }
+        }));
+        serial(tasks)
+    }
+
+    fn delete_tag(
+        &self,
+        name: &str,
+        _remote: Option<&str>,
+    ) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command
+                .arg("tag")
+                .arg("cancel")
+                .arg(name)
+                .arg(format!("tag:{}", name));
+        })
+    }
+
+    fn list_submodules(&self) -> Box<dyn ActionTask> {
+        unsupported(self, "submodules")
+    }
+
+    fn init_submodules(&self) -> Box<dyn ActionTask> {
+        unsupported(self, "submodules")
+    }
+
+    fn update_submodules(&self) -> Box<dyn ActionTask> {
+        unsupported(self, "submodules")
+    }
+
+    fn sync_submodules(&self) -> Box<dyn ActionTask> {
+        unsupported(self, "submodules")
+    }
+
+    fn get_superproject_root(&self) -> Result<String, VcsError> {
+        Err(unsupported_error(self, "submodules"))
+    }
+
+    fn list_worktrees(&self) -> Box<dyn ActionTask> {
+        unsupported(self, "worktrees")
+    }
+
+    fn add_worktree(&self, _path: &str, _target: &str) -> Box<dyn ActionTask> {
+        unsupported(self, "worktrees")
+    }
+
+    fn remove_worktree(&self, _path: &str) -> Box<dyn ActionTask> {
+        unsupported(self, "worktrees")
+    }
+
+    fn prune_worktrees(&self) -> Box<dyn ActionTask> {
+        unsupported(self, "worktrees")
+    }
+
+    fn list_remotes(&self) -> Box<dyn ActionTask> {
+        unsupported(self, "remotes")
+    }
+
+    fn add_remote(&self, _name: &str, _url: &str) -> Box<dyn ActionTask> {
+        unsupported(self, "remotes")
+    }
+
+    fn rename_remote(
+        &self,
+        _name: &str,
+        _new_name: &str,
+    ) -> Box<dyn ActionTask> {
+        unsupported(self, "remotes")
+    }
+
+    fn remove_remote(&self, _name: &str) -> Box<dyn ActionTask> {
+        unsupported(self, "remotes")
+    }
+
+    fn set_remote_url(&self, _name: &str, _url: &str) -> Box<dyn ActionTask> {
+        unsupported(self, "remotes")
+    }
+
+    fn list_branches(&self) -> Box<dyn ActionTask> {
+        let list = task(self, |command| {
+            command.args(["branch", "list"]);
+        });
+        map_output(list, |output| {
+            let mut branches = String::new();
+            for branch in output.lines() {
+                branches.push_str(branch.trim_start_matches('*').trim());
+                branches.push('\n');
+            }
+            branches
+        })
+    }
+
+    fn create_branch(
+        &self,
+        name: &str,
+        _remote: Option<&str>,
+    ) -> Box<dyn ActionTask> {
+        let mut tasks = task_vec();
+        tasks.push(task(self, |command| {
+            command.arg("branch").arg("new").arg(name).arg("current");
+        }));
+        tasks.push(self.update(name));
+        serial(tasks)
+    }
+
+    fn create_branch_at(
+        &self,
+        name: &str,
+        revision: &str,
+    ) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.arg("branch").arg("new").arg(name).arg(revision);
+        })
+    }
+
+    fn close_branch(
+        &self,
+        name: &str,
+        _remote: Option<&str>,
+    ) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.arg("branch").arg("close").arg(name);
+        })
+    }
+
+    fn list_stashes(&self) -> Box<dyn ActionTask> {
+        let list = task(self, |command| {
+            command.args(["stash", "list"]);
+        });
+        map_output(list, format_stash_list)
+    }
+
+    fn stash_all(&self, message: &str) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.args(["stash", "save", "-m", message]);
+        })
+    }
+
+    fn stash_selected(
+        &self,
+        message: &str,
+        entries: &[Entry],
+    ) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.args(["stash", "save", "-m", message]);
+            for e in entries.iter().filter(|e| e.selected) {
+                command.arg(&e.filename);
+            }
+        })
+    }
+
+    fn show_stash(&self, name: &str) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.args(["stash", "show"]).arg(name);
+        })
+    }
+
+    fn apply_stash(&self, name: &str) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.args(["stash", "apply"]).arg(name);
+        })
+    }
+
+    fn pop_stash(&self, name: &str) -> Box<dyn ActionTask> {
+        // fossil stash pop only takes the most recent stash
+        let mut tasks = task_vec();
+        tasks.push(task(self, |command| {
+            command.args(["stash", "apply"]).arg(name);
+        }));
+        tasks.push(task(self, |command| {
+            command.args(["stash", "drop"]).arg(name);
+        }));
+        chain(tasks)
+    }
+
+    fn drop_stash(&self, name: &str) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.args(["stash", "drop"]).arg(name);
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn changes_lines_become_entries() {
+        let entries = parse_changes(
+            "EDITED     src/main.c\nADDED      docs/new file.md\n\
+             MISSING    gone.txt\nCONFLICT   both.c\n\n",
+        );
+        let parsed: Vec<_> = entries
+            .iter()
+            .map(|e| (e.filename.as_str(), e.state.clone()))
+            .collect();
+        assert_eq!(parsed.len(), 4);
+        assert_eq!(parsed[0].0, "src/main.c");
+        assert!(matches!(parsed[0].1, State::Modified));
+        assert_eq!(parsed[1].0, "docs/new file.md");
+        assert!(matches!(parsed[1].1, State::Added));
+        assert!(matches!(parsed[2].1, State::Missing));
+        assert!(matches!(parsed[3].1, State::Unmerged));
+    }
+
+    #[test]
+    fn changes_lines_without_a_path_are_skipped() {
+        assert!(parse_changes("EDITED\n   \n").is_empty());
+    }
+
+    #[test]
+    fn stash_comments_are_joined_to_their_entry() {
+        let output = "   2: [5c7a1e2b3d] on 2024-03-01 12:00:00\n\
+                      \x20     fix the parser\n\
+                      \x20     before lunch\n\
+                      \x20  1: [0a1b2c3d4e] on 2024-02-01 08:30:00\n";
+        assert_eq!(
+            format_stash_list(String::from(output)),
+            "2 [5c7a1e2b3d] on 2024-03-01 12:00:00 fix the parser before lunch\n\
+             1 [0a1b2c3d4e] on 2024-02-01 08:30:00\n"
+        );
+    }
+
+    #[test]
+    fn comment_with_a_colon_is_not_a_new_stash() {
+        let output = "1: [0a1b2c3d4e] on 2024-02-01\n    note: keep this\n";
+        assert_eq!(
+            format_stash_list(String::from(output)),
+            "1 [0a1b2c3d4e] on 2024-02-01 note: keep this\n"
+        );
+    }
+}
@@ -0,0 +1,943 @@
+use std::{collections::HashMap, fmt::Write};
+
+use git2::{
+    Branch, BranchType, Commit, Delta, DiffOptions, ErrorClass, ErrorCode, Oid,
+    ReferenceType, Repository, Sort, Status, StatusOptions, Time,
+};
+
+use crate::{
+    action::{background, ActionResult, ActionTask},
+    git_actions::GitActions,
+    history_edit::PlanEntry,
+    log_entry::{format_epoch_date, LogEntry},
+    patch::FilePatch,
+    select::{Entry, State},
+    vcs_error::VcsError,
+    version_control_actions::{
+        Capability, Support, Upstream, VersionControlActions,
+    },
+};
+
+fn error_to_vcs_error(error: git2::Error) -> VcsError {
+    let message = error.message().to_owned();
+    match (error.code(), error.class()) {
+        (ErrorCode::Conflict | ErrorCode::MergeConflict, _) => {
+            VcsError::MergeConflict(message)
+        }
+        (ErrorCode::Auth, _) | (_, ErrorClass::Ssh) => {
+            VcsError::AuthFailure(message)
+        }
+        (ErrorCode::NotFound, ErrorClass::Repository) => {
+            VcsError::NotARepository(message)
+        }
+        _ => VcsError::CommandFailed {
+            code: Some(error.raw_code()),
+            stderr: message,
+        },
+    }
+}
+
+fn status_to_state(status: Status) -> State {
+    if status.is_conflicted() {
+        State::Unmerged
+    } else if status.is_index_new() {
+        State::Added
+    } else if status.is_index_deleted() || status.is_wt_deleted() {
+        State::Deleted
+    } else if status.is_index_renamed() || status.is_wt_renamed() {
+        State::Renamed
+    } else if status.is_index_modified()
+        || status.is_index_typechange()
+        || status.is_wt_modified()
+        || status.is_wt_typechange()
+    {
+        State::Modified
+    } else if status.is_wt_new() {
+        State::Untracked
+    } else if status.is_ignored() {
+        State::Ignored
+    } else {
+        State::Unmodified
+    }
+}
+
+fn delta_to_state(delta: Delta) -> State {
+    match delta {
+        Delta::Added => State::Added,
+        Delta::Deleted => State::Deleted,
+        Delta::Modified | Delta::Typechange => State::Modified,
+        Delta::Renamed => State::Renamed,
+        Delta::Copied => State::Copied,
+        Delta::Untracked => State::Untracked,
+        Delta::Ignored => State::Ignored,
+        Delta::Conflicted => State::Unmerged,
+        Delta::Unmodified | Delta::Unreadable => State::Unmodified,
+    }
+}
+
+fn delta_to_str(delta: Delta) -> &'static str {
+    match delta {
+        Delta::Added => "A",
+        Delta::Deleted => "D",
+        Delta::Modified => "M",
+        Delta::Renamed => "R",
+        Delta::Copied => "C",
+        Delta::Typechange => "T",
+        Delta::Conflicted => "U",
+        _ => "X",
+    }
+}
+
+/// Formats a commit time as `YYYY-MM-DD` in the commit's own timezone,
+/// matching git's `%as` placeholder
+fn format_short_date(time: Time) -> String {
+    format_epoch_date(time.seconds() + time.offset_minutes() as i64 * 60)
+}
+
+/// Keeps track of which commit each graph column is waiting for while
+/// walking the history in topological order
+#[derive(Default)]
+struct LogGraph {
+    lanes: Vec<Option<Oid>>,
+    forked_lanes: Vec<usize>,
+}
+
+impl LogGraph {
+    /// Writes a connector line to `output` if more than one column was
+    /// waiting for `commit` and returns the graph prefix of its own line
+    fn commit_row(&mut self, commit: &Commit, output: &mut String) -> String {
+        let id = commit.id();
+        let column = match self.lanes.iter().position(|l| *l == Some(id)) {
+            Some(column) => column,
+            None => self.free_lane(),
+        };
+
+        let joins = self
+            .lanes
+            .iter()
+            .enumerate()
+            .any(|(i, l)| i != column && *l == Some(id));
+        if joins {
+            self.write_row(output, |i, lane| {
+                if i != column && lane == Some(id) {
+                    '/'
+                } else if lane.is_some() {
+                    '|'
+                } else {
+                    ' '
+                }
+            });
+            output.push('\n');
+            for lane in &mut self.lanes {
+                if *lane == Some(id) {
+                    *lane = None;
+                }
+            }
+        }
+
+        let mut graph = String::new();
+        self.write_row(&mut graph, |i, lane| {
+            if i == column {
+                '*'
+            } else if lane.is_some() {
+                '|'
+            } else {
+                ' '
+            }
+        });
+
+        let mut parents = commit.parent_ids();
+        self.lanes[column] = parents.next();
+        self.forked_lanes.clear();
+        for parent in parents {
+            if !self.lanes.contains(&Some(parent)) {
+                let lane = self.free_lane();
+                self.lanes[lane] = Some(parent);
+                self.forked_lanes.push(lane);
+            }
+        }
+
+        while let Some(None) = self.lanes.last() {
+            self.lanes.pop();
+        }
+
+        graph
+    }
+
+    /// Writes a connector line for the columns opened by the parents of the
+    /// last merge commit
+    fn write_merge_row(&mut self, output: &mut String) {
+        if self.forked_lanes.is_empty() {
+            return;
+        }
+
+        output.push('\n');
+        let forked_lanes = std::mem::take(&mut self.forked_lanes);
+        self.write_row(output, |i, lane| {
+            if forked_lanes.contains(&i) {
+                '\\'
+            } else if lane.is_some() {
+                '|'
+            } else {
+                ' '
+            }
+        });
+    }
+
+    fn write_row<F>(&self, output: &mut String, lane_char: F)
+    where
+        F: Fn(usize, Option<Oid>) -> char,
+    {
+        for (i, lane) in self.lanes.iter().enumerate() {
+            output.push(lane_char(i, *lane));
+            output.push(' ');
+        }
+    }
+
+    fn free_lane(&mut self) -> usize {
+        match self.lanes.iter().position(|l| l.is_none()) {
+            Some(lane) => lane,
+            None => {
+                self.lanes.push(None);
+                self.lanes.len() - 1
+            }
+        }
+    }
+}
+
+/// Git backend that serves read-only queries in-process through libgit2
+/// and falls back to the git executable for everything else
+pub struct Git2Actions {
+    pub cli: GitActions,
+    repository: Repository,
+}
+
+impl Git2Actions {
+    pub fn open(cli: GitActions) -> Result<Self, VcsError> {
+        let repository =
+            Repository::open(cli.get_root()).map_err(error_to_vcs_error)?;
+        Ok(Self { cli, repository })
+    }
+
+    /// Runs `run` on a worker thread against a repository opened there,
+    /// since a libgit2 repository can't be shared between threads
+    fn in_background<F>(&self, run: F) -> Box<dyn ActionTask>
+    where
+        F: 'static + Send + FnOnce(&Git2Actions) -> Result<String, git2::Error>,
+    {
+        let cli = self.cli.clone();
+        background(move || {
+            let output = Git2Actions::open(cli)
+                .and_then(|git2| run(&git2).map_err(error_to_vcs_error));
+            match output {
+                Ok(output) => ActionResult::from_ok(output),
+                Err(error) => ActionResult::from_error(error),
+            }
+        })
+    }
+
+    fn decorations(&self) -> Result<HashMap<Oid, Vec<String>>, git2::Error> {
+        let mut decorations: HashMap<Oid, Vec<String>> = HashMap::new();
+
+        match self.repository.head() {
+            Ok(head) => {
+                if let Some(id) = head.target() {
+                    let decoration = if head.is_branch() {
+                        format!("HEAD -> {}", head.shorthand().unwrap_or(""))
+                    } else {
+                        String::from("HEAD")
+                    };
+                    decorations.entry(id).or_default().push(decoration);
+                }
+            }
+            Err(ref e) if e.code() == ErrorCode::UnbornBranch => (),
+            Err(e) => return Err(e),
+        }
+
+        let head_name =
+            self.repository.head().ok().and_then(|h| {
+                h.name().map(String::from).filter(|_| h.is_branch())
+            });
+
+        for prefix in &["refs/tags/", "refs/heads/", "refs/remotes/"] {
+            let glob = format!("{}*", prefix);
+            let mut references = Vec::new();
+            for reference in self.repository.references_glob(&glob)? {
+                let reference = reference?;
+                if reference.kind() != Some(ReferenceType::Direct) {
+                    continue;
+                }
+                if reference.name() == head_name.as_deref() {
+                    continue;
+                }
+                let id = match reference.peel_to_commit() {
+                    Ok(commit) => commit.id(),
+                    Err(_) => continue,
+                };
+                let name = reference.shorthand().unwrap_or("").to_owned();
+                references.push((id, name));
+            }
+
+            references.sort_by(|a, b| a.1.cmp(&b.1));
+            for (id, name) in references {
+                let decoration = if *prefix == "refs/tags/" {
+                    format!("tag: {}", name)
+                } else {
+                    name
+                };
+                decorations.entry(id).or_default().push(decoration);
+            }
+        }
+
+        Ok(decorations)
+    }
+
+    fn revision_changed_deltas(
+        &self,
+        target: &str,
+    ) -> Result<Vec<(Delta, String)>, git2::Error> {
+        let commit =
+            self.repository.revparse_single(target)?.peel_to_commit()?;
+
+        // mimic `git diff-tree` which does not show anything for root and
+        // merge commits unless explicitly asked to
+        if commit.parent_count() != 1 {
+            return Ok(Vec::new());
+        }
+
+        let tree = commit.tree()?;
+        let parent_tree = commit.parent(0)?.tree()?;
+        let mut options = DiffOptions::new();
+        let diff = self.repository.diff_tree_to_tree(
+            Some(&parent_tree),
+            Some(&tree),
+            Some(&mut options),
+        )?;
+
+        let deltas = diff
+            .deltas()
+            .map(|d| {
+                let file = match d.status() {
+                    Delta::Deleted => d.old_file(),
+                    _ => d.new_file(),
+                };
+                let filename = file
+                    .path()
+                    .map(|p| p.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                (d.status(), filename)
+            })
+            .collect();
+        Ok(deltas)
+    }
+
+    fn log_text(&self, count: usize) -> Result<String, git2::Error> {
+        let decorations = self.decorations()?;
+
+        let mut revwalk = self.repository.revwalk()?;
+        // children must come before their parents for the graph lanes
+        revwalk.set_sorting(Sort::TOPOLOGICAL | Sort::TIME)?;
+        revwalk.push_glob("*")?;
+        match revwalk.push_head() {
+            Ok(()) => (),
+            Err(ref e) if e.code() == ErrorCode::UnbornBranch => (),
+            Err(ref e) if e.code() == ErrorCode::NotFound => (),
+            Err(e) => return Err(e),
+        }
+
+        let mut graph = LogGraph::default();
+        let mut output = String::new();
+        for id in revwalk.take(count) {
+            let commit = self.repository.find_commit(id?)?;
+
+            if !output.is_empty() {
+                output.push('\n');
+            }
+            let short_id = commit.as_object().short_id()?;
+            let author = commit.author();
+            let entry = LogEntry {
+                graph: graph.commit_row(&commit, &mut output),
+                id: commit.id().to_string(),
+                short_id: String::from(short_id.as_str().unwrap_or("")),
+                parents: commit.parent_ids().map(|p| p.to_string()).collect(),
+                author: String::from(author.name().unwrap_or("")),
+                date: format_short_date(author.when()),
+                refs: decorations
+                    .get(&commit.id())
+                    .map(|d| d.join(", "))
+                    .unwrap_or_default(),
+                branch: String::new(),
+                subject: String::from(commit.summary().unwrap_or("")),
+                graph_lines: Vec::new(),
+            };
+            output.push_str(&entry.format_record());
+            graph.write_merge_row(&mut output);
+        }
+
+        Ok(output)
+    }
+
+    /// The branch `branch` tracks, or its name alone if it is gone
+    fn upstream_of(
+        &self,
+        branch: &Branch,
+    ) -> Result<Option<Result<Upstream, String>>, git2::Error> {
+        let refname = match branch.get().name() {
+            Some(refname) if branch.get().is_branch() => refname,
+            _ => return Ok(None),
+        };
+        let upstream_refname =
+            match self.repository.branch_upstream_name(refname) {
+                Ok(name) => name,
+                Err(e) if e.code() == ErrorCode::NotFound => return Ok(None),
+                Err(e) => return Err(e),
+            };
+        let upstream_refname = upstream_refname.as_str().unwrap_or("");
+        let name = upstream_refname
+            .strip_prefix("refs/remotes/")
+            .or_else(|| upstream_refname.strip_prefix("refs/heads/"))
+            .unwrap_or(upstream_refname)
+            .to_owned();
+
+        let upstream = match self.repository.find_reference(upstream_refname) {
+            Ok(upstream) => upstream,
+            Err(e) if e.code() == ErrorCode::NotFound => {
+                return Ok(Some(Err(name)))
+            }
+            Err(e) => return Err(e),
+        };
+        let (ahead, behind) = match (branch.get().target(), upstream.target()) {
+            (Some(local), Some(upstream)) => {
+                self.repository.graph_ahead_behind(local, upstream)?
+            }
+            _ => (0, 0),
+        };
+        Ok(Some(Ok(Upstream {
+            name,
+            ahead,
+            behind,
+        })))
+    }
+
+    fn branches_text(&self) -> Result<String, git2::Error> {
+        let mut output = String::new();
+        for branch_type in &[BranchType::Local, BranchType::Remote] {
+            let mut names = Vec::new();
+            for branch in self.repository.branches(Some(*branch_type))? {
+                let (branch, _) = branch?;
+                if branch.get().kind() != Some(ReferenceType::Direct) {
+                    continue;
+                }
+                if let Some(name) = branch.name()? {
+                    let name = match self.upstream_of(&branch)? {
+                        Some(Ok(upstream)) => format!("{} {}", name, upstream),
+                        Some(Err(upstream)) => {
+                            format!("{} {} [gone]", name, upstream)
+                        }
+                        None => name.to_owned(),
+                    };
+                    names.push(name);
+                }
+            }
+
+            names.sort();
+            for name in names {
+                output.push_str(&name);
+                output.push('\n');
+            }
+        }
+        Ok(output)
+    }
+}
+
+impl VersionControlActions for Git2Actions {
+    fn executable_name(&self) -> &'static str {
+        self.cli.executable_name()
+    }
+
+    fn current_dir(&self) -> &str {
+        self.cli.current_dir()
+    }
+
+    fn classify_error(&self, error: VcsError) -> VcsError {
+        self.cli.classify_error(error)
+    }
+
+    fn support(&self, capability: Capability) -> Support {
+        self.cli.support(capability)
+    }
+
+    fn set_root(&mut self) -> Result<(), VcsError> {
+        self.cli.set_root()?;
+        self.repository = Repository::open(self.cli.get_root())
+            .map_err(error_to_vcs_error)?;
+        Ok(())
+    }
+
+    fn get_root(&self) -> &str {
+        self.cli.get_root()
+    }
+
+    fn switch_root(&mut self, dir: &str) -> Result<(), VcsError> {
+        let mut cli = self.cli.clone();
+        cli.switch_root(dir)?;
+        self.repository =
+            Repository::open(cli.get_root()).map_err(error_to_vcs_error)?;
+        self.cli = cli;
+        Ok(())
+    }
+
+    fn get_current_changed_files(&self) -> Result<Vec<Entry>, VcsError> {
+        let mut options = StatusOptions::new();
+        options
+            .include_untracked(true)
+            .recurse_untracked_dirs(false)
+            .include_ignored(false);
+
+        let statuses = self
+            .repository
+            .statuses(Some(&mut options))
+            .map_err(error_to_vcs_error)?;
+
+        let files = statuses
+            .iter()
+            .filter(|s| s.status() != Status::CURRENT)
+            .map(|s| Entry {
+                filename: String::from_utf8_lossy(s.path_bytes()).into_owned(),
+                selected: false,
+                state: status_to_state(s.status()),
+            })
+            .collect();
+        Ok(files)
+    }
+
+    fn get_tracked_files(&self) -> Result<Vec<Entry>, VcsError> {
+        self.cli.get_tracked_files()
+    }
+
+    fn get_revision_changed_files(
+        &self,
+        target: &str,
+    ) -> Result<Vec<Entry>, VcsError> {
+        let deltas = self
+            .revision_changed_deltas(target)
+            .map_err(error_to_vcs_error)?;
+
+        let files = deltas
+            .into_iter()
+            .map(|(delta, filename)| Entry {
+                filename,
+                selected: false,
+                state: delta_to_state(delta),
+            })
+            .collect();
+        Ok(files)
+    }
+
+    fn version(&self) -> Result<String, VcsError> {
+        let (major, minor, rev) = git2::Version::get().libgit2_version();
+        let mut version = self.cli.version()?;
+        let _ = write!(version, "libgit2 version {}.{}.{}", major, minor, rev);
+        Ok(version)
+    }
+
+    fn status(&self) -> Box<dyn ActionTask> {
+        self.cli.status()
+    }
+
+    fn reflog(&self) -> Box<dyn ActionTask> {
+        self.cli.reflog()
+    }
+
+    fn current_export(&self) -> Box<dyn ActionTask> {
+        self.cli.current_export()
+    }
+
+    fn log(&self, count: usize) -> Box<dyn ActionTask> {
+        self.in_background(move |git2| git2.log_text(count))
+    }
+
+    fn blame(
+        &self,
+        filename: &str,
+        revision: Option<&str>,
+    ) -> Box<dyn ActionTask> {
+        self.cli.blame(filename, revision)
+    }
+
+    fn get_parent_revision(&self, revision: &str) -> Result<String, VcsError> {
+        self.cli.get_parent_revision(revision)
+    }
+
+    fn current_diff_all(&self) -> Box<dyn ActionTask> {
+        self.cli.current_diff_all()
+    }
+
+    fn current_diff_selected(&self, entries: &[Entry]) -> Box<dyn ActionTask> {
+        self.cli.current_diff_selected(entries)
+    }
+
+    fn revision_changes(&self, target: &str) -> Box<dyn ActionTask> {
+        let target = String::from(target);
+        self.in_background(move |git2| {
+            let mut output = String::new();
+            for (delta, filename) in git2.revision_changed_deltas(&target)? {
+                output.push_str(delta_to_str(delta));
+                output.push('\t');
+                output.push_str(&filename);
+                output.push('\n');
+            }
+            Ok(output)
+        })
+    }
+
+    fn revision_diff_all(&self, target: &str) -> Box<dyn ActionTask> {
+        self.cli.revision_diff_all(target)
+    }
+
+    fn revision_diff_selected(
+        &self,
+        target: &str,
+        entries: &[Entry],
+    ) -> Box<dyn ActionTask> {
+        self.cli.revision_diff_selected(target, entries)
+    }
+
+    fn commit_all(&self, message: &str) -> Box<dyn ActionTask> {
+        self.cli.commit_all(message)
+    }
+
+    fn commit_selected(
+        &self,
+        message: &str,
+        entries: &[Entry],
+    ) -> Box<dyn ActionTask> {
+        self.cli.commit_selected(message, entries)
+    }
+
+    fn get_current_patch(
+        &self,
+        entries: &[Entry],
+    ) -> Result<Vec<FilePatch>, VcsError> {
+        self.cli.get_current_patch(entries)
+    }
+
+    fn commit_hunks(
+        &self,
+        message: &str,
+        files: &[FilePatch],
+    ) -> Box<dyn ActionTask> {
+        self.cli.commit_hunks(message, files)
+    }
+
+    fn get_last_commit_message(&self) -> Result<String, VcsError> {
+        self.cli.get_last_commit_message()
+    }
+
+    fn is_last_commit_published(&self) -> Result<bool, VcsError> {
+        self.cli.is_last_commit_published()
+    }
+
+    fn amend(&self, message: &str, entries: &[Entry]) -> Box<dyn ActionTask> {
+        self.cli.amend(message, entries)
+    }
+
+    fn revert_all(&self) -> Box<dyn ActionTask> {
+        self.cli.revert_all()
+    }
+
+    fn revert_selected(&self, entries: &[Entry]) -> Box<dyn ActionTask> {
+        self.cli.revert_selected(entries)
+    }
+
+    fn update(&self, target: &str) -> Box<dyn ActionTask> {
+        self.cli.update(target)
+    }
+
+    fn merge(&self, target: &str) -> Box<dyn ActionTask> {
+        self.cli.merge(target)
+    }
+
+    fn rebase(&self, target: &str) -> Box<dyn ActionTask> {
+        self.cli.rebase(target)
+    }
+
+    fn rebase_continue(&self) -> Box<dyn ActionTask> {
+        self.cli.rebase_continue()
+    }
+
+    fn rebase_skip(&self) -> Box<dyn ActionTask> {
+        self.cli.rebase_skip()
+    }
+
+    fn rebase_abort(&self) -> Box<dyn ActionTask> {
+        self.cli.rebase_abort()
+    }
+
+    fn is_rebase_in_progress(&self) -> bool {
+        self.cli.is_rebase_in_progress()
+    }
+
+    fn cherry_pick(&self, revisions: &[String]) -> Box<dyn ActionTask> {
+        self.cli.cherry_pick(revisions)
+    }
+
+    fn cherry_pick_continue(&self) -> Box<dyn ActionTask> {
+        self.cli.cherry_pick_continue()
+    }
+
+    fn cherry_pick_abort(&self) -> Box<dyn ActionTask> {
+        self.cli.cherry_pick_abort()
+    }
+
+    fn is_cherry_pick_in_progress(&self) -> bool {
+        self.cli.is_cherry_pick_in_progress()
+    }
+
+    fn backout(&self, revision: &str, message: &str) -> Box<dyn ActionTask> {
+        self.cli.backout(revision, message)
+    }
+
+    fn is_backout_in_progress(&self) -> bool {
+        self.cli.is_backout_in_progress()
+    }
+
+    fn get_backout_message(&self) -> Option<String> {
+        self.cli.get_backout_message()
+    }
+
+    fn bisect_start(&self, bad: &str, good: &str) -> Box<dyn ActionTask> {
+        self.cli.bisect_start(bad, good)
+    }
+
+    fn bisect_good(&self) -> Box<dyn ActionTask> {
+        self.cli.bisect_good()
+    }
+
+    fn bisect_bad(&self) -> Box<dyn ActionTask> {
+        self.cli.bisect_bad()
+    }
+
+    fn bisect_skip(&self) -> Box<dyn ActionTask> {
+        self.cli.bisect_skip()
+    }
+
+    fn bisect_run(
+        &self,
+        command: &str,
+        args: &[String],
+    ) -> Box<dyn ActionTask> {
+        self.cli.bisect_run(command, args)
+    }
+
+    fn bisect_reset(&self) -> Box<dyn ActionTask> {
+        self.cli.bisect_reset()
+    }
+
+    fn is_bisect_in_progress(&self) -> bool {
+        self.cli.is_bisect_in_progress()
+    }
+
+    fn get_history(&self, base: &str) -> Result<Vec<PlanEntry>, VcsError> {
+        self.cli.get_history(base)
+    }
+
+    fn edit_history(
+        &self,
+        base: &str,
+        plan: &[PlanEntry],
+    ) -> Box<dyn ActionTask> {
+        self.cli.edit_history(base, plan)
+    }
+
+    fn conflicts(&self) -> Box<dyn ActionTask> {
+        self.cli.conflicts()
+    }
+
+    fn take_other(&self) -> Box<dyn ActionTask> {
+        self.cli.take_other()
+    }
+
+    fn take_local(&self) -> Box<dyn ActionTask> {
+        self.cli.take_local()
+    }
+
+    fn fetch(&self, remote: Option<&str>) -> Box<dyn ActionTask> {
+        self.cli.fetch(remote)
+    }
+
+    fn pull(&self, remote: Option<&str>) -> Box<dyn ActionTask> {
+        self.cli.pull(remote)
+    }
+
+    fn push(&self, remote: Option<&str>) -> Box<dyn ActionTask> {
+        self.cli.push(remote)
+    }
+
+    fn list_tags(&self) -> Box<dyn ActionTask> {
+        self.cli.list_tags()
+    }
+
+    fn get_tags(&self) -> Result<Vec<String>, VcsError> {
+        self.cli.get_tags()
+    }
+
+    fn create_tag(
+        &self,
+        name: &str,
+        message: Option<&str>,
+        force: bool,
+        remote: Option<&str>,
+    ) -> Box<dyn ActionTask> {
+        self.cli.create_tag(name, message, force, remote)
+    }
+
+    fn delete_tag(
+        &self,
+        name: &str,
+        remote: Option<&str>,
+    ) -> Box<dyn ActionTask> {
+        self.cli.delete_tag(name, remote)
+    }
+
+    fn list_submodules(&self) -> Box<dyn ActionTask> {
+        self.cli.list_submodules()
+    }
+
+    fn init_submodules(&self) -> Box<dyn ActionTask> {
+        self.cli.init_submodules()
+    }
+
+    fn update_submodules(&self) -> Box<dyn ActionTask> {
+        self.cli.update_submodules()
+    }
+
+    fn sync_submodules(&self) -> Box<dyn ActionTask> {
+        self.cli.sync_submodules()
+    }
+
+    fn get_superproject_root(&self) -> Result<String, VcsError> {
+        self.cli.get_superproject_root()
+    }
+
+    fn list_worktrees(&self) -> Box<dyn ActionTask> {
+        self.cli.list_worktrees()
+    }
+
+    fn add_worktree(&self, path: &str, target: &str) -> Box<dyn ActionTask> {
+        self.cli.add_worktree(path, target)
+    }
+
+    fn remove_worktree(&self, path: &str) -> Box<dyn ActionTask> {
+        self.cli.remove_worktree(path)
+    }
+
+    fn prune_worktrees(&self) -> Box<dyn ActionTask> {
+        self.cli.prune_worktrees()
+    }
+
+    fn list_remotes(&self) -> Box<dyn ActionTask> {
+        self.cli.list_remotes()
+    }
+
+    fn add_remote(&self, name: &str, url: &str) -> Box<dyn ActionTask> {
+        self.cli.add_remote(name, url)
+    }
+
+    fn rename_remote(&self, name: &str, new_name: &str) -> Box<dyn ActionTask> {
+        self.cli.rename_remote(name, new_name)
+    }
+
+    fn remove_remote(&self, name: &str) -> Box<dyn ActionTask> {
+        self.cli.remove_remote(name)
+    }
+
+    fn set_remote_url(&self, name: &str, url: &str) -> Box<dyn ActionTask> {
+        self.cli.set_remote_url(name, url)
+    }
+
+    fn get_upstream_remote(&self) -> Option<String> {
+        self.cli.get_upstream_remote()
+    }
+
+    fn get_default_remote(&self) -> Option<String> {
+        self.cli.get_default_remote()
+    }
+
+    fn upstream(&self) -> Box<dyn ActionTask> {
+        self.in_background(|git2| {
+            let head = match git2.repository.head() {
+                Ok(head) if head.is_branch() => head,
+                _ => return Ok(String::new()),
+            };
+            Ok(match git2.upstream_of(&Branch::wrap(head))? {
+                Some(Ok(upstream)) => upstream.to_string(),
+                _ => String::new(),
+            })
+        })
+    }
+
+    fn publishes_refs(&self) -> bool {
+        self.cli.publishes_refs()
+    }
+
+    fn list_branches(&self) -> Box<dyn ActionTask> {
+        self.in_background(|git2| git2.branches_text())
+    }
+
+    fn create_branch(
+        &self,
+        name: &str,
+        remote: Option<&str>,
+    ) -> Box<dyn ActionTask> {
+        self.cli.create_branch(name, remote)
+    }
+
+    fn create_branch_at(
+        &self,
+        name: &str,
+        revision: &str,
+    ) -> Box<dyn ActionTask> {
+        self.cli.create_branch_at(name, revision)
+    }
+
+    fn close_branch(
+        &self,
+        name: &str,
+        remote: Option<&str>,
+    ) -> Box<dyn ActionTask> {
+        self.cli.close_branch(name, remote)
+    }
+
+    fn list_stashes(&self) -> Box<dyn ActionTask> {
+        self.cli.list_stashes()
+    }
+
+    fn stash_all(&self, message: &str) -> Box<dyn ActionTask> {
+        self.cli.stash_all(message)
+    }
+
+    fn stash_selected(
+        &self,
+        message: &str,
+        entries: &[Entry],
+    ) -> Box<dyn ActionTask> {
+        self.cli.stash_selected(message, entries)
+    }
+
+    fn show_stash(&self, name: &str) -> Box<dyn ActionTask> {
+        self.cli.show_stash(name)
+    }
+
+    fn apply_stash(&self, name: &str) -> Box<dyn ActionTask> {
+        self.cli.apply_stash(name)
+    }
+
+    fn pop_stash(&self, name: &str) -> Box<dyn ActionTask> {
+        self.cli.pop_stash(name)
+    }
+
+    fn drop_stash(&self, name: &str) -> Box<dyn ActionTask> {
+        self.cli.drop_stash(name)
+    }
+}
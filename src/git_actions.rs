@@ -1,7 +1,24 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+};
+
 use crate::{
-    action::{parallel, serial, task_vec, ActionTask},
+    action::{
+        background, chain, lazy, map_output, parallel, ready, serial, task_vec,
+        ActionResult, ActionTask,
+    },
+    blame::BlameLine,
+    history_edit::{messages_editor, strip_comments, write_plan, PlanEntry},
+    log_entry::format_epoch_date,
+    patch::{format_selected, parse_patch, FilePatch},
     select::{Entry, State},
-    version_control_actions::{handle_command, task, VersionControlActions},
+    vcs_error::{ErrorMessages, VcsError},
+    version_control_actions::{
+        task, task_with_input, Capability, Support, Upstream,
+        VersionControlActions,
+    },
 };
 
 fn str_to_state(s: &str) -> State {
@@ -17,10 +34,318 @@ fn str_to_state(s: &str) -> State {
     }
 }
 
+const ERROR_MESSAGES: ErrorMessages = ErrorMessages {
+    not_a_repository: &["not a git repository"],
+    merge_conflict: &[
+        "CONFLICT (",
+        "Automatic merge failed",
+        "unmerged files",
+        "needs merge",
+        "could not apply",
+        "could not revert",
+    ],
+    auth_failure: &[
+        "Authentication failed",
+        "could not read Username",
+        "terminal prompts disabled",
+        "Permission denied (publickey",
+    ],
+};
+
+/// The git directory of the repository at `root`, which `.git` points to
+/// when it is a file as in worktrees
+fn git_dir(root: &str) -> PathBuf {
+    let dot_git = Path::new(root).join(".git");
+    match fs::read_to_string(&dot_git) {
+        Ok(contents) => match contents.trim().strip_prefix("gitdir: ") {
+            Some(dir) => Path::new(root).join(dir),
+            None => dot_git,
+        },
+        Err(_) => dot_git,
+    }
+}
+
+/// Parses a timezone like `+0130` into minutes
+fn parse_timezone(timezone: &str) -> i64 {
+    let sign = if timezone.starts_with('-') { -1 } else { 1 };
+    let digits = timezone.trim_start_matches(['+', '-']);
+    let hours: i64 = digits.get(..2).and_then(|h| h.parse().ok()).unwrap_or(0);
+    let minutes: i64 =
+        digits.get(2..).and_then(|m| m.parse().ok()).unwrap_or(0);
+    sign * (hours * 60 + minutes)
+}
+
+/// Renders `git blame --porcelain` into blame records
+///
+/// Commit details only come with the first line blamed on each commit so
+/// they are remembered for the following ones.
+fn format_blame_porcelain(output: String) -> String {
+    let mut commits: HashMap<&str, BlameLine> = HashMap::new();
+    let mut current: Option<(&str, usize)> = None;
+    let mut author_time = 0;
+    let mut blame = String::new();
+    for line in output.lines() {
+        let (revision, line_number) = match current {
+            Some(current) => current,
+            None => {
+                let mut fields = line.split(' ');
+                let revision = fields.next().unwrap_or("");
+                let line_number = fields.nth(1).and_then(|n| n.parse().ok());
+                current = Some((revision, line_number.unwrap_or(0)));
+                commits.entry(revision).or_insert_with(|| BlameLine {
+                    revision: String::from(revision),
+                    short_revision: String::from(
+                        revision.get(..7).unwrap_or(revision),
+                    ),
+                    ..Default::default()
+                });
+                continue;
+            }
+        };
+
+        let commit = commits.entry(revision).or_default();
+        if let Some(text) = line.strip_prefix('\t') {
+            let line = BlameLine {
+                line_number,
+                text: String::from(text),
+                ..commit.clone()
+            };
+            blame.push_str(&line.format_record());
+            blame.push('\n');
+            current = None;
+        } else if let Some(author) = line.strip_prefix("author ") {
+            commit.author = String::from(author);
+        } else if let Some(time) = line.strip_prefix("author-time ") {
+            author_time = time.parse().unwrap_or(0);
+        } else if let Some(timezone) = line.strip_prefix("author-tz ") {
+            let offset = parse_timezone(timezone) * 60;
+            commit.date = format_epoch_date(author_time + offset);
+        }
+    }
+    blame
+}
+
+/// Renders `git worktree list --porcelain` as one line per worktree with its
+/// path followed by its branch in brackets or else its revision in
+/// parentheses
+fn format_worktree_porcelain(output: String) -> String {
+    let mut worktrees = String::new();
+    for block in output.split("\n\n") {
+        let mut path = None;
+        let mut revision = "";
+        let mut suffix = None;
+        for line in block.lines() {
+            if let Some(worktree) = line.strip_prefix("worktree ") {
+                path = Some(worktree);
+            } else if let Some(head) = line.strip_prefix("HEAD ") {
+                revision = head.get(..7).unwrap_or(head);
+            } else if let Some(branch) = line.strip_prefix("branch ") {
+                let branch = branch.trim_start_matches("refs/heads/");
+                suffix = Some(format!("[{}]", branch));
+            } else if line == "bare" {
+                suffix = Some(String::from("(bare)"));
+            }
+        }
+
+        if let Some(path) = path {
+            let suffix = suffix.unwrap_or_else(|| format!("({})", revision));
+            worktrees.push_str(path);
+            worktrees.push(' ');
+            worktrees.push_str(&suffix);
+            worktrees.push('\n');
+        }
+    }
+    worktrees
+}
+
+/// Splits a line of `git submodule status` into its flag, revision and path
+fn parse_submodule_status(line: &str) -> Option<(char, &str, &str)> {
+    let flag = line.chars().next()?;
+    let (revision, path) = line.get(1..)?.split_once(' ')?;
+    // initialized submodules end with the description of their revision
+    let path = match path.rfind(" (") {
+        Some(index) if path.ends_with(')') => &path[..index],
+        _ => path,
+    };
+    Some((flag, revision, path))
+}
+
+/// Prefixes every line of `output` with `tag` to tell it apart from the
+/// output of the tasks it runs alongside
+fn tag_lines(output: String, tag: &str) -> String {
+    output.lines().map(|l| format!("{} {}\n", tag, l)).collect()
+}
+
+/// Formats the checked out `git submodule status` lines along with the
+/// `recorded` ones and the `dirty` submodule paths
+fn format_submodules(output: String) -> String {
+    let mut checked_out = Vec::new();
+    let mut recorded = HashMap::new();
+    let mut dirty = HashSet::new();
+    for line in output.lines() {
+        if let Some(line) = line.strip_prefix("recorded ") {
+            if let Some((_, revision, path)) = parse_submodule_status(line) {
+                recorded.insert(path, revision);
+            }
+        } else if let Some(path) = line.strip_prefix("dirty ") {
+            dirty.insert(path);
+        } else if let Some(status) = parse_submodule_status(line) {
+            checked_out.push(status);
+        }
+    }
+
+    let short =
+        |revision: &str| String::from(revision.get(..7).unwrap_or(revision));
+    let mut submodules = String::new();
+    for (flag, revision, path) in checked_out {
+        let state = match (flag, dirty.contains(path)) {
+            ('-', _) => "uninitialized",
+            ('U', _) => "conflicted",
+            ('+', false) => "moved",
+            ('+', true) => "moved,dirty",
+            (_, false) => "clean",
+            (_, true) => "dirty",
+        };
+        let checked_out = if flag == '-' {
+            String::from("-")
+        } else {
+            short(revision)
+        };
+        let recorded = recorded.get(path).map(|r| short(r)).unwrap_or_default();
+
+        submodules.push_str(&format!(
+            "{} {} {} {}\n",
+            path, checked_out, recorded, state
+        ));
+    }
+    submodules
+}
+
+/// Reads the upstream name followed by the ahead and behind counts
+fn parse_upstream(output: &str) -> Option<Upstream> {
+    let mut words = output.split_whitespace();
+    Some(Upstream {
+        name: String::from(words.next()?),
+        ahead: words.next()?.parse().ok()?,
+        behind: words.next()?.parse().ok()?,
+    })
+}
+
+/// Writes `plan` as the todo list of `git rebase --interactive`
+fn format_rebase_todo(plan: &[PlanEntry]) -> String {
+    let mut todo = String::new();
+    for entry in plan {
+        todo.push_str(&format!(
+            "{} {} {}\n",
+            entry.action.name(),
+            entry.revision,
+            entry.summary()
+        ));
+    }
+    todo
+}
+
+/// Where the plan of a history edit is kept until the rebase is done
+const HISTORY_EDIT_DIR: &str = "verco-history-edit";
+
+#[derive(Clone)]
 pub struct GitActions {
     pub current_dir: String,
 }
 
+impl GitActions {
+    /// The checked out branch unless the HEAD is detached
+    fn current_branch(&self) -> Option<String> {
+        let branch = self
+            .run_command(self.command().args([
+                "symbolic-ref",
+                "--short",
+                "-q",
+                "HEAD",
+            ]))
+            .ok()?;
+        Some(String::from(branch.trim()))
+    }
+
+    /// Stages the files that had conflicts, leaving any other change out of
+    /// the commit being continued
+    fn add_unmerged(&self) -> Box<dyn ActionTask> {
+        let git = self.clone();
+        background(move || {
+            let unmerged = match git.run_command(git.command().args([
+                "diff",
+                "--name-only",
+                "--diff-filter=U",
+                "-z",
+            ])) {
+                Ok(unmerged) => unmerged,
+                Err(error) => return ActionResult::from_error(error),
+            };
+            let paths: Vec<_> =
+                unmerged.split('\0').filter(|p| !p.is_empty()).collect();
+            if paths.is_empty() {
+                return ActionResult::from_ok(String::new());
+            }
+            match git
+                .run_command(git.command().arg("add").arg("--").args(paths))
+            {
+                Ok(output) => ActionResult::from_ok(output),
+                Err(error) => ActionResult::from_error(error),
+            }
+        })
+    }
+
+    /// Reverts `revision` without committing it, leaving `message` for the
+    /// commit that concludes it if it stops on conflicts
+    fn revert_without_commit(
+        &self,
+        revision: &str,
+        message: &str,
+    ) -> ActionResult {
+        let parents = match self.run_command(
+            self.command()
+                .args(["rev-list", "--parents", "-n", "1"])
+                .arg(revision)
+                .arg("--"),
+        ) {
+            Ok(parents) => parents,
+            Err(error) => return ActionResult::from_error(error),
+        };
+        // which side of a merge to keep is not obvious enough to guess
+        if parents.split_whitespace().count() > 2 {
+            return ActionResult::from_err(format!(
+                "{} is a merge commit, back out its parents' changes instead",
+                revision
+            ));
+        }
+
+        match self.run_command(
+            self.command().args(["revert", "--no-commit"]).arg(revision),
+        ) {
+            Ok(output) => ActionResult::from_ok(output),
+            Err(error) => {
+                if self.is_backout_in_progress() {
+                    let path = git_dir(&self.current_dir).join("MERGE_MSG");
+                    let _ = fs::write(path, format!("{}\n", message));
+                }
+                ActionResult::from_error(error)
+            }
+        }
+    }
+
+    /// Removes the plan of a history edit once its rebase is over
+    fn forget_history_edit(&self) -> Box<dyn ActionTask> {
+        let git = self.clone();
+        lazy(move || {
+            if !git.is_rebase_in_progress() {
+                let dir = git_dir(&git.current_dir).join(HISTORY_EDIT_DIR);
+                let _ = fs::remove_dir_all(dir);
+            }
+            ActionResult::from_ok(String::new())
+        })
+    }
+}
+
 impl VersionControlActions for GitActions {
     fn executable_name(&self) -> &'static str {
         "git"
@@ -30,10 +355,32 @@ impl VersionControlActions for GitActions {
         &self.current_dir[..]
     }
 
-    fn set_root(&mut self) -> Result<(), String> {
+    fn classify_error(&self, error: VcsError) -> VcsError {
+        error.classify(&ERROR_MESSAGES)
+    }
+
+    fn support(&self, capability: Capability) -> Support {
+        match capability {
+            Capability::CreateTag => {
+                Support::Differs("also pushes the tag to the chosen remote unless it is -")
+            }
+            Capability::DeleteTag => {
+                Support::Differs("also deletes the tag from the chosen remote unless it is -")
+            }
+            Capability::CreateBranch => Support::Differs(
+                "also pushes the branch to the chosen remote and sets it as upstream unless it is -",
+            ),
+            Capability::CloseBranch => Support::Differs(
+                "also deletes the branch from the chosen remote unless it is -",
+            ),
+            _ => Support::Supported,
+        }
+    }
+
+    fn set_root(&mut self) -> Result<(), VcsError> {
         let mut command = self.command();
         let dir =
-            handle_command(command.args(&["rev-parse", "--show-toplevel"]))?;
+            self.run_command(command.args(["rev-parse", "--show-toplevel"]))?;
 
         let dir = dir
             .lines()
@@ -48,8 +395,21 @@ impl VersionControlActions for GitActions {
         &self.current_dir[..]
     }
 
-    fn get_current_changed_files(&self) -> Result<Vec<Entry>, String> {
-        let output = handle_command(self.command().args(&["status", "-z"]))?;
+    fn switch_root(&mut self, dir: &str) -> Result<(), VcsError> {
+        let previous_dir = self.current_dir.clone();
+        self.current_dir = Path::new(&previous_dir)
+            .join(dir)
+            .to_string_lossy()
+            .into_owned();
+        let result = self.set_root();
+        if result.is_err() {
+            self.current_dir = previous_dir;
+        }
+        result
+    }
+
+    fn get_current_changed_files(&self) -> Result<Vec<Entry>, VcsError> {
+        let output = self.run_command(self.command().args(["status", "-z"]))?;
 
         let files = output
             .trim()
@@ -68,11 +428,26 @@ impl VersionControlActions for GitActions {
         Ok(files)
     }
 
+    fn get_tracked_files(&self) -> Result<Vec<Entry>, VcsError> {
+        let output =
+            self.run_command(self.command().args(["ls-files", "-z"]))?;
+        let files = output
+            .split('\0')
+            .filter(|f| !f.is_empty())
+            .map(|f| Entry {
+                filename: String::from(f),
+                selected: false,
+                state: State::Clean,
+            })
+            .collect();
+        Ok(files)
+    }
+
     fn get_revision_changed_files(
         &self,
         target: &str,
-    ) -> Result<Vec<Entry>, String> {
-        let output = handle_command(
+    ) -> Result<Vec<Entry>, VcsError> {
+        let output = self.run_command(
             self.command()
                 .arg("diff-tree")
                 .arg("--no-commit-id")
@@ -97,19 +472,25 @@ impl VersionControlActions for GitActions {
         Ok(files)
     }
 
-    fn version(&self) -> Result<String, String> {
-        handle_command(self.command().arg("--version"))
+    fn version(&self) -> Result<String, VcsError> {
+        self.run_command(self.command().arg("--version"))
     }
 
     fn status(&self) -> Box<dyn ActionTask> {
         task(self, |command| {
-            command.args(&["-c", "color.status=always", "status"]);
+            command.args(["-c", "color.status=always", "status"]);
+        })
+    }
+
+    fn reflog(&self) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.args(["reflog", "--no-color"]);
         })
     }
 
     fn current_export(&self) -> Box<dyn ActionTask> {
         task(self, |command| {
-            command.args(&["show", "--color"]);
+            command.args(["show", "--color"]);
         })
     }
 
@@ -117,28 +498,46 @@ impl VersionControlActions for GitActions {
         task(self, |command| {
             let count_str = format!("-{}", count);
             let template =
-                "--format=format:%x1e%h%x1e%as%x1e%<(10,trunc)%aN%x1e%D%x1e%s";
+                "--format=format:%x1e%H%x1f%h%x1f%P%x1f%aN%x1f%as%x1f%D%x1f%x1f%s";
             command
                 .arg("log")
                 .arg("--all")
                 .arg("--decorate")
-                .arg("--oneline")
                 .arg("--graph")
                 .arg(&count_str)
                 .arg(template);
         })
     }
 
+    fn blame(
+        &self,
+        filename: &str,
+        revision: Option<&str>,
+    ) -> Box<dyn ActionTask> {
+        let blame = task(self, |command| {
+            command.args(["blame", "--porcelain"]);
+            command.args(revision);
+            command.arg("--").arg(filename);
+        });
+        map_output(blame, format_blame_porcelain)
+    }
+
+    fn get_parent_revision(&self, revision: &str) -> Result<String, VcsError> {
+        let output = self.run_command(
+            self.command()
+                .args(["rev-parse", "--verify"])
+                .arg(format!("{}^", revision)),
+        )?;
+        Ok(String::from(output.trim()))
+    }
+
     fn current_diff_all(&self) -> Box<dyn ActionTask> {
         task(self, |command| {
-            command.args(&["diff", "--color"]);
+            command.args(["diff", "--color"]);
         })
     }
 
-    fn current_diff_selected(
-        &self,
-        entries: &Vec<Entry>,
-    ) -> Box<dyn ActionTask> {
+    fn current_diff_selected(&self, entries: &[Entry]) -> Box<dyn ActionTask> {
         task(self, |command| {
             command.arg("diff").arg("--color").arg("--");
             for e in entries.iter().filter(|e| e.selected) {
@@ -170,7 +569,7 @@ impl VersionControlActions for GitActions {
     fn revision_diff_selected(
         &self,
         target: &str,
-        entries: &Vec<Entry>,
+        entries: &[Entry],
     ) -> Box<dyn ActionTask> {
         task(self, |command| {
             let mut parents = String::from(target);
@@ -192,7 +591,7 @@ impl VersionControlActions for GitActions {
     fn commit_all(&self, message: &str) -> Box<dyn ActionTask> {
         let mut tasks = task_vec();
         tasks.push(task(self, |command| {
-            command.args(&["add", "--all"]);
+            command.args(["add", "--all"]);
         }));
         tasks.push(task(self, |command| {
             command.arg("commit").arg("-m").arg(message);
@@ -203,7 +602,7 @@ impl VersionControlActions for GitActions {
     fn commit_selected(
         &self,
         message: &str,
-        entries: &Vec<Entry>,
+        entries: &[Entry],
     ) -> Box<dyn ActionTask> {
         let mut tasks = task_vec();
         for e in entries.iter().filter(|e| e.selected) {
@@ -218,18 +617,73 @@ impl VersionControlActions for GitActions {
         serial(tasks)
     }
 
+    fn get_current_patch(
+        &self,
+        entries: &[Entry],
+    ) -> Result<Vec<FilePatch>, VcsError> {
+        let mut command = self.command();
+        command.args(["diff", "--no-color", "--no-ext-diff", "--"]);
+        for e in entries.iter().filter(|e| e.selected) {
+            command.arg(&e.filename);
+        }
+        let output = self.run_command(&mut command)?;
+        Ok(parse_patch(&output))
+    }
+
+    fn commit_hunks(
+        &self,
+        message: &str,
+        files: &[FilePatch],
+    ) -> Box<dyn ActionTask> {
+        let mut tasks = task_vec();
+        tasks.push(task_with_input(self, format_selected(files), |command| {
+            command.args(["apply", "--cached", "-"]);
+        }));
+        tasks.push(task(self, |command| {
+            command.arg("commit").arg("-m").arg(message);
+        }));
+        chain(tasks)
+    }
+
+    fn get_last_commit_message(&self) -> Result<String, VcsError> {
+        self.run_command(self.command().args(["log", "-1", "--format=%B"]))
+    }
+
+    fn is_last_commit_published(&self) -> Result<bool, VcsError> {
+        let output = self.run_command(
+            self.command()
+                .args(["branch", "-r", "--contains"])
+                .arg("HEAD"),
+        )?;
+        Ok(!output.trim().is_empty())
+    }
+
+    fn amend(&self, message: &str, entries: &[Entry]) -> Box<dyn ActionTask> {
+        let mut tasks = task_vec();
+        for e in entries.iter().filter(|e| e.selected) {
+            tasks.push(task(self, |command| {
+                command.arg("add").arg("--").arg(&e.filename);
+            }));
+        }
+
+        tasks.push(task(self, |command| {
+            command.arg("commit").arg("--amend").arg("-m").arg(message);
+        }));
+        chain(tasks)
+    }
+
     fn revert_all(&self) -> Box<dyn ActionTask> {
         let mut tasks = task_vec();
         tasks.push(task(self, |command| {
-            command.args(&["reset", "--hard"]);
+            command.args(["reset", "--hard"]);
         }));
         tasks.push(task(self, |command| {
-            command.args(&["clean", "-df"]);
+            command.args(["clean", "-df"]);
         }));
         serial(tasks)
     }
 
-    fn revert_selected(&self, entries: &Vec<Entry>) -> Box<dyn ActionTask> {
+    fn revert_selected(&self, entries: &[Entry]) -> Box<dyn ActionTask> {
         let mut tasks = task_vec();
         for e in entries.iter().filter(|e| e.selected) {
             match e.state {
@@ -269,83 +723,773 @@ impl VersionControlActions for GitActions {
         })
     }
 
+    fn rebase(&self, target: &str) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.arg("rebase").arg(target);
+        })
+    }
+
+    fn rebase_continue(&self) -> Box<dyn ActionTask> {
+        let mut tasks = task_vec();
+        tasks.push(self.add_unmerged());
+        let dir = git_dir(&self.current_dir).join(HISTORY_EDIT_DIR);
+        let editor = messages_editor(&dir);
+        tasks.push(task(self, |command| {
+            command.env("GIT_EDITOR", editor.as_deref().unwrap_or("true"));
+            command.args(["rebase", "--continue"]);
+        }));
+        tasks.push(self.forget_history_edit());
+        chain(tasks)
+    }
+
+    fn rebase_skip(&self) -> Box<dyn ActionTask> {
+        let mut tasks = task_vec();
+        tasks.push(task(self, |command| {
+            command.args(["rebase", "--skip"]);
+        }));
+        tasks.push(self.forget_history_edit());
+        chain(tasks)
+    }
+
+    fn rebase_abort(&self) -> Box<dyn ActionTask> {
+        let mut tasks = task_vec();
+        tasks.push(task(self, |command| {
+            command.args(["rebase", "--abort"]);
+        }));
+        tasks.push(self.forget_history_edit());
+        chain(tasks)
+    }
+
+    fn is_rebase_in_progress(&self) -> bool {
+        let dir = git_dir(&self.current_dir);
+        dir.join("rebase-merge").exists() || dir.join("rebase-apply").exists()
+    }
+
+    fn cherry_pick(&self, revisions: &[String]) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.arg("cherry-pick").args(revisions);
+        })
+    }
+
+    fn cherry_pick_continue(&self) -> Box<dyn ActionTask> {
+        let mut tasks = task_vec();
+        tasks.push(self.add_unmerged());
+        tasks.push(task(self, |command| {
+            command.env("GIT_EDITOR", "true");
+            command.args(["cherry-pick", "--continue"]);
+        }));
+        chain(tasks)
+    }
+
+    fn cherry_pick_abort(&self) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.args(["cherry-pick", "--abort"]);
+        })
+    }
+
+    fn is_cherry_pick_in_progress(&self) -> bool {
+        git_dir(&self.current_dir).join("CHERRY_PICK_HEAD").exists()
+    }
+
+    fn backout(&self, revision: &str, message: &str) -> Box<dyn ActionTask> {
+        let git = self.clone();
+        let (revision, pending_message) =
+            (String::from(revision), String::from(message));
+        let mut tasks = task_vec();
+        tasks.push(background(move || {
+            git.revert_without_commit(&revision, &pending_message)
+        }));
+        tasks.push(task(self, |command| {
+            command.arg("commit").arg("-m").arg(message);
+        }));
+        chain(tasks)
+    }
+
+    fn is_backout_in_progress(&self) -> bool {
+        git_dir(&self.current_dir).join("REVERT_HEAD").exists()
+    }
+
+    fn get_backout_message(&self) -> Option<String> {
+        if !self.is_backout_in_progress() {
+            return None;
+        }
+        let path = git_dir(&self.current_dir).join("MERGE_MSG");
+        let message = strip_comments(&fs::read_to_string(path).ok()?);
+        Some(message).filter(|m| !m.is_empty())
+    }
+
+    fn bisect_start(&self, bad: &str, good: &str) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.args(["bisect", "start", bad, good]);
+        })
+    }
+
+    fn bisect_good(&self) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.args(["bisect", "good"]);
+        })
+    }
+
+    fn bisect_bad(&self) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.args(["bisect", "bad"]);
+        })
+    }
+
+    fn bisect_skip(&self) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.args(["bisect", "skip"]);
+        })
+    }
+
+    fn bisect_run(
+        &self,
+        bisect_command: &str,
+        args: &[String],
+    ) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.args(["bisect", "run", bisect_command]).args(args);
+        })
+    }
+
+    fn bisect_reset(&self) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.args(["bisect", "reset"]);
+        })
+    }
+
+    fn is_bisect_in_progress(&self) -> bool {
+        git_dir(&self.current_dir).join("BISECT_START").exists()
+    }
+
+    fn get_history(&self, base: &str) -> Result<Vec<PlanEntry>, VcsError> {
+        let output = self.run_command(
+            self.command()
+                .args(["log", "--reverse", "--no-merges"])
+                .arg("--format=%H%x1f%h%x1f%B%x1e")
+                .arg(format!("{}..HEAD", base)),
+        )?;
+        Ok(PlanEntry::parse_all(&output))
+    }
+
+    fn edit_history(
+        &self,
+        base: &str,
+        plan: &[PlanEntry],
+    ) -> Box<dyn ActionTask> {
+        let todo = format_rebase_todo(plan);
+        let dir = git_dir(&self.current_dir).join(HISTORY_EDIT_DIR);
+        let editors = match write_plan(&dir, &todo, plan) {
+            Ok(editors) => editors,
+            Err(error) => {
+                return ready(ActionResult::from_err(error.to_string()))
+            }
+        };
+        let mut tasks = task_vec();
+        tasks.push(task(self, |command| {
+            command.env("GIT_SEQUENCE_EDITOR", &editors.plan);
+            command.env("GIT_EDITOR", &editors.messages);
+            command.args(["rebase", "--interactive"]).arg(base);
+        }));
+        tasks.push(self.forget_history_edit());
+        chain(tasks)
+    }
+
     fn conflicts(&self) -> Box<dyn ActionTask> {
         task(self, |command| {
-            command.args(&["diff", "--name-only", "--diff-filter=U"]);
+            command.args(["diff", "--name-only", "--diff-filter=U"]);
         })
     }
 
     fn take_other(&self) -> Box<dyn ActionTask> {
         task(self, |command| {
-            command.args(&["checkout", ".", "--theirs"]);
+            command.args(["checkout", ".", "--theirs"]);
         })
     }
 
     fn take_local(&self) -> Box<dyn ActionTask> {
         task(self, |command| {
-            command.args(&["checkout", ".", "--ours"]);
+            command.args(["checkout", ".", "--ours"]);
         })
     }
 
-    fn fetch(&self) -> Box<dyn ActionTask> {
+    fn fetch(&self, remote: Option<&str>) -> Box<dyn ActionTask> {
         task(self, |command| {
-            command.args(&["fetch", "--all"]);
+            command.arg("fetch");
+            match remote {
+                Some(remote) => command.arg(remote),
+                None => command.arg("--all"),
+            };
         })
     }
 
-    fn pull(&self) -> Box<dyn ActionTask> {
+    fn pull(&self, remote: Option<&str>) -> Box<dyn ActionTask> {
+        // a remote other than the upstream one needs the branch to merge
+        let branch = remote.and_then(|_| self.current_branch());
         task(self, |command| {
-            command.args(&["pull", "--all"]);
+            command.arg("pull");
+            match remote {
+                Some(remote) => command.arg(remote).args(branch),
+                None => command.arg("--all"),
+            };
         })
     }
 
-    fn push(&self) -> Box<dyn ActionTask> {
+    fn push(&self, remote: Option<&str>) -> Box<dyn ActionTask> {
         task(self, |command| {
             command.arg("push");
+            if let Some(remote) = remote {
+                command.arg(remote).arg("HEAD");
+            }
+        })
+    }
+
+    fn list_remotes(&self) -> Box<dyn ActionTask> {
+        let list = task(self, |command| {
+            command.args(["remote", "--verbose"]);
+        });
+        map_output(list, |output| {
+            let mut remotes = String::new();
+            for line in output.lines() {
+                if let Some(remote) = line.strip_suffix(" (fetch)") {
+                    remotes.push_str(&remote.replace('\t', " "));
+                    remotes.push('\n');
+                }
+            }
+            remotes
+        })
+    }
+
+    fn add_remote(&self, name: &str, url: &str) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.args(["remote", "add", name, url]);
         })
     }
 
-    fn create_tag(&self, name: &str) -> Box<dyn ActionTask> {
+    fn rename_remote(&self, name: &str, new_name: &str) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.args(["remote", "rename", name, new_name]);
+        })
+    }
+
+    fn remove_remote(&self, name: &str) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.args(["remote", "remove", name]);
+        })
+    }
+
+    fn set_remote_url(&self, name: &str, url: &str) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.args(["remote", "set-url", name, url]);
+        })
+    }
+
+    fn get_upstream_remote(&self) -> Option<String> {
+        let branch = self.current_branch()?;
+        let remote = self
+            .run_command(
+                self.command()
+                    .args(["config", "--get"])
+                    .arg(format!("branch.{}.remote", branch)),
+            )
+            .ok()?;
+        Some(String::from(remote.trim()))
+    }
+
+    fn get_default_remote(&self) -> Option<String> {
+        if let Some(remote) = self.get_upstream_remote() {
+            return Some(remote);
+        }
+        let remotes = self.run_command(self.command().arg("remote")).ok()?;
+        let mut remotes = remotes.lines();
+        let first = remotes.next()?;
+        let origin = std::iter::once(first)
+            .chain(remotes)
+            .find(|r| *r == "origin");
+        Some(String::from(origin.unwrap_or(first)))
+    }
+
+    fn upstream(&self) -> Box<dyn ActionTask> {
         let mut tasks = task_vec();
         tasks.push(task(self, |command| {
-            command.arg("tag").arg(name).arg("-f");
+            command.args([
+                "rev-parse",
+                "--abbrev-ref",
+                "--symbolic-full-name",
+                "@{upstream}",
+            ]);
         }));
         tasks.push(task(self, |command| {
-            command.arg("push").arg("origin").arg(name);
+            command.args([
+                "rev-list",
+                "--left-right",
+                "--count",
+                "HEAD...@{upstream}",
+            ]);
         }));
-        serial(tasks)
+        map_output(parallel(tasks), |output| {
+            parse_upstream(&output)
+                .map(|upstream| upstream.to_string())
+                .unwrap_or_default()
+        })
+    }
+
+    fn publishes_refs(&self) -> bool {
+        true
+    }
+
+    fn list_tags(&self) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.args([
+                "for-each-ref",
+                "refs/tags",
+                "--sort=-creatordate",
+                concat!(
+                    "--format=%(refname:short) ",
+                    "%(if)%(*objectname)%(then)%(*objectname:short)",
+                    "%(else)%(objectname:short)%(end) ",
+                    "%(creatordate:short) ",
+                    "%(if:equals=tag)%(objecttype)%(then)%(contents:subject)%(end)",
+                ),
+            ]);
+        })
+    }
+
+    fn get_tags(&self) -> Result<Vec<String>, VcsError> {
+        let output =
+            self.run_command(self.command().args(["tag", "--list"]))?;
+        Ok(output.lines().map(String::from).collect())
+    }
+
+    fn create_tag(
+        &self,
+        name: &str,
+        message: Option<&str>,
+        force: bool,
+        remote: Option<&str>,
+    ) -> Box<dyn ActionTask> {
+        let mut tasks = task_vec();
+        tasks.push(task(self, |command| {
+            command.arg("tag");
+            if let Some(message) = message {
+                command.arg("-a").arg("-m").arg(message);
+            }
+            if force {
+                command.arg("-f");
+            }
+            command.arg(name);
+        }));
+        if let Some(remote) = remote {
+            let refspec = if force {
+                format!("+refs/tags/{}", name)
+            } else {
+                format!("refs/tags/{}", name)
+            };
+            tasks.push(task(self, |command| {
+                command.arg("push").arg(remote).arg(refspec);
+            }));
+        }
+        chain(tasks)
+    }
+
+    fn delete_tag(
+        &self,
+        name: &str,
+        remote: Option<&str>,
+    ) -> Box<dyn ActionTask> {
+        let mut tasks = task_vec();
+        tasks.push(task(self, |command| {
+            command.arg("tag").arg("-d").arg(name);
+        }));
+        if let Some(remote) = remote {
+            tasks.push(task(self, |command| {
+                command
+                    .arg("push")
+                    .arg("-d")
+                    .arg(remote)
+                    .arg(format!("refs/tags/{}", name));
+            }));
+        }
+        chain(tasks)
+    }
+
+    fn list_submodules(&self) -> Box<dyn ActionTask> {
+        let mut tasks = task_vec();
+        tasks.push(task(self, |command| {
+            command.args(["submodule", "status", "--recursive"]);
+        }));
+        let recorded = task(self, |command| {
+            command.args(["submodule", "status", "--recursive", "--cached"]);
+        });
+        tasks
+            .push(map_output(recorded, |output| tag_lines(output, "recorded")));
+        let dirty = task(self, |command| {
+            command.args(["submodule", "foreach", "--recursive", "--quiet"]);
+            command.arg(concat!(
+                "if test -n \"$(git status --porcelain)\"; ",
+                "then echo \"$displaypath\"; fi"
+            ));
+        });
+        tasks.push(map_output(dirty, |output| tag_lines(output, "dirty")));
+        map_output(parallel(tasks), format_submodules)
+    }
+
+    fn init_submodules(&self) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.args(["submodule", "init"]);
+        })
+    }
+
+    fn update_submodules(&self) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.args(["submodule", "update", "--init", "--recursive"]);
+        })
+    }
+
+    fn sync_submodules(&self) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.args(["submodule", "sync", "--recursive"]);
+        })
+    }
+
+    fn get_superproject_root(&self) -> Result<String, VcsError> {
+        let output = self.run_command(
+            self.command()
+                .args(["rev-parse", "--show-superproject-working-tree"]),
+        )?;
+        match output.lines().next() {
+            Some(root) if !root.is_empty() => Ok(String::from(root)),
+            _ => Err(VcsError::CommandFailed {
+                code: None,
+                stderr: String::from("not inside a submodule"),
+            }),
+        }
+    }
+
+    fn list_worktrees(&self) -> Box<dyn ActionTask> {
+        let list = task(self, |command| {
+            command.args(["worktree", "list", "--porcelain"]);
+        });
+        map_output(list, format_worktree_porcelain)
+    }
+
+    fn add_worktree(&self, path: &str, target: &str) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.args(["worktree", "add", path, target]);
+        })
+    }
+
+    fn remove_worktree(&self, path: &str) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.args(["worktree", "remove", path]);
+        })
+    }
+
+    fn prune_worktrees(&self) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.args(["worktree", "prune", "--verbose"]);
+        })
     }
 
     fn list_branches(&self) -> Box<dyn ActionTask> {
         task(self, |command| {
-            command.args(&["branch", "--all", "--format=%(refname:short)"]);
+            command.args([
+                "branch",
+                "--all",
+                concat!(
+                    "--format=%(refname:short)",
+                    "%(if)%(upstream)%(then) %(upstream:short)",
+                    "%(if)%(upstream:track)%(then) %(upstream:track)%(end)",
+                    "%(end)",
+                ),
+            ]);
         })
     }
 
-    fn create_branch(&self, name: &str) -> Box<dyn ActionTask> {
+    fn create_branch(
+        &self,
+        name: &str,
+        remote: Option<&str>,
+    ) -> Box<dyn ActionTask> {
         let mut tasks = task_vec();
         tasks.push(task(self, |command| {
             command.arg("branch").arg(name);
         }));
         tasks.push(self.update(name));
-        tasks.push(task(self, |command| {
-            command
-                .arg("push")
-                .arg("--set-upstream")
-                .arg("origin")
-                .arg(name);
-        }));
+        if let Some(remote) = remote {
+            tasks.push(task(self, |command| {
+                command
+                    .arg("push")
+                    .arg("--set-upstream")
+                    .arg(remote)
+                    .arg(name);
+            }));
+        }
         serial(tasks)
     }
 
-    fn close_branch(&self, name: &str) -> Box<dyn ActionTask> {
+    fn create_branch_at(
+        &self,
+        name: &str,
+        revision: &str,
+    ) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.arg("branch").arg(name).arg(revision);
+        })
+    }
+
+    fn close_branch(
+        &self,
+        name: &str,
+        remote: Option<&str>,
+    ) -> Box<dyn ActionTask> {
         let mut tasks = task_vec();
         tasks.push(task(self, |command| {
             command.arg("branch").arg("-d").arg(name);
         }));
-        tasks.push(task(self, |command| {
-            command.arg("push").arg("-d").arg("origin").arg(name);
-        }));
+        if let Some(remote) = remote {
+            tasks.push(task(self, |command| {
+                command.arg("push").arg("-d").arg(remote).arg(name);
+            }));
+        }
         serial(tasks)
     }
+
+    fn list_stashes(&self) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.args(["stash", "list", "--format=%gd %s"]);
+        })
+    }
+
+    fn stash_all(&self, message: &str) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.args(["stash", "push", "--include-untracked"]);
+            if !message.is_empty() {
+                command.arg("-m").arg(message);
+            }
+        })
+    }
+
+    fn stash_selected(
+        &self,
+        message: &str,
+        entries: &[Entry],
+    ) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.args(["stash", "push", "--include-untracked"]);
+            if !message.is_empty() {
+                command.arg("-m").arg(message);
+            }
+            command.arg("--");
+            for e in entries.iter().filter(|e| e.selected) {
+                command.arg(&e.filename);
+            }
+        })
+    }
+
+    fn show_stash(&self, name: &str) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command
+                .args(["-c", "color.diff=always", "stash", "show", "-p"])
+                .arg(name);
+        })
+    }
+
+    fn apply_stash(&self, name: &str) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.args(["stash", "apply"]).arg(name);
+        })
+    }
+
+    fn pop_stash(&self, name: &str) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.args(["stash", "pop"]).arg(name);
+        })
+    }
+
+    fn drop_stash(&self, name: &str) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.args(["stash", "drop"]).arg(name);
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history_edit::PlanAction;
+
+    const BLAME_PORCELAIN: &str = "\
+1111111111111111111111111111111111111111 1 1 2
+author Alice
+author-mail <alice@example.com>
+author-time 1704164645
+author-tz +0100
+committer Alice
+committer-mail <alice@example.com>
+committer-time 1704164645
+committer-tz +0100
+summary add the parser
+filename src/lib.rs
+\tfn parse() {
+1111111111111111111111111111111111111111 2 2
+\t    todo!()
+2222222222222222222222222222222222222222 3 3 1
+author Bob
+author-mail <bob@example.com>
+author-time 1704151800
+author-tz +0100
+committer Bob
+committer-mail <bob@example.com>
+committer-time 1704151800
+committer-tz +0100
+summary close the brace
+previous 1111111111111111111111111111111111111111 src/lib.rs
+filename src/lib.rs
+\t}
+";
+
+    #[test]
+    fn blame_porcelain_reuses_the_details_of_repeated_commits() {
+        let blame = format_blame_porcelain(String::from(BLAME_PORCELAIN));
+        let lines = BlameLine::parse_all(&blame);
+        assert_eq!(lines.len(), 3);
+
+        let first = &lines[0];
+        assert_eq!(first.revision, "1".repeat(40));
+        assert_eq!(first.short_revision, "1111111");
+        assert_eq!(first.author, "Alice");
+        assert_eq!(first.date, "2024-01-02");
+        assert_eq!(first.line_number, 1);
+        assert_eq!(first.text, "fn parse() {");
+
+        // the repeated header has no metadata block
+        let second = &lines[1];
+        assert_eq!(second.revision, first.revision);
+        assert_eq!(second.author, "Alice");
+        assert_eq!(second.date, "2024-01-02");
+        assert_eq!(second.line_number, 2);
+        assert_eq!(second.text, "    todo!()");
+
+        // 2024-01-01 23:30 UTC is already the next day in +0100
+        let third = &lines[2];
+        assert_eq!(third.short_revision, "2222222");
+        assert_eq!(third.author, "Bob");
+        assert_eq!(third.date, "2024-01-02");
+        assert_eq!(third.line_number, 3);
+        assert_eq!(third.text, "}");
+    }
+
+    #[test]
+    fn empty_blame_has_no_lines() {
+        assert!(format_blame_porcelain(String::new()).is_empty());
+    }
+
+    #[test]
+    fn worktrees_show_their_branch_revision_or_bareness() {
+        let output = "\
+worktree /repo/main
+HEAD 89abcdef0123456789abcdef0123456789abcdef
+branch refs/heads/main
+
+worktree /repo/detached
+HEAD 0123456789abcdef0123456789abcdef01234567
+detached
+
+worktree /repo/bare.git
+bare
+
+worktree /repo/feature
+HEAD fedcba9876543210fedcba9876543210fedcba98
+branch refs/heads/feature/nested
+locked
+
+";
+        let worktrees = format_worktree_porcelain(String::from(output));
+        assert_eq!(
+            worktrees,
+            "/repo/main [main]\n\
+             /repo/detached (0123456)\n\
+             /repo/bare.git (bare)\n\
+             /repo/feature [feature/nested]\n"
+        );
+    }
+
+    #[test]
+    fn submodules_combine_status_recorded_and_dirty_lines() {
+        let output = "\
+ 1111111111111111111111111111111111111111 libs/clean (v1.0)
++2222222222222222222222222222222222222222 libs/moved (heads/main)
+ 3333333333333333333333333333333333333333 libs/dirty (v2.0-1-g3333333)
+-4444444444444444444444444444444444444444 libs/new
+Uaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa libs/conflicted
+recorded  1111111111111111111111111111111111111111 libs/clean (v1.0)
+recorded +5555555555555555555555555555555555555555 libs/moved (v0.9)
+recorded  3333333333333333333333333333333333333333 libs/dirty (v2.0)
+recorded -4444444444444444444444444444444444444444 libs/new
+dirty libs/dirty
+";
+        let submodules = format_submodules(String::from(output));
+        assert_eq!(
+            submodules,
+            "libs/clean 1111111 1111111 clean\n\
+             libs/moved 2222222 5555555 moved\n\
+             libs/dirty 3333333 3333333 dirty\n\
+             libs/new - 4444444 uninitialized\n\
+             libs/conflicted aaaaaaa  conflicted\n"
+        );
+    }
+
+    #[test]
+    fn submodule_paths_may_contain_spaces() {
+        assert_eq!(
+            parse_submodule_status(" 1234 my libs/a (v1)"),
+            Some((' ', "1234", "my libs/a"))
+        );
+        assert_eq!(
+            parse_submodule_status("-1234 my libs/b"),
+            Some(('-', "1234", "my libs/b"))
+        );
+    }
+
+    #[test]
+    fn upstream_is_read_with_its_ahead_and_behind_counts() {
+        // as joined by parallel from rev-parse and rev-list --left-right
+        let upstream = parse_upstream("\norigin/main\n\n3\t1\n").unwrap();
+        assert_eq!(upstream.name, "origin/main");
+        assert_eq!(upstream.ahead, 3);
+        assert_eq!(upstream.behind, 1);
+        assert_eq!(upstream.to_string(), "origin/main [ahead 3, behind 1]");
+
+        let even = parse_upstream("\norigin/main\n\n0\t0\n").unwrap();
+        assert_eq!(even.to_string(), "origin/main");
+        let behind = parse_upstream("\nfork/topic\n\n0\t2\n").unwrap();
+        assert_eq!(behind.to_string(), "fork/topic [behind 2]");
+    }
+
+    #[test]
+    fn missing_or_gone_upstream_is_none() {
+        // no upstream configured, both commands print nothing
+        assert!(parse_upstream("\n\n").is_none());
+        // the upstream branch was deleted so there is nothing to count
+        assert!(parse_upstream("\norigin/gone\n\n").is_none());
+        assert!(parse_upstream("\norigin/main\n\nfatal\n").is_none());
+    }
+
+    #[test]
+    fn plan_becomes_a_rebase_todo_list() {
+        let mut plan = PlanEntry::parse_all(
+            "aaaa\x1faa\x1ffirst\n\x1ebbbb\x1fbb\x1fsecond\n\nbody\n\x1e\
+             cccc\x1fcc\x1fthird\n\x1edddd\x1fdd\x1ffourth\n\x1e",
+        );
+        plan[1].action = PlanAction::Reword;
+        plan[1].new_message = String::from("second, reworded\n\nbody");
+        plan[2].action = PlanAction::Fixup;
+        plan[3].action = PlanAction::Drop;
+        assert_eq!(
+            format_rebase_todo(&plan),
+            "pick aaaa first\nreword bbbb second, reworded\n\
+             fixup cccc third\ndrop dddd fourth\n"
+        );
+    }
 }
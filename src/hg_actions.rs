@@ -1,7 +1,22 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
 use crate::{
-    action::{parallel, serial, task_vec, ActionTask},
+    action::{
+        background, chain, lazy, map_output, parallel, ready, serial, task_vec,
+        ActionResult, ActionTask,
+    },
+    history_edit::{messages_editor, write_plan, PlanAction, PlanEntry},
+    patch::{format_selected, parse_patch, FilePatch},
     select::{Entry, State},
-    version_control_actions::{handle_command, task, VersionControlActions},
+    vcs_error::{ErrorMessages, VcsError},
+    version_control_actions::{
+        task, task_with_input, unsupported, unsupported_error, Capability,
+        Support, Upstream, VersionControlActions,
+    },
 };
 
 fn str_to_state(s: &str) -> State {
@@ -17,11 +32,222 @@ fn str_to_state(s: &str) -> State {
     }
 }
 
+const ERROR_MESSAGES: ErrorMessages = ErrorMessages {
+    not_a_repository: &["no repository found"],
+    merge_conflict: &[
+        "unresolved conflicts",
+        "unresolved merge conflicts",
+        "outstanding uncommitted merge",
+    ],
+    auth_failure: &[
+        "authorization failed",
+        "HTTP Error 401",
+        "HTTP Error 403",
+        "Permission denied (publickey",
+    ],
+};
+
+/// Where the plan of a history edit is kept until histedit is done
+const HISTORY_EDIT_DIR: &str = "verco-history-edit";
+
+/// Where the message of a backout stopped on conflicts is kept until it is
+/// committed
+const BACKOUT_MESSAGE_FILE: &str = "verco-backout-message";
+
+/// Writes back the contents of files as they were before a commit, removing
+/// the ones that did not exist
+fn restore_files(files: Vec<(PathBuf, Option<Vec<u8>>)>) -> ActionResult {
+    for (path, contents) in files {
+        let result = match contents {
+            Some(contents) => fs::write(&path, contents),
+            None if path.exists() => fs::remove_file(&path),
+            None => Ok(()),
+        };
+        if let Err(error) = result {
+            return ActionResult::from_err(format!(
+                "could not restore {}: {}",
+                path.display(),
+                error
+            ));
+        }
+    }
+    ActionResult::from_ok(String::new())
+}
+
+/// Writes `plan` as the commands of `hg histedit --commands`
+fn format_histedit_commands(plan: &[PlanEntry]) -> String {
+    let mut commands = String::new();
+    for entry in plan {
+        let action = match entry.action {
+            PlanAction::Pick => "pick",
+            PlanAction::Reword => "mess",
+            PlanAction::Squash => "fold",
+            PlanAction::Fixup => "roll",
+            PlanAction::Drop => "drop",
+        };
+        commands.push_str(&format!("{} {}\n", action, entry.revision));
+    }
+    commands
+}
+
+/// Puts back the working copy saved by `commit_hunks` and has
+/// `add_untracked` add back the `forgotten` files a failed commit left
+/// untracked
+fn restore_working_copy<F>(
+    files: Vec<(PathBuf, Option<Vec<u8>>)>,
+    forgotten: &[String],
+    add_untracked: F,
+) -> ActionResult
+where
+    F: FnOnce(&[String]) -> Result<(), VcsError>,
+{
+    let restored = restore_files(files);
+    if forgotten.is_empty() {
+        return restored;
+    }
+    match add_untracked(forgotten) {
+        Ok(()) => restored,
+        Err(error) => ActionResult::from_error(error),
+    }
+}
+
+#[derive(Clone)]
 pub struct HgActions {
     pub current_dir: String,
 }
 
-impl<'a> VersionControlActions for HgActions {
+/// Lists the `branch` tagged lines of `hg branches` with how many of the
+/// `draft` tagged lines of `hg log` are on them, drafts being the changesets
+/// not pushed yet
+fn format_branches(output: String) -> String {
+    let mut branches = Vec::new();
+    let mut drafts = HashMap::<&str, usize>::new();
+    for line in output.lines() {
+        if let Some(branch) = line.strip_prefix("branch ") {
+            branches.push(branch);
+        } else if let Some(branch) = line.strip_prefix("draft ") {
+            *drafts.entry(branch).or_default() += 1;
+        }
+    }
+
+    let mut formatted = String::new();
+    for branch in branches {
+        formatted.push_str(branch);
+        if let Some(ahead) = drafts.get(branch) {
+            formatted.push_str(&format!(" [ahead {}]", ahead));
+        }
+        formatted.push('\n');
+    }
+    formatted
+}
+
+/// Reads the subrepo paths in `.hgsub` along with the revisions
+/// `.hgsubstate` records for them
+fn recorded_subrepos(root: &Path) -> Vec<(String, String)> {
+    let hgsub = fs::read_to_string(root.join(".hgsub")).unwrap_or_default();
+    let hgsubstate =
+        fs::read_to_string(root.join(".hgsubstate")).unwrap_or_default();
+    let recorded: HashMap<_, _> = hgsubstate
+        .lines()
+        .filter_map(|l| l.split_once(' '))
+        .map(|(revision, path)| (path, revision))
+        .collect();
+
+    // entries under a section such as [subpaths] are not subrepos
+    hgsub
+        .lines()
+        .take_while(|l| !l.starts_with('['))
+        .filter_map(|l| l.split_once('='))
+        .map(|(path, _)| {
+            let path = path.trim();
+            let revision = recorded.get(path).copied().unwrap_or_default();
+            (String::from(path), String::from(revision))
+        })
+        .collect()
+}
+
+/// Describes a subrepo from its `recorded` revision and the output of
+/// `hg identify` in it, which ends with a `+` if it has changes, or `None`
+/// if it is not cloned yet
+fn describe_subrepo(
+    path: &str,
+    recorded: &str,
+    identify: Option<&str>,
+) -> String {
+    fn short(revision: &str) -> &str {
+        revision.get(..12).unwrap_or(revision)
+    }
+    let recorded = short(recorded);
+    let (checked_out, state) = match identify.map(str::trim) {
+        Some(identify) => {
+            let revision = identify.trim_end_matches('+');
+            let checked_out = short(revision);
+            let state = match (checked_out != recorded, revision != identify) {
+                (false, false) => "clean",
+                (false, true) => "dirty",
+                (true, false) => "moved",
+                (true, true) => "moved,dirty",
+            };
+            (checked_out, state)
+        }
+        None => ("-", "uninitialized"),
+    };
+    format!("{} {} {} {}\n", path, checked_out, recorded, state)
+}
+
+impl HgActions {
+    fn hg_dir(&self) -> PathBuf {
+        Path::new(&self.current_dir).join(".hg")
+    }
+
+    fn is_history_edit_in_progress(&self) -> bool {
+        self.hg_dir().join("histedit-state").exists()
+    }
+
+    /// Backs out `revision` into the working copy, keeping `message` for
+    /// the commit if it stops on conflicts
+    fn backout_without_commit(
+        &self,
+        revision: &str,
+        message: &str,
+    ) -> ActionResult {
+        let path = self.hg_dir().join(BACKOUT_MESSAGE_FILE);
+        let _ = fs::remove_file(&path);
+        match self.run_command(
+            self.command()
+                .args(["backout", "--no-commit", "--rev"])
+                .arg(revision),
+        ) {
+            Ok(output) => ActionResult::from_ok(output),
+            Err(error) => {
+                if self.hg_dir().join("merge").exists() {
+                    let _ = fs::write(path, format!("{}\n", message));
+                }
+                ActionResult::from_error(error)
+            }
+        }
+    }
+}
+
+impl HgActions {
+    /// Adds those of `paths` that are not tracked
+    fn add_untracked(&self, paths: &[String]) -> Result<(), VcsError> {
+        let untracked = self.run_command(
+            self.command()
+                .args(["status", "--unknown", "--ignored", "--no-status", "--"])
+                .args(paths),
+        )?;
+        let untracked: Vec<_> = untracked.lines().collect();
+        if !untracked.is_empty() {
+            self.run_command(
+                self.command().arg("add").arg("--").args(untracked),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl VersionControlActions for HgActions {
     fn executable_name(&self) -> &'static str {
         "hg"
     }
@@ -30,9 +256,57 @@ impl<'a> VersionControlActions for HgActions {
         &self.current_dir[..]
     }
 
-    fn set_root(&mut self) -> Result<(), String> {
+    fn classify_error(&self, error: VcsError) -> VcsError {
+        error.classify(&ERROR_MESSAGES)
+    }
+
+    fn support(&self, capability: Capability) -> Support {
+        match capability {
+            Capability::Fetch | Capability::Pull => Support::Differs(
+                "runs hg pull which does not update the working directory",
+            ),
+            Capability::CreateTag => Support::Differs(
+                "commits the tag to the current branch with the message as its description",
+            ),
+            Capability::DeleteTag => {
+                Support::Differs("commits the removal of the tag")
+            }
+            Capability::CreateBranch => {
+                Support::Differs("the branch is created by the next commit")
+            }
+            Capability::CloseBranch => {
+                Support::Differs("commits a changeset closing the branch")
+            }
+            Capability::Stash => Support::Differs("uses hg shelve"),
+            Capability::Rebase => {
+                Support::Differs("can't skip changesets while rebasing")
+            }
+            Capability::CherryPick => Support::Differs("uses hg graft"),
+            Capability::Remotes => {
+                Support::Differs("lists hg paths, which are edited in .hg/hgrc")
+            }
+            Capability::Submodules => Support::Differs(
+                "subrepos are cloned and updated along with the parent",
+            ),
+            Capability::Worktrees => {
+                Support::Unsupported("hg shares repositories with hg share")
+            }
+            Capability::Reflog => Support::Differs(
+                "uses hg journal and creates bookmarks at its entries",
+            ),
+            Capability::Bisect => Support::Differs(
+                "resetting keeps the last tested revision checked out",
+            ),
+            Capability::EditHistory => Support::Differs(
+                "uses hg histedit where squash folds and fixup rolls",
+            ),
+            _ => Support::Supported,
+        }
+    }
+
+    fn set_root(&mut self) -> Result<(), VcsError> {
         let mut command = self.command();
-        let dir = handle_command(command.arg("root"))?;
+        let dir = self.run_command(command.arg("root"))?;
 
         let dir = dir
             .lines()
@@ -47,8 +321,12 @@ impl<'a> VersionControlActions for HgActions {
         &self.current_dir[..]
     }
 
-    fn get_current_changed_files(&self) -> Result<Vec<Entry>, String> {
-        let output = handle_command(self.command().arg("status"))?;
+    fn switch_root(&mut self, _dir: &str) -> Result<(), VcsError> {
+        Err(unsupported_error(self, "switching worktrees"))
+    }
+
+    fn get_current_changed_files(&self) -> Result<Vec<Entry>, VcsError> {
+        let output = self.run_command(self.command().arg("status"))?;
 
         let files = output
             .trim()
@@ -67,11 +345,25 @@ impl<'a> VersionControlActions for HgActions {
         Ok(files)
     }
 
+    fn get_tracked_files(&self) -> Result<Vec<Entry>, VcsError> {
+        let output = self.run_command(self.command().arg("files"))?;
+        let files = output
+            .lines()
+            .filter(|f| !f.is_empty())
+            .map(|f| Entry {
+                filename: String::from(f),
+                selected: false,
+                state: State::Clean,
+            })
+            .collect();
+        Ok(files)
+    }
+
     fn get_revision_changed_files(
         &self,
         target: &str,
-    ) -> Result<Vec<Entry>, String> {
-        let output = handle_command(
+    ) -> Result<Vec<Entry>, VcsError> {
+        let output = self.run_command(
             self.command().arg("status").arg("--change").arg(target),
         )?;
 
@@ -92,31 +384,45 @@ impl<'a> VersionControlActions for HgActions {
         Ok(files)
     }
 
-    fn version(&self) -> Result<String, String> {
-        handle_command(self.command().arg("--version"))
+    fn version(&self) -> Result<String, VcsError> {
+        self.run_command(self.command().arg("--version"))
     }
 
     fn status(&self) -> Box<dyn ActionTask> {
         let mut tasks = task_vec();
         tasks.push(task(self, |command| {
-            command.args(&["summary", "--color", "always"]);
+            command.args(["summary", "--color", "always"]);
         }));
         tasks.push(task(self, |command| {
-            command.args(&["status", "--color", "always"]);
+            command.args(["status", "--color", "always"]);
         }));
         parallel(tasks)
     }
 
+    fn reflog(&self) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            // quiet hides the title line so every line starts with a node
+            command.args([
+                "--config",
+                "extensions.journal=",
+                "journal",
+                "--quiet",
+                "--template",
+                "{join(newnodes, ' ')} {date|isodate} {command}\n",
+            ]);
+        })
+    }
+
     fn current_export(&self) -> Box<dyn ActionTask> {
         task(self, |command| {
-            command.args(&["export", "--color", "always"]);
+            command.args(["export", "--color", "always"]);
         })
     }
 
     fn log(&self, count: usize) -> Box<dyn ActionTask> {
         task(self, |command| {
             let count_str = format!("{}", count);
-            let template = "\x1e{node|short}\x1e{date|shortdate}\x1e{author|person}\x1e{ifeq(phase,'secret','(secret) ','')}{ifeq(phase,'draft','(draft) ','')}{if(topics,'[{topics}] ')}{tags % '{tag} '}{branch}\x1e{desc|firstline|strip}";
+            let template = "\x1e{node}\x1f{node|short}\x1f{revset('parents(%d)', rev) % '{node} '}\x1f{author|person}\x1f{date|shortdate}\x1f{if(topics,'[{topics}] ')}{tags % '{tag} '}{bookmarks % '{bookmark} '}\x1f{ifeq(phase,'secret','(secret) ','')}{ifeq(phase,'draft','(draft) ','')}{branch}\x1f{desc|firstline|strip}";
             command
                 .arg("log")
                 .arg("--config")
@@ -129,16 +435,39 @@ impl<'a> VersionControlActions for HgActions {
         })
     }
 
+    fn blame(
+        &self,
+        filename: &str,
+        revision: Option<&str>,
+    ) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            let template = "{lines % '{node}\x1f{node|short}\x1f{user|person}\x1f{date|shortdate}\x1f{lineno}\x1f{line}'}";
+            command.args(["annotate", "-cudl", "--template", template]);
+            if let Some(revision) = revision {
+                command.arg("-r").arg(revision);
+            }
+            command.arg("--").arg(filename);
+        })
+    }
+
+    fn get_parent_revision(&self, revision: &str) -> Result<String, VcsError> {
+        let output = self.run_command(
+            self.command()
+                .arg("log")
+                .arg("-r")
+                .arg(format!("p1({})", revision))
+                .args(["--template", "{node}"]),
+        )?;
+        Ok(String::from(output.trim()))
+    }
+
     fn current_diff_all(&self) -> Box<dyn ActionTask> {
         task(self, |command| {
             command.arg("diff").arg("--color").arg("always");
         })
     }
 
-    fn current_diff_selected(
-        &self,
-        entries: &Vec<Entry>,
-    ) -> Box<dyn ActionTask> {
+    fn current_diff_selected(&self, entries: &[Entry]) -> Box<dyn ActionTask> {
         task(self, |command| {
             command.arg("diff").arg("--color").arg("always").arg("--");
             for e in entries.iter().filter(|e| e.selected) {
@@ -172,7 +501,7 @@ impl<'a> VersionControlActions for HgActions {
     fn revision_diff_selected(
         &self,
         target: &str,
-        entries: &Vec<Entry>,
+        entries: &[Entry],
     ) -> Box<dyn ActionTask> {
         task(self, |command| {
             command
@@ -204,7 +533,7 @@ impl<'a> VersionControlActions for HgActions {
     fn commit_selected(
         &self,
         message: &str,
-        entries: &Vec<Entry>,
+        entries: &[Entry],
     ) -> Box<dyn ActionTask> {
         let mut tasks = task_vec();
         let mut files_to_commit = Vec::new();
@@ -236,18 +565,168 @@ impl<'a> VersionControlActions for HgActions {
         serial(tasks)
     }
 
+    fn get_current_patch(
+        &self,
+        entries: &[Entry],
+    ) -> Result<Vec<FilePatch>, VcsError> {
+        let mut command = self.command();
+        command.args(["diff", "--git", "--color", "never", "--"]);
+        for e in entries.iter().filter(|e| e.selected) {
+            command.arg(&e.filename);
+        }
+        let output = self.run_command(&mut command)?;
+        Ok(parse_patch(&output))
+    }
+
+    fn commit_hunks(
+        &self,
+        message: &str,
+        files: &[FilePatch],
+    ) -> Box<dyn ActionTask> {
+        let files: Vec<_> = files
+            .iter()
+            .filter(|f| f.hunks.iter().any(|h| h.is_selected()))
+            .cloned()
+            .collect();
+        let patch = format_selected(&files);
+
+        // hg can't commit part of a file so the selected changes are
+        // committed from a reverted file and the working copy is restored
+        // afterwards
+        let root = Path::new(&self.current_dir);
+        let working_copy: Vec<_> = files
+            .iter()
+            .map(|f| {
+                let path = root.join(&f.filename);
+                let contents = fs::read(&path).ok();
+                (path, contents)
+            })
+            .collect();
+
+        // reverting an added file would also forget it, so those are
+        // forgotten and removed instead and the patch adds them back
+        let (added, modified): (Vec<_>, Vec<_>) =
+            files.iter().partition(|f| f.is_new_file());
+        let added_paths: Vec<_> =
+            added.iter().map(|f| root.join(&f.filename)).collect();
+        let forgotten: Vec<_> =
+            added.iter().map(|f| f.filename.clone()).collect();
+
+        let mut commit_tasks = task_vec();
+        if !modified.is_empty() {
+            commit_tasks.push(task(self, |command| {
+                command.args(["revert", "--no-backup", "--"]);
+                for f in &modified {
+                    command.arg(&f.filename);
+                }
+            }));
+        }
+        if !added.is_empty() {
+            commit_tasks.push(task(self, |command| {
+                command.args(["forget", "--"]);
+                for f in &added {
+                    command.arg(&f.filename);
+                }
+            }));
+            commit_tasks.push(lazy(move || {
+                let contents = added_paths.iter().map(|p| (p.clone(), None));
+                restore_files(contents.collect())
+            }));
+        }
+        commit_tasks.push(task_with_input(self, patch, |command| {
+            command.args(["import", "--no-commit", "--force", "-"]);
+        }));
+        commit_tasks.push(task(self, |command| {
+            command.arg("commit").arg("-m").arg(message).arg("--");
+            for f in &files {
+                command.arg(&f.filename);
+            }
+        }));
+
+        let mut tasks = task_vec();
+        tasks.push(chain(commit_tasks));
+        // runs whether the commit failed or not so forgotten files are
+        // never left untracked
+        let hg = self.clone();
+        tasks.push(background(move || {
+            restore_working_copy(working_copy, &forgotten, |paths| {
+                hg.add_untracked(paths)
+            })
+        }));
+        serial(tasks)
+    }
+
+    fn get_last_commit_message(&self) -> Result<String, VcsError> {
+        self.run_command(self.command().args([
+            "log",
+            "-r",
+            ".",
+            "--template",
+            "{desc}",
+        ]))
+    }
+
+    fn is_last_commit_published(&self) -> Result<bool, VcsError> {
+        let output = self.run_command(self.command().args([
+            "log",
+            "-r",
+            ".",
+            "--template",
+            "{phase}",
+        ]))?;
+        Ok(output.trim() == "public")
+    }
+
+    fn amend(&self, message: &str, entries: &[Entry]) -> Box<dyn ActionTask> {
+        let mut tasks = task_vec();
+        let mut files_to_commit = Vec::new();
+        for e in entries.iter().filter(|e| e.selected) {
+            match e.state {
+                State::Missing | State::Deleted => {
+                    tasks.push(task(self, |command| {
+                        command.arg("remove").arg(&e.filename);
+                    }))
+                }
+                State::Untracked => tasks.push(task(self, |command| {
+                    command.arg("add").arg(&e.filename);
+                })),
+                _ => (),
+            }
+            files_to_commit.push(&e.filename);
+        }
+        tasks.push(task(self, |command| {
+            command.arg("commit").arg("--amend").arg("-m").arg(message);
+            if files_to_commit.is_empty() {
+                // without files hg would amend every change
+                command.args(["--exclude", "glob:**"]);
+            } else {
+                command.arg("--");
+                for file in files_to_commit {
+                    command.arg(file);
+                }
+            }
+        }));
+        chain(tasks)
+    }
+
     fn revert_all(&self) -> Box<dyn ActionTask> {
         let mut tasks = task_vec();
         tasks.push(task(self, |command| {
-            command.args(&["revert", "-C", "--all"]);
+            command.args(["revert", "-C", "--all"]);
         }));
         tasks.push(task(self, |command| {
-            command.args(&["purge"]);
+            command.args(["purge"]);
+        }));
+        // reverting leaves the merge state behind so this drops the backout
+        let backout_message = self.hg_dir().join(BACKOUT_MESSAGE_FILE);
+        tasks.push(lazy(move || {
+            let _ = fs::remove_file(backout_message);
+            ActionResult::from_ok(String::new())
         }));
         serial(tasks)
     }
 
-    fn revert_selected(&self, entries: &Vec<Entry>) -> Box<dyn ActionTask> {
+    fn revert_selected(&self, entries: &[Entry]) -> Box<dyn ActionTask> {
         let mut tasks = task_vec();
         let mut files_to_revert = Vec::new();
         for e in entries.iter().filter(|e| e.selected) {
@@ -258,7 +737,7 @@ impl<'a> VersionControlActions for HgActions {
                 _ => files_to_revert.push(&e.filename),
             }
         }
-        if files_to_revert.len() > 0 {
+        if !files_to_revert.is_empty() {
             tasks.push(task(self, |command| {
                 command.arg("revert").arg("-C").arg("--color").arg("always");
                 for file in files_to_revert {
@@ -281,66 +760,501 @@ impl<'a> VersionControlActions for HgActions {
         })
     }
 
+    fn rebase(&self, target: &str) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command
+                .args(["--config", "extensions.rebase=", "rebase", "-d"])
+                .arg(target);
+        })
+    }
+
+    fn rebase_continue(&self) -> Box<dyn ActionTask> {
+        if self.is_history_edit_in_progress() {
+            let dir = self.hg_dir().join(HISTORY_EDIT_DIR);
+            let editor = messages_editor(&dir);
+            return task(self, |command| {
+                if let Some(editor) = &editor {
+                    command.env("HGEDITOR", editor);
+                }
+                command.args([
+                    "--config",
+                    "extensions.histedit=",
+                    "histedit",
+                    "--continue",
+                ]);
+            });
+        }
+
+        task(self, |command| {
+            command.args([
+                "--config",
+                "extensions.rebase=",
+                "rebase",
+                "--continue",
+            ]);
+        })
+    }
+
+    fn rebase_skip(&self) -> Box<dyn ActionTask> {
+        unsupported(self, "skipping a changeset while rebasing")
+    }
+
+    fn rebase_abort(&self) -> Box<dyn ActionTask> {
+        if self.is_history_edit_in_progress() {
+            return task(self, |command| {
+                command.args([
+                    "--config",
+                    "extensions.histedit=",
+                    "histedit",
+                    "--abort",
+                ]);
+            });
+        }
+
+        task(self, |command| {
+            command.args([
+                "--config",
+                "extensions.rebase=",
+                "rebase",
+                "--abort",
+            ]);
+        })
+    }
+
+    fn is_rebase_in_progress(&self) -> bool {
+        self.hg_dir().join("rebasestate").exists()
+            || self.is_history_edit_in_progress()
+    }
+
+    fn cherry_pick(&self, revisions: &[String]) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.arg("graft");
+            for revision in revisions {
+                command.arg("-r").arg(revision);
+            }
+        })
+    }
+
+    fn cherry_pick_continue(&self) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.args(["graft", "--continue"]);
+        })
+    }
+
+    fn cherry_pick_abort(&self) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.args(["graft", "--abort"]);
+        })
+    }
+
+    fn is_cherry_pick_in_progress(&self) -> bool {
+        self.hg_dir().join("graftstate").exists()
+    }
+
+    fn backout(&self, revision: &str, message: &str) -> Box<dyn ActionTask> {
+        let hg = self.clone();
+        let (revision, pending_message) =
+            (String::from(revision), String::from(message));
+        let mut tasks = task_vec();
+        tasks.push(background(move || {
+            hg.backout_without_commit(&revision, &pending_message)
+        }));
+        tasks.push(task(self, |command| {
+            command.arg("commit").arg("--message").arg(message);
+        }));
+        chain(tasks)
+    }
+
+    fn is_backout_in_progress(&self) -> bool {
+        // the merge state is gone once the backout is committed
+        self.hg_dir().join(BACKOUT_MESSAGE_FILE).exists()
+            && self.hg_dir().join("merge").exists()
+    }
+
+    fn get_backout_message(&self) -> Option<String> {
+        if !self.is_backout_in_progress() {
+            return None;
+        }
+        let path = self.hg_dir().join(BACKOUT_MESSAGE_FILE);
+        let message = fs::read_to_string(path).ok()?;
+        Some(String::from(message.trim())).filter(|m| !m.is_empty())
+    }
+
+    fn bisect_start(&self, bad: &str, good: &str) -> Box<dyn ActionTask> {
+        let mut tasks = task_vec();
+        tasks.push(task(self, |command| {
+            command.args(["bisect", "--reset"]);
+        }));
+        tasks.push(task(self, |command| {
+            command.args(["bisect", "--bad", bad]);
+        }));
+        tasks.push(task(self, |command| {
+            command.args(["bisect", "--good", good]);
+        }));
+        chain(tasks)
+    }
+
+    fn bisect_good(&self) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.args(["bisect", "--good"]);
+        })
+    }
+
+    fn bisect_bad(&self) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.args(["bisect", "--bad"]);
+        })
+    }
+
+    fn bisect_skip(&self) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.args(["bisect", "--skip"]);
+        })
+    }
+
+    fn bisect_run(
+        &self,
+        bisect_command: &str,
+        args: &[String],
+    ) -> Box<dyn ActionTask> {
+        // hg runs the test through the shell as a single string
+        let mut shell_command = String::from(bisect_command);
+        for arg in args {
+            shell_command.push(' ');
+            shell_command.push_str(arg);
+        }
+        task(self, |command| {
+            command.args(["bisect", "--command"]).arg(&shell_command);
+        })
+    }
+
+    fn bisect_reset(&self) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.args(["bisect", "--reset"]);
+        })
+    }
+
+    fn is_bisect_in_progress(&self) -> bool {
+        self.hg_dir().join("bisect.state").exists()
+    }
+
+    fn get_history(&self, base: &str) -> Result<Vec<PlanEntry>, VcsError> {
+        let output = self.run_command(
+            self.command()
+                .arg("log")
+                .arg("-r")
+                .arg(format!("({0}::.) - {0}", base))
+                .arg("--template")
+                .arg("{node}\x1f{node|short}\x1f{desc}\x1e"),
+        )?;
+        Ok(PlanEntry::parse_all(&output))
+    }
+
+    fn edit_history(
+        &self,
+        base: &str,
+        plan: &[PlanEntry],
+    ) -> Box<dyn ActionTask> {
+        let commands = format_histedit_commands(plan);
+        let dir = self.hg_dir().join(HISTORY_EDIT_DIR);
+        let editors = match write_plan(&dir, &commands, plan) {
+            Ok(editors) => editors,
+            Err(error) => {
+                return ready(ActionResult::from_err(error.to_string()))
+            }
+        };
+        let plan_path = dir.join("plan");
+        task(self, |command| {
+            command.env("HGEDITOR", &editors.messages);
+            command
+                .args(["--config", "extensions.histedit=", "histedit"])
+                .arg("--commands")
+                .arg(&plan_path)
+                .arg(format!("first(({0}::.) - {0})", base));
+        })
+    }
+
     fn conflicts(&self) -> Box<dyn ActionTask> {
         task(self, |command| {
-            command.args(&["resolve", "-l", "--color", "always"]);
+            command.args(["resolve", "-l", "--color", "always"]);
         })
     }
 
     fn take_other(&self) -> Box<dyn ActionTask> {
         task(self, |command| {
-            command.args(&["resolve", "-a", "-t", "internal:other"]);
+            command.args(["resolve", "-a", "-t", "internal:other"]);
         })
     }
 
     fn take_local(&self) -> Box<dyn ActionTask> {
         task(self, |command| {
-            command.args(&["resolve", "-a", "-t", "internal:local"]);
+            command.args(["resolve", "-a", "-t", "internal:local"]);
         })
     }
 
-    fn fetch(&self) -> Box<dyn ActionTask> {
-        self.pull()
+    fn fetch(&self, remote: Option<&str>) -> Box<dyn ActionTask> {
+        self.pull(remote)
     }
 
-    fn pull(&self) -> Box<dyn ActionTask> {
+    fn pull(&self, remote: Option<&str>) -> Box<dyn ActionTask> {
         task(self, |command| {
-            command.arg("pull");
+            command.arg("pull").args(remote);
         })
     }
 
-    fn push(&self) -> Box<dyn ActionTask> {
+    fn push(&self, remote: Option<&str>) -> Box<dyn ActionTask> {
         task(self, |command| {
-            command.args(&["push", "--new-branch"]);
+            command.args(["push", "--new-branch"]).args(remote);
         })
     }
 
-    fn create_tag(&self, name: &str) -> Box<dyn ActionTask> {
+    fn list_remotes(&self) -> Box<dyn ActionTask> {
+        let list = task(self, |command| {
+            command.arg("paths");
+        });
+        map_output(list, |output| {
+            let mut remotes = String::new();
+            for line in output.lines() {
+                if let Some((name, url)) = line.split_once(" = ") {
+                    remotes.push_str(name);
+                    remotes.push(' ');
+                    remotes.push_str(url);
+                    remotes.push('\n');
+                }
+            }
+            remotes
+        })
+    }
+
+    fn add_remote(&self, _name: &str, _url: &str) -> Box<dyn ActionTask> {
+        unsupported(self, "adding paths, edit them in .hg/hgrc")
+    }
+
+    fn rename_remote(
+        &self,
+        _name: &str,
+        _new_name: &str,
+    ) -> Box<dyn ActionTask> {
+        unsupported(self, "renaming paths, edit them in .hg/hgrc")
+    }
+
+    fn remove_remote(&self, _name: &str) -> Box<dyn ActionTask> {
+        unsupported(self, "removing paths, edit them in .hg/hgrc")
+    }
+
+    fn set_remote_url(&self, _name: &str, _url: &str) -> Box<dyn ActionTask> {
+        unsupported(self, "changing paths, edit them in .hg/hgrc")
+    }
+
+    fn get_upstream_remote(&self) -> Option<String> {
+        Some(String::from("default"))
+    }
+
+    fn upstream(&self) -> Box<dyn ActionTask> {
+        // counted locally from phases instead of asking the server
+        let count = |revset: &str| {
+            task(self, |command| {
+                command.args(["log", "--rev", revset, "--template", "x"]);
+            })
+        };
+        let mut tasks = task_vec();
+        tasks.push(count("draft() and ::."));
+        tasks.push(count("(.:: and branch(.)) - ."));
+        map_output(parallel(tasks), |output| {
+            // one x per changeset on each line
+            let mut counts = output.split('\n').skip(1).map(str::len);
+            let upstream = Upstream {
+                name: String::from("default"),
+                ahead: counts.next().unwrap_or(0),
+                behind: counts.next().unwrap_or(0),
+            };
+            upstream.to_string()
+        })
+    }
+
+    fn list_tags(&self) -> Box<dyn ActionTask> {
         task(self, |command| {
-            command.arg("tag").arg(name).arg("-f");
+            command.args([
+                "log",
+                "--rev",
+                "reverse(tag())",
+                "--template",
+                "{tags % '{ifeq(tag, \"tip\", \"\", \"{tag} {node|short} {date|shortdate}\\n\")}'}",
+            ]);
         })
     }
 
-    fn list_branches(&self) -> Box<dyn ActionTask> {
+    fn get_tags(&self) -> Result<Vec<String>, VcsError> {
+        let output =
+            self.run_command(self.command().args(["tags", "--quiet"]))?;
+        Ok(output
+            .lines()
+            .filter(|t| *t != "tip")
+            .map(String::from)
+            .collect())
+    }
+
+    fn create_tag(
+        &self,
+        name: &str,
+        message: Option<&str>,
+        force: bool,
+        _remote: Option<&str>,
+    ) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.arg("tag");
+            if let Some(message) = message {
+                command.arg("-m").arg(message);
+            }
+            if force {
+                command.arg("-f");
+            }
+            command.arg(name);
+        })
+    }
+
+    fn delete_tag(
+        &self,
+        name: &str,
+        _remote: Option<&str>,
+    ) -> Box<dyn ActionTask> {
         task(self, |command| {
-            command.args(&["branches", "--template", "{branch}\n"]);
+            command.arg("tag").arg("--remove").arg(name);
         })
     }
 
-    fn create_branch(&self, name: &str) -> Box<dyn ActionTask> {
+    fn list_submodules(&self) -> Box<dyn ActionTask> {
+        let root = Path::new(&self.current_dir);
+        let mut tasks = task_vec();
+        for (path, recorded) in recorded_subrepos(root) {
+            let subrepo = root.join(&path);
+            if !subrepo.join(".hg").exists() {
+                let description = describe_subrepo(&path, &recorded, None);
+                tasks.push(ready(ActionResult::from_ok(description)));
+                continue;
+            }
+            let identify = task(self, |command| {
+                command
+                    .current_dir(subrepo)
+                    .args(["identify", "--id", "--debug"]);
+            });
+            tasks.push(map_output(identify, move |output| {
+                describe_subrepo(&path, &recorded, Some(&output))
+            }));
+        }
+        // parallel separates the outputs with an extra newline
+        map_output(parallel(tasks), |output| {
+            output
+                .lines()
+                .filter(|l| !l.is_empty())
+                .map(|l| format!("{}\n", l))
+                .collect()
+        })
+    }
+
+    fn init_submodules(&self) -> Box<dyn ActionTask> {
+        unsupported(self, "initializing subrepos, updating clones them")
+    }
+
+    fn update_submodules(&self) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.args(["update", "--rev", "."]);
+        })
+    }
+
+    fn sync_submodules(&self) -> Box<dyn ActionTask> {
+        unsupported(
+            self,
+            "syncing subrepos, their sources are read from .hgsub",
+        )
+    }
+
+    fn get_superproject_root(&self) -> Result<String, VcsError> {
+        let not_a_subrepo = || VcsError::CommandFailed {
+            code: None,
+            stderr: String::from("not inside a subrepo"),
+        };
+        let parent = Path::new(&self.current_dir)
+            .parent()
+            .ok_or_else(not_a_subrepo)?;
+        let root = self
+            .run_command(self.command().current_dir(parent).arg("root"))
+            .map_err(|_| not_a_subrepo())?;
+        let root = root.trim();
+        if Path::new(root).join(".hgsub").exists() {
+            Ok(String::from(root))
+        } else {
+            Err(not_a_subrepo())
+        }
+    }
+
+    fn list_worktrees(&self) -> Box<dyn ActionTask> {
+        unsupported(self, "worktrees")
+    }
+
+    fn add_worktree(&self, _path: &str, _target: &str) -> Box<dyn ActionTask> {
+        unsupported(self, "worktrees")
+    }
+
+    fn remove_worktree(&self, _path: &str) -> Box<dyn ActionTask> {
+        unsupported(self, "worktrees")
+    }
+
+    fn prune_worktrees(&self) -> Box<dyn ActionTask> {
+        unsupported(self, "worktrees")
+    }
+
+    fn list_branches(&self) -> Box<dyn ActionTask> {
+        let mut tasks = task_vec();
+        tasks.push(task(self, |command| {
+            command.args(["branches", "--template", "branch {branch}\n"]);
+        }));
+        tasks.push(task(self, |command| {
+            command.args([
+                "log",
+                "--rev",
+                "draft()",
+                "--template",
+                "draft {branch}\n",
+            ]);
+        }));
+        map_output(parallel(tasks), format_branches)
+    }
+
+    fn create_branch(
+        &self,
+        name: &str,
+        _remote: Option<&str>,
+    ) -> Box<dyn ActionTask> {
         task(self, |command| {
             command.arg("branch").arg(name);
         })
     }
 
-    fn close_branch(&self, name: &str) -> Box<dyn ActionTask> {
-        let changeset =
-            handle_command(self.command().args(&["identify", "--num"])).ok();
+    fn create_branch_at(
+        &self,
+        name: &str,
+        revision: &str,
+    ) -> Box<dyn ActionTask> {
+        // named branches only start at the next commit so bookmark it instead
+        task(self, |command| {
+            command.args(["bookmark", "--rev", revision, name]);
+        })
+    }
+
+    fn close_branch(
+        &self,
+        name: &str,
+        _remote: Option<&str>,
+    ) -> Box<dyn ActionTask> {
+        let changeset = self
+            .run_command(self.command().args(["identify", "--num"]))
+            .ok();
 
         let mut tasks = task_vec();
         tasks.push(self.update(name));
         tasks.push(task(self, |command| {
-            command.args(&[
+            command.args([
                 "commit",
                 "-m",
                 "\"close branch\"",
@@ -352,4 +1266,242 @@ impl<'a> VersionControlActions for HgActions {
         }
         serial(tasks)
     }
+
+    fn list_stashes(&self) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.args(["shelve", "--list"]);
+        })
+    }
+
+    fn stash_all(&self, message: &str) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.args(["shelve", "--unknown"]);
+            if !message.is_empty() {
+                command.arg("-m").arg(message);
+            }
+        })
+    }
+
+    fn stash_selected(
+        &self,
+        message: &str,
+        entries: &[Entry],
+    ) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.args(["shelve", "--unknown"]);
+            if !message.is_empty() {
+                command.arg("-m").arg(message);
+            }
+            command.arg("--");
+            for e in entries.iter().filter(|e| e.selected) {
+                command.arg(&e.filename);
+            }
+        })
+    }
+
+    fn show_stash(&self, name: &str) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command
+                .args(["shelve", "--color", "always", "--patch"])
+                .arg(name);
+        })
+    }
+
+    fn apply_stash(&self, name: &str) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.args(["unshelve", "--keep"]).arg(name);
+        })
+    }
+
+    fn pop_stash(&self, name: &str) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.arg("unshelve").arg(name);
+        })
+    }
+
+    fn drop_stash(&self, name: &str) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.args(["shelve", "--delete"]).arg(name);
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, process};
+
+    use super::*;
+
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!(
+            "verco-hg-{}-{}",
+            name,
+            process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn failed_commit_restores_contents_and_re_adds_forgotten_files() {
+        let dir = scratch_dir("restore");
+        let modified = dir.join("modified.txt");
+        let added = dir.join("added.txt");
+        // as left by a failed import: reverted and with the new file gone
+        fs::write(&modified, "old\n").unwrap();
+        let imported = dir.join("imported.txt");
+        fs::write(&imported, "partial\n").unwrap();
+
+        let mut re_added = Vec::new();
+        let result = restore_working_copy(
+            vec![
+                (modified.clone(), Some(b"old\nnew\n".to_vec())),
+                (added.clone(), Some(b"brand new\n".to_vec())),
+                (imported.clone(), None),
+            ],
+            &[String::from("added.txt")],
+            |paths| {
+                re_added.extend_from_slice(paths);
+                Ok(())
+            },
+        );
+
+        assert!(result.success);
+        assert_eq!(fs::read_to_string(&modified).unwrap(), "old\nnew\n");
+        assert_eq!(fs::read_to_string(&added).unwrap(), "brand new\n");
+        assert!(!imported.exists());
+        assert_eq!(re_added, ["added.txt"]);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn failing_to_re_add_is_reported() {
+        let dir = scratch_dir("re-add");
+        let added = dir.join("added.txt");
+        let result = restore_working_copy(
+            vec![(added.clone(), Some(b"kept\n".to_vec()))],
+            &[String::from("added.txt")],
+            |_| {
+                Err(VcsError::CommandFailed {
+                    code: Some(255),
+                    stderr: String::from("abort: lock held"),
+                })
+            },
+        );
+
+        assert!(!result.success);
+        assert_eq!(result.output, "abort: lock held");
+        assert_eq!(fs::read_to_string(&added).unwrap(), "kept\n");
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn plan_becomes_histedit_commands() {
+        let mut plan = PlanEntry::parse_all(
+            "aaaa\x1faa\x1ffirst\x1ebbbb\x1fbb\x1fsecond\x1e\
+             cccc\x1fcc\x1fthird\x1edddd\x1fdd\x1ffourth\x1e\
+             eeee\x1fee\x1ffifth\x1e",
+        );
+        plan[1].action = PlanAction::Reword;
+        plan[2].action = PlanAction::Squash;
+        plan[3].action = PlanAction::Fixup;
+        plan[4].action = PlanAction::Drop;
+        assert_eq!(
+            format_histedit_commands(&plan),
+            "pick aaaa\nmess bbbb\nfold cccc\nroll dddd\ndrop eeee\n"
+        );
+    }
+
+    #[test]
+    fn nothing_is_re_added_without_forgotten_files() {
+        let result = restore_working_copy(Vec::new(), &[], |_| {
+            panic!("no file was forgotten")
+        });
+        assert!(result.success);
+    }
+
+    #[test]
+    fn subrepos_are_read_with_their_recorded_revisions() {
+        let dir = scratch_dir("subrepos");
+        fs::write(
+            dir.join(".hgsub"),
+            "libs/a = https://example.com/a\n\
+             libs/b = ../b\n\
+             [subpaths]\n\
+             https://example.com/(.*) = https://mirror.example.com/\\1\n",
+        )
+        .unwrap();
+        fs::write(dir.join(".hgsubstate"), "0123456789abcdef libs/a\n")
+            .unwrap();
+
+        let subrepos = recorded_subrepos(&dir);
+        assert_eq!(
+            subrepos,
+            [
+                (String::from("libs/a"), String::from("0123456789abcdef")),
+                (String::from("libs/b"), String::new()),
+            ]
+        );
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn subrepo_state_compares_the_recorded_and_checked_out_revisions() {
+        let recorded = "0123456789abcdef0123";
+        let describe = |identify| describe_subrepo("a", recorded, identify);
+        assert_eq!(
+            describe(Some("0123456789abcdef0123\n")),
+            "a 0123456789ab 0123456789ab clean\n"
+        );
+        assert_eq!(
+            describe(Some("0123456789abcdef0123+\n")),
+            "a 0123456789ab 0123456789ab dirty\n"
+        );
+        assert_eq!(
+            describe(Some("fedcba9876543210fedc\n")),
+            "a fedcba987654 0123456789ab moved\n"
+        );
+        assert_eq!(
+            describe(Some("fedcba9876543210fedc+\n")),
+            "a fedcba987654 0123456789ab moved,dirty\n"
+        );
+        assert_eq!(describe(None), "a - 0123456789ab uninitialized\n");
+    }
+
+    #[test]
+    fn branches_count_their_drafts() {
+        let output = "\
+\nbranch default\nbranch feature x\nbranch stable\n\
+\ndraft default\ndraft feature x\ndraft feature x\n";
+        assert_eq!(
+            format_branches(String::from(output)),
+            "default [ahead 1]\nfeature x [ahead 2]\nstable\n"
+        );
+    }
+
+    #[test]
+    fn backout_is_in_progress_while_its_merge_is_unresolved() {
+        let dir = scratch_dir("backout");
+        let hg = HgActions {
+            current_dir: dir.to_string_lossy().into_owned(),
+        };
+        fs::create_dir_all(hg.hg_dir().join("merge")).unwrap();
+        assert!(!hg.is_backout_in_progress());
+        assert_eq!(hg.get_backout_message(), None);
+
+        let message_path = hg.hg_dir().join(BACKOUT_MESSAGE_FILE);
+        fs::write(&message_path, "back out the parser\n").unwrap();
+        assert!(hg.is_backout_in_progress());
+        assert_eq!(
+            hg.get_backout_message().as_deref(),
+            Some("back out the parser")
+        );
+
+        // committing clears the merge state
+        fs::remove_dir_all(hg.hg_dir().join("merge")).unwrap();
+        assert!(!hg.is_backout_in_progress());
+        assert_eq!(hg.get_backout_message(), None);
+        let _ = fs::remove_dir_all(&dir);
+    }
 }
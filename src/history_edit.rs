@@ -0,0 +1,309 @@
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+};
+
+/// The argument that makes verco act as the editor git and hg run while
+/// editing history
+pub const EDITOR_FLAG: &str = "--history-editor";
+
+/// What to do with a commit when editing history
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PlanAction {
+    Pick,
+    Reword,
+    /// Combines it with the previous commit keeping both messages
+    Squash,
+    /// Combines it with the previous commit discarding its message
+    Fixup,
+    Drop,
+}
+
+impl PlanAction {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Pick => "pick",
+            Self::Reword => "reword",
+            Self::Squash => "squash",
+            Self::Fixup => "fixup",
+            Self::Drop => "drop",
+        }
+    }
+}
+
+/// A commit in a history editing plan
+#[derive(Clone)]
+pub struct PlanEntry {
+    pub action: PlanAction,
+    pub revision: String,
+    pub short_revision: String,
+    pub message: String,
+    /// The message the commit gets when reworded
+    pub new_message: String,
+}
+
+impl PlanEntry {
+    /// Parses records of revision, short revision and message separated by
+    /// `\x1f` and ending with `\x1e`
+    pub fn parse_all(output: &str) -> Vec<Self> {
+        output
+            .split('\x1e')
+            .filter_map(|record| {
+                let mut fields = record.trim_start().splitn(3, '\x1f');
+                let revision = fields.next()?;
+                let short_revision = fields.next()?;
+                let message = fields.next()?.trim();
+                Some(Self {
+                    action: PlanAction::Pick,
+                    revision: String::from(revision),
+                    short_revision: String::from(short_revision),
+                    message: String::from(message),
+                    new_message: String::from(message),
+                })
+            })
+            .collect()
+    }
+
+    pub fn summary(&self) -> &str {
+        let message = match self.action {
+            PlanAction::Reword => &self.new_message,
+            _ => &self.message,
+        };
+        message.lines().next().unwrap_or("")
+    }
+}
+
+/// The editor commands that make git or hg follow a plan
+pub struct PlanEditors {
+    /// Replaces the plan the backend asks to be edited
+    pub plan: String,
+    /// Replaces the messages of reworded commits
+    pub messages: String,
+}
+
+fn shell_quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', "'\\''"))
+}
+
+fn editor_command(source: &Path) -> io::Result<String> {
+    let executable = env::current_exe()?;
+    Ok(format!(
+        "{} {} {}",
+        shell_quote(&executable.to_string_lossy()),
+        EDITOR_FLAG,
+        shell_quote(&source.to_string_lossy())
+    ))
+}
+
+fn messages_dir(dir: &Path) -> PathBuf {
+    dir.join("messages")
+}
+
+/// Writes `plan` and the messages of reworded commits in `entries` to `dir`
+/// replacing a previous plan
+pub fn write_plan(
+    dir: &Path,
+    plan: &str,
+    entries: &[PlanEntry],
+) -> io::Result<PlanEditors> {
+    if dir.exists() {
+        fs::remove_dir_all(dir)?;
+    }
+    let messages = messages_dir(dir);
+    fs::create_dir_all(&messages)?;
+
+    let plan_path = dir.join("plan");
+    fs::write(&plan_path, plan)?;
+    for (i, entry) in entries
+        .iter()
+        .filter(|e| e.action == PlanAction::Reword)
+        .enumerate()
+    {
+        fs::write(messages.join(format!("{}.old", i)), &entry.message)?;
+        fs::write(messages.join(format!("{}.new", i)), &entry.new_message)?;
+    }
+
+    Ok(PlanEditors {
+        plan: editor_command(&plan_path)?,
+        messages: editor_command(&messages)?,
+    })
+}
+
+/// The editor command to resume a plan written to `dir`, if there is one
+pub fn messages_editor(dir: &Path) -> Option<String> {
+    let messages = messages_dir(dir);
+    if messages.is_dir() {
+        editor_command(&messages).ok()
+    } else {
+        None
+    }
+}
+
+/// A message without the comment lines git and hg add for the editor
+pub(crate) fn strip_comments(message: &str) -> String {
+    let lines: Vec<_> = message
+        .lines()
+        .filter(|l| !l.starts_with('#') && !l.starts_with("HG:"))
+        .collect();
+    String::from(lines.join("\n").trim())
+}
+
+/// Runs verco as an editor with the arguments after `EDITOR_FLAG`
+///
+/// A source file replaces the edited file. A source directory of reworded
+/// messages replaces the edited message if it is one of the old ones and
+/// leaves it unchanged otherwise.
+pub fn run_editor(args: &[String]) -> io::Result<()> {
+    let (source, target) = match args {
+        [source, target] => (Path::new(source), Path::new(target)),
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "expected a source and a file to edit",
+            ))
+        }
+    };
+
+    if !source.is_dir() {
+        return fs::copy(source, target).map(|_| ());
+    }
+
+    let message = strip_comments(&fs::read_to_string(target)?);
+    for i in 0.. {
+        let old = match fs::read_to_string(source.join(format!("{}.old", i))) {
+            Ok(old) => old,
+            Err(_) => break,
+        };
+        if strip_comments(&old) == message {
+            return fs::copy(source.join(format!("{}.new", i)), target)
+                .map(|_| ());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process;
+
+    use super::*;
+
+    /// Output of the `git log --format=%H%x1f%h%x1f%B%x1e` run by
+    /// `get_history`, where each message ends with a newline
+    const GIT_HISTORY: &str = "\
+1111111111111111111111111111111111111111\x1f1111111\x1fadd the parser
+
+with a body
+\x1e
+2222222222222222222222222222222222222222\x1f2222222\x1ffix typo
+\x1e
+3333333333333333333333333333333333333333\x1f3333333\x1fwip
+\x1e
+";
+
+    #[test]
+    fn history_records_become_picks() {
+        let entries = PlanEntry::parse_all(GIT_HISTORY);
+        assert_eq!(entries.len(), 3);
+        assert!(entries.iter().all(|e| e.action == PlanAction::Pick));
+        assert_eq!(entries[0].revision, "1".repeat(40));
+        assert_eq!(entries[0].short_revision, "1111111");
+        assert_eq!(entries[0].message, "add the parser\n\nwith a body");
+        assert_eq!(entries[0].new_message, entries[0].message);
+        assert_eq!(entries[0].summary(), "add the parser");
+        assert_eq!(entries[2].message, "wip");
+    }
+
+    #[test]
+    fn incomplete_records_are_skipped() {
+        let entries = PlanEntry::parse_all("abc\x1fa\x1e\n\x1e");
+        assert!(entries.is_empty());
+    }
+
+    #[test]
+    fn reworded_summary_is_the_new_message() {
+        let mut entry = PlanEntry::parse_all(GIT_HISTORY).remove(1);
+        entry.new_message = String::from("fix the typo\n\nin the readme");
+        assert_eq!(entry.summary(), "fix typo");
+        entry.action = PlanAction::Reword;
+        assert_eq!(entry.summary(), "fix the typo");
+    }
+
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!(
+            "verco-history-{}-{}",
+            name,
+            process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// Runs the editor command `editor` the way git or hg would on `target`
+    fn edit(editor: &str, target: &Path) {
+        let source = editor.rsplit(' ').next().unwrap().trim_matches('\'');
+        let args =
+            [String::from(source), String::from(target.to_string_lossy())];
+        run_editor(&args).unwrap();
+    }
+
+    #[test]
+    fn plan_editor_replaces_the_todo_list() {
+        let dir = scratch_dir("plan");
+        let plan = "pick 1111111 add the parser\ndrop 2222222 fix typo\n";
+        let editors = write_plan(&dir.join("plan-dir"), plan, &[]).unwrap();
+        assert!(editors.plan.contains(EDITOR_FLAG));
+
+        let todo = dir.join("git-rebase-todo");
+        fs::write(&todo, "pick 1111111 add the parser\n# comments\n").unwrap();
+        edit(&editors.plan, &todo);
+        assert_eq!(fs::read_to_string(&todo).unwrap(), plan);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn messages_editor_rewords_only_the_planned_commits() {
+        let dir = scratch_dir("messages");
+        let mut entries = PlanEntry::parse_all(GIT_HISTORY);
+        entries[0].action = PlanAction::Reword;
+        entries[0].new_message = String::from("add the log parser");
+        let plan_dir = dir.join("plan-dir");
+        let editors = write_plan(&plan_dir, "", &entries).unwrap();
+        assert_eq!(messages_editor(&plan_dir), Some(editors.messages.clone()));
+
+        let message = dir.join("COMMIT_EDITMSG");
+        fs::write(
+            &message,
+            "add the parser\n\nwith a body\n\n# Please enter the commit message\n",
+        )
+        .unwrap();
+        edit(&editors.messages, &message);
+        assert_eq!(fs::read_to_string(&message).unwrap(), "add the log parser");
+
+        fs::write(&message, "fix typo\nHG: Enter commit message.\n").unwrap();
+        edit(&editors.messages, &message);
+        assert_eq!(
+            fs::read_to_string(&message).unwrap(),
+            "fix typo\nHG: Enter commit message.\n"
+        );
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn writing_a_plan_replaces_the_previous_one() {
+        let dir = scratch_dir("replace");
+        let mut entries = PlanEntry::parse_all(GIT_HISTORY);
+        entries[1].action = PlanAction::Reword;
+        write_plan(&dir, "old", &entries).unwrap();
+        write_plan(&dir, "new", &[]).unwrap();
+        assert_eq!(fs::read_to_string(dir.join("plan")).unwrap(), "new");
+        assert!(!messages_dir(&dir).join("0.old").exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn editor_needs_a_source_and_a_target() {
+        assert!(run_editor(&[String::from("only-one")]).is_err());
+    }
+}
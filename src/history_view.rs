@@ -0,0 +1,288 @@
+use crossterm::{
+    cursor,
+    event::{self, KeyCode, KeyEvent, KeyModifiers},
+    handle_command,
+    style::{Color, Print, ResetColor, SetBackgroundColor, SetForegroundColor},
+    terminal::{Clear, ClearType},
+    QueueableCommand, Result,
+};
+
+use std::io::Write;
+
+use verco::history_edit::{PlanAction, PlanEntry};
+
+use crate::{
+    input,
+    tui_util::{
+        move_cursor, AvailableSize, TerminalSize, ENTRY_COLOR,
+        SELECTED_BG_COLOR,
+    },
+};
+
+const REWORD_COLOR: Color = Color::Rgb {
+    r: 100,
+    g: 180,
+    b: 255,
+};
+const SQUASH_COLOR: Color = Color::Rgb {
+    r: 255,
+    g: 200,
+    b: 0,
+};
+const DROP_COLOR: Color = Color::Rgb { r: 255, g: 0, b: 0 };
+const ACTION_COLUMN: usize = 8;
+
+fn action_color(action: PlanAction) -> Color {
+    match action {
+        PlanAction::Pick => Color::Reset,
+        PlanAction::Reword => REWORD_COLOR,
+        PlanAction::Squash | PlanAction::Fixup => SQUASH_COLOR,
+        PlanAction::Drop => DROP_COLOR,
+    }
+}
+
+struct PlanEdit<'a> {
+    entries: &'a mut [PlanEntry],
+    scroll: usize,
+    cursor: usize,
+}
+
+impl<'a> PlanEdit<'a> {
+    fn move_cursor(&mut self, available_size: AvailableSize, delta: i32) {
+        move_cursor(
+            &mut self.scroll,
+            &mut self.cursor,
+            available_size,
+            self.entries.len(),
+            delta,
+        );
+    }
+
+    /// Moves the entry under the cursor and the cursor with it
+    fn move_entry(&mut self, available_size: AvailableSize, delta: i32) {
+        let target = self.cursor as i32 + delta;
+        if target < 0 || target >= self.entries.len() as i32 {
+            return;
+        }
+        self.entries.swap(self.cursor, target as usize);
+        self.move_cursor(available_size, delta);
+    }
+
+    fn draw_all_entries<W>(
+        &self,
+        write: &mut W,
+        available_size: AvailableSize,
+    ) -> Result<()>
+    where
+        W: Write,
+    {
+        handle_command!(write, cursor::MoveTo(0, 1))?;
+        handle_command!(write, ResetColor)?;
+
+        for (i, entry) in self
+            .entries
+            .iter()
+            .enumerate()
+            .skip(self.scroll)
+            .take(available_size.height)
+        {
+            if i == self.cursor {
+                handle_command!(write, SetBackgroundColor(SELECTED_BG_COLOR))?;
+            }
+
+            let action_name = entry.action.name();
+            handle_command!(
+                write,
+                SetForegroundColor(action_color(entry.action))
+            )?;
+            handle_command!(write, Print(action_name))?;
+            for _ in action_name.len()..ACTION_COLUMN {
+                handle_command!(write, Print(' '))?;
+            }
+            handle_command!(write, SetForegroundColor(ENTRY_COLOR))?;
+            handle_command!(write, Print(&entry.short_revision))?;
+            handle_command!(write, SetForegroundColor(Color::Reset))?;
+            handle_command!(write, Print(' '))?;
+
+            let used = ACTION_COLUMN + entry.short_revision.len() + 1;
+            let summary: String = entry
+                .summary()
+                .chars()
+                .take(available_size.width.saturating_sub(used))
+                .collect();
+            handle_command!(write, Print(summary))?;
+
+            handle_command!(write, ResetColor)?;
+            handle_command!(write, Clear(ClearType::UntilNewLine))?;
+            handle_command!(write, cursor::MoveToNextLine(1))?;
+        }
+
+        handle_command!(write, ResetColor)?;
+        handle_command!(write, Clear(ClearType::FromCursorDown))?;
+        Ok(())
+    }
+
+    /// Prompts for the new summary of the entry under the cursor keeping
+    /// the rest of its message
+    fn reword<W>(&mut self, write: &mut W) -> Result<()>
+    where
+        W: Write,
+    {
+        let entry = &mut self.entries[self.cursor];
+        let TerminalSize { height, .. } = TerminalSize::get()?;
+        handle_command!(write, cursor::MoveTo(0, height - 2))?;
+        handle_command!(write, Clear(ClearType::FromCursorDown))?;
+        handle_command!(write, SetForegroundColor(ENTRY_COLOR))?;
+        handle_command!(write, Print("new message"))?;
+        handle_command!(write, ResetColor)?;
+        handle_command!(write, cursor::MoveToNextLine(1))?;
+        handle_command!(write, cursor::Show)?;
+        write.flush()?;
+
+        let summary = entry.message.lines().next().unwrap_or("");
+        let line = input::read_line(summary).ok().flatten().unwrap_or_default();
+        handle_command!(write, cursor::Hide)?;
+
+        let line = line.trim();
+        if !line.is_empty() {
+            let body = &entry.message[summary.len()..];
+            entry.new_message = format!("{}{}", line, body);
+            entry.action = PlanAction::Reword;
+        }
+        Ok(())
+    }
+}
+
+/// Lets the user reorder `entries` and choose what to do with each of them
+/// and returns whether they confirmed the plan
+pub fn edit_plan<W>(write: &mut W, entries: &mut [PlanEntry]) -> Result<bool>
+where
+    W: Write,
+{
+    if entries.is_empty() {
+        return Ok(false);
+    }
+
+    let mut edit = PlanEdit {
+        entries,
+        scroll: 0,
+        cursor: 0,
+    };
+
+    let mut available_size =
+        AvailableSize::from_temrinal_size(TerminalSize::get()?);
+    edit.draw_all_entries(write, available_size)?;
+
+    loop {
+        write.queue(cursor::MoveTo(0, 2))?;
+        write.flush()?;
+        let key_event = match event::read()? {
+            event::Event::Resize(width, height) => {
+                available_size =
+                    AvailableSize::from_temrinal_size(TerminalSize {
+                        width,
+                        height,
+                    });
+                edit.move_cursor(available_size, 0);
+                edit.draw_all_entries(write, available_size)?;
+                continue;
+            }
+            event::Event::Key(key_event) => key_event,
+            _ => continue,
+        };
+
+        let action = match key_event {
+            KeyEvent {
+                code: KeyCode::Esc, ..
+            }
+            | KeyEvent {
+                code: KeyCode::Char('q'),
+                ..
+            }
+            | KeyEvent {
+                code: KeyCode::Char('c'),
+                modifiers: KeyModifiers::CONTROL,
+            } => return Ok(false),
+            KeyEvent {
+                code: KeyCode::Enter,
+                ..
+            }
+            | KeyEvent {
+                code: KeyCode::Char('m'),
+                modifiers: KeyModifiers::CONTROL,
+            } => return Ok(true),
+            KeyEvent {
+                code: KeyCode::Char('j'),
+                ..
+            }
+            | KeyEvent {
+                code: KeyCode::Char('n'),
+                modifiers: KeyModifiers::CONTROL,
+            }
+            | KeyEvent {
+                code: KeyCode::Down,
+                ..
+            } => {
+                edit.move_cursor(available_size, 1);
+                None
+            }
+            KeyEvent {
+                code: KeyCode::Char('k'),
+                ..
+            }
+            | KeyEvent {
+                code: KeyCode::Char('p'),
+                modifiers: KeyModifiers::CONTROL,
+            }
+            | KeyEvent {
+                code: KeyCode::Up, ..
+            } => {
+                edit.move_cursor(available_size, -1);
+                None
+            }
+            KeyEvent {
+                code: KeyCode::Char('J'),
+                ..
+            } => {
+                edit.move_entry(available_size, 1);
+                None
+            }
+            KeyEvent {
+                code: KeyCode::Char('K'),
+                ..
+            } => {
+                edit.move_entry(available_size, -1);
+                None
+            }
+            KeyEvent {
+                code: KeyCode::Char('r'),
+                ..
+            } => {
+                edit.reword(write)?;
+                None
+            }
+            KeyEvent {
+                code: KeyCode::Char('p'),
+                ..
+            } => Some(PlanAction::Pick),
+            KeyEvent {
+                code: KeyCode::Char('s'),
+                ..
+            } => Some(PlanAction::Squash),
+            KeyEvent {
+                code: KeyCode::Char('f'),
+                ..
+            } => Some(PlanAction::Fixup),
+            KeyEvent {
+                code: KeyCode::Char('d'),
+                ..
+            } => Some(PlanAction::Drop),
+            _ => continue,
+        };
+
+        if let Some(action) = action {
+            edit.entries[edit.cursor].action = action;
+        }
+        edit.draw_all_entries(write, available_size)?;
+    }
+}
@@ -0,0 +1,400 @@
+use crossterm::{
+    cursor,
+    event::{self, KeyCode, KeyEvent, KeyModifiers},
+    handle_command,
+    style::{Color, Print, ResetColor, SetBackgroundColor, SetForegroundColor},
+    terminal::{Clear, ClearType},
+    QueueableCommand, Result,
+};
+
+use std::io::Write;
+
+use verco::patch::{FilePatch, Hunk, LineKind};
+
+use crate::tui_util::{
+    AvailableSize, TerminalSize, ENTRY_COLOR, SELECTED_BG_COLOR,
+};
+
+const ADDED_COLOR: Color = Color::Rgb { r: 0, g: 255, b: 0 };
+const REMOVED_COLOR: Color = Color::Rgb { r: 255, g: 0, b: 0 };
+const HUNK_HEADER_COLOR: Color = Color::Rgb {
+    r: 100,
+    g: 180,
+    b: 255,
+};
+const LINE_INDENT: usize = 4;
+
+#[derive(Clone, Copy)]
+enum Row {
+    File(usize),
+    Hunk(usize, usize),
+    Line(usize, usize, usize),
+}
+
+struct HunkSelect<'a> {
+    files: &'a mut [FilePatch],
+    rows: Vec<Row>,
+    /// Indices of the `Row::Hunk` rows
+    hunk_rows: Vec<usize>,
+    scroll: usize,
+    cursor: usize,
+    /// Row of the selected line when selecting lines inside a hunk
+    line_cursor: Option<usize>,
+}
+
+impl<'a> HunkSelect<'a> {
+    fn new(files: &'a mut [FilePatch]) -> Self {
+        let mut rows = Vec::new();
+        let mut hunk_rows = Vec::new();
+        for (file_index, file) in files.iter().enumerate() {
+            rows.push(Row::File(file_index));
+            for (hunk_index, hunk) in file.hunks.iter().enumerate() {
+                hunk_rows.push(rows.len());
+                rows.push(Row::Hunk(file_index, hunk_index));
+                for line_index in 0..hunk.lines.len() {
+                    rows.push(Row::Line(file_index, hunk_index, line_index));
+                }
+            }
+        }
+
+        Self {
+            files,
+            rows,
+            hunk_rows,
+            scroll: 0,
+            cursor: 0,
+            line_cursor: None,
+        }
+    }
+
+    fn cursor_hunk(&mut self) -> Option<&mut Hunk> {
+        match self.rows[*self.hunk_rows.get(self.cursor)?] {
+            Row::Hunk(file, hunk) => Some(&mut self.files[file].hunks[hunk]),
+            _ => None,
+        }
+    }
+
+    fn hunks_mut(&mut self) -> impl Iterator<Item = &mut Hunk> {
+        self.files.iter_mut().flat_map(|f| f.hunks.iter_mut())
+    }
+
+    /// Rows of the added and removed lines of the hunk under the cursor
+    fn change_rows(&self) -> Vec<usize> {
+        let header_row = self.hunk_rows[self.cursor];
+        self.rows
+            .iter()
+            .enumerate()
+            .skip(header_row + 1)
+            .take_while(|(_, row)| matches!(row, Row::Line(..)))
+            .filter(|(_, row)| match **row {
+                Row::Line(file, hunk, line) => {
+                    self.files[file].hunks[hunk].lines[line].is_change()
+                }
+                _ => false,
+            })
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    fn enter_line_mode(&mut self, available_size: AvailableSize) {
+        self.line_cursor = self.change_rows().first().copied();
+        self.move_cursor(available_size, 0);
+    }
+
+    fn move_cursor(&mut self, available_size: AvailableSize, delta: i32) {
+        if self.hunk_rows.is_empty() {
+            return;
+        }
+
+        if let Some(line_row) = self.line_cursor {
+            let change_rows = self.change_rows();
+            let index = change_rows.iter().position(|&r| r == line_row);
+            let last = change_rows.len() as i32 - 1;
+            let index = (index.unwrap_or(0) as i32 + delta).max(0).min(last);
+            let line_row = change_rows[index as usize];
+            self.line_cursor = Some(line_row);
+
+            if line_row < self.scroll {
+                self.scroll = line_row;
+            } else if line_row >= self.scroll + available_size.height {
+                self.scroll = line_row + 1 - available_size.height;
+            }
+            return;
+        }
+
+        let last = self.hunk_rows.len() as i32 - 1;
+        self.cursor = (self.cursor as i32 + delta).max(0).min(last) as usize;
+
+        // keep the whole hunk in view if it fits, its header otherwise
+        let header_row = self.hunk_rows[self.cursor];
+        let first_row = match header_row.checked_sub(1).map(|r| self.rows[r]) {
+            Some(Row::File(_)) => header_row - 1,
+            _ => header_row,
+        };
+        let last_row = self
+            .hunk_rows
+            .get(self.cursor + 1)
+            .map(|r| r - 1)
+            .unwrap_or(self.rows.len() - 1);
+
+        if first_row < self.scroll {
+            self.scroll = first_row;
+        } else if last_row >= self.scroll + available_size.height {
+            self.scroll = first_row.min(last_row + 1 - available_size.height);
+        }
+    }
+
+    /// Toggles the line under the cursor when selecting lines or the whole
+    /// hunk otherwise
+    fn toggle(&mut self) {
+        if let Some(line_row) = self.line_cursor {
+            if let Row::Line(file, hunk, line) = self.rows[line_row] {
+                let line = &mut self.files[file].hunks[hunk].lines[line];
+                line.selected = !line.selected;
+            }
+        } else if let Some(hunk) = self.cursor_hunk() {
+            let selected = hunk.is_selected() && !hunk.is_partially_selected();
+            hunk.set_selected(!selected);
+        }
+    }
+
+    fn draw_all_rows<W>(
+        &self,
+        write: &mut W,
+        available_size: AvailableSize,
+    ) -> Result<()>
+    where
+        W: Write,
+    {
+        handle_command!(write, cursor::MoveTo(0, 1))?;
+        handle_command!(write, ResetColor)?;
+
+        let cursor_row = self.hunk_rows.get(self.cursor).copied();
+        for (i, row) in self
+            .rows
+            .iter()
+            .enumerate()
+            .skip(self.scroll)
+            .take(available_size.height)
+        {
+            match *row {
+                Row::File(file) => {
+                    handle_command!(write, SetForegroundColor(ENTRY_COLOR))?;
+                    handle_command!(write, Print(&self.files[file].filename))?;
+                }
+                Row::Hunk(file, hunk) => {
+                    let hunk = &self.files[file].hunks[hunk];
+                    if Some(i) == cursor_row {
+                        handle_command!(
+                            write,
+                            SetBackgroundColor(SELECTED_BG_COLOR)
+                        )?;
+                    }
+                    let select_char = if hunk.is_partially_selected() {
+                        '~'
+                    } else if hunk.is_selected() {
+                        '+'
+                    } else {
+                        ' '
+                    };
+                    handle_command!(write, Print(select_char))?;
+                    handle_command!(write, Print(' '))?;
+                    handle_command!(
+                        write,
+                        SetForegroundColor(HUNK_HEADER_COLOR)
+                    )?;
+                    let header = hunk.header();
+                    let header: String = header
+                        .chars()
+                        .take(available_size.width.saturating_sub(2))
+                        .collect();
+                    handle_command!(write, Print(header))?;
+                }
+                Row::Line(file, hunk, line) => {
+                    let line = &self.files[file].hunks[hunk].lines[line];
+                    let color = match line.kind {
+                        LineKind::Added => ADDED_COLOR,
+                        LineKind::Removed => REMOVED_COLOR,
+                        _ => Color::Reset,
+                    };
+                    if Some(i) == self.line_cursor {
+                        handle_command!(
+                            write,
+                            SetBackgroundColor(SELECTED_BG_COLOR)
+                        )?;
+                    }
+                    let select_char = if line.is_change() && line.selected {
+                        '+'
+                    } else {
+                        ' '
+                    };
+                    handle_command!(write, Print("  "))?;
+                    handle_command!(write, Print(select_char))?;
+                    handle_command!(write, Print(' '))?;
+                    handle_command!(write, SetForegroundColor(color))?;
+                    let text: String = line
+                        .text
+                        .replace('\t', "    ")
+                        .chars()
+                        .take(available_size.width.saturating_sub(LINE_INDENT))
+                        .collect();
+                    handle_command!(write, Print(text))?;
+                }
+            }
+
+            handle_command!(write, ResetColor)?;
+            handle_command!(write, Clear(ClearType::UntilNewLine))?;
+            handle_command!(write, cursor::MoveToNextLine(1))?;
+        }
+
+        handle_command!(write, ResetColor)?;
+        handle_command!(write, Clear(ClearType::FromCursorDown))?;
+        Ok(())
+    }
+}
+
+/// Lets the user toggle the hunks of `files`, or single lines inside them,
+/// and returns whether they confirmed the selection
+pub fn select_hunks<W>(write: &mut W, files: &mut [FilePatch]) -> Result<bool>
+where
+    W: Write,
+{
+    let mut select = HunkSelect::new(files);
+    if select.hunk_rows.is_empty() {
+        return Ok(false);
+    }
+
+    let mut available_size =
+        AvailableSize::from_temrinal_size(TerminalSize::get()?);
+    select.move_cursor(available_size, 0);
+    select.draw_all_rows(write, available_size)?;
+
+    loop {
+        write.queue(cursor::MoveTo(0, 2))?;
+        write.flush()?;
+        let key_event = match event::read()? {
+            event::Event::Resize(width, height) => {
+                available_size =
+                    AvailableSize::from_temrinal_size(TerminalSize {
+                        width,
+                        height,
+                    });
+                select.move_cursor(available_size, 0);
+                select.draw_all_rows(write, available_size)?;
+                continue;
+            }
+            event::Event::Key(key_event) => key_event,
+            _ => continue,
+        };
+
+        match key_event {
+            KeyEvent {
+                code: KeyCode::Esc, ..
+            }
+            | KeyEvent {
+                code: KeyCode::Char('h'),
+                ..
+            }
+            | KeyEvent {
+                code: KeyCode::Left,
+                ..
+            } if select.line_cursor.is_some() => {
+                select.line_cursor = None;
+                select.move_cursor(available_size, 0);
+            }
+            KeyEvent {
+                code: KeyCode::Esc, ..
+            }
+            | KeyEvent {
+                code: KeyCode::Char('q'),
+                ..
+            }
+            | KeyEvent {
+                code: KeyCode::Char('c'),
+                modifiers: KeyModifiers::CONTROL,
+            } => return Ok(false),
+            KeyEvent {
+                code: KeyCode::Enter,
+                ..
+            }
+            | KeyEvent {
+                code: KeyCode::Char('m'),
+                modifiers: KeyModifiers::CONTROL,
+            } => {
+                if !select.hunks_mut().any(|h| h.is_selected()) {
+                    if let Some(hunk) = select.cursor_hunk() {
+                        hunk.set_selected(true);
+                    }
+                }
+                return Ok(true);
+            }
+            KeyEvent {
+                code: KeyCode::Char('l'),
+                ..
+            }
+            | KeyEvent {
+                code: KeyCode::Right,
+                ..
+            }
+            | KeyEvent {
+                code: KeyCode::Tab, ..
+            } => select.enter_line_mode(available_size),
+            KeyEvent {
+                code: KeyCode::Char('j'),
+                ..
+            }
+            | KeyEvent {
+                code: KeyCode::Char('n'),
+                modifiers: KeyModifiers::CONTROL,
+            }
+            | KeyEvent {
+                code: KeyCode::Down,
+                ..
+            } => select.move_cursor(available_size, 1),
+            KeyEvent {
+                code: KeyCode::Char('k'),
+                ..
+            }
+            | KeyEvent {
+                code: KeyCode::Char('p'),
+                modifiers: KeyModifiers::CONTROL,
+            }
+            | KeyEvent {
+                code: KeyCode::Up, ..
+            } => select.move_cursor(available_size, -1),
+            KeyEvent {
+                code: KeyCode::Char('g'),
+                ..
+            }
+            | KeyEvent {
+                code: KeyCode::Home,
+                ..
+            } => select.move_cursor(available_size, i32::MIN / 2),
+            KeyEvent {
+                code: KeyCode::Char('G'),
+                ..
+            }
+            | KeyEvent {
+                code: KeyCode::End, ..
+            } => select.move_cursor(available_size, i32::MAX / 2),
+            KeyEvent {
+                code: KeyCode::Char(' '),
+                ..
+            } => select.toggle(),
+            KeyEvent {
+                code: KeyCode::Char('a'),
+                ..
+            } => {
+                let all_selected = select
+                    .hunks_mut()
+                    .all(|h| h.is_selected() && !h.is_partially_selected());
+                for hunk in select.hunks_mut() {
+                    hunk.set_selected(!all_selected);
+                }
+            }
+            _ => continue,
+        }
+
+        select.draw_all_rows(write, available_size)?;
+    }
+}
@@ -43,13 +43,12 @@ pub fn key_to_char(key: KeyEvent) -> Option<char> {
     }
 }
 
-pub fn read_line(initial: &str) -> Result<String, ReadlineError> {
+/// Reads a line, or `None` if it was cancelled with ctrl-c or ctrl-d
+pub fn read_line(initial: &str) -> Result<Option<String>, ReadlineError> {
     let mut readline = Editor::<()>::new();
     match readline.readline_with_initial("", (initial, "")) {
-        Ok(line) => Ok(line),
-        Err(ReadlineError::Interrupted) | Err(ReadlineError::Eof) => {
-            Ok("".into())
-        }
+        Ok(line) => Ok(Some(line)),
+        Err(ReadlineError::Interrupted) | Err(ReadlineError::Eof) => Ok(None),
         Err(error) => Err(error),
     }
 }
@@ -0,0 +1,787 @@
+use crate::{
+    action::{chain, serial, task_vec, ActionTask},
+    history_edit::PlanEntry,
+    patch::FilePatch,
+    select::{Entry, State},
+    vcs_error::{ErrorMessages, VcsError},
+    version_control_actions::{
+        task, unsupported, unsupported_error, Capability, Support,
+        VersionControlActions,
+    },
+};
+
+fn str_to_state(s: &str) -> State {
+    match s {
+        "M" => State::Modified,
+        "A" => State::Added,
+        "D" => State::Deleted,
+        "R" => State::Renamed,
+        "C" => State::Copied,
+        _ => State::Unmodified,
+    }
+}
+
+/// Resolves jj's compact rename notation `dir/{old => new}/file` into the
+/// new path
+fn renamed_path(path: &str) -> String {
+    match (path.find('{'), path.find(" => "), path.find('}')) {
+        (Some(open), Some(arrow), Some(close))
+            if open < arrow && arrow < close =>
+        {
+            let mut new_path = String::from(&path[..open]);
+            new_path.push_str(&path[(arrow + 4)..close]);
+            new_path.push_str(&path[(close + 1)..]);
+            new_path.replace("//", "/")
+        }
+        _ => match path.find(" => ") {
+            Some(arrow) => String::from(&path[(arrow + 4)..]),
+            None => String::from(path),
+        },
+    }
+}
+
+fn parse_summary(output: &str) -> Vec<Entry> {
+    output
+        .trim()
+        .split('\n')
+        .map(|e| e.trim())
+        .filter(|e| e.len() > 2)
+        .map(|e| {
+            let (state, filename) = e.split_at(1);
+            Entry {
+                filename: renamed_path(filename.trim()),
+                selected: false,
+                state: str_to_state(state),
+            }
+        })
+        .collect()
+}
+
+/// Quotes a path as a fileset so that jj does not interpret any of its
+/// characters as fileset operators
+fn fileset(filename: &str) -> String {
+    let mut fileset = String::from("root-file:\"");
+    for c in filename.chars() {
+        if c == '"' || c == '\\' {
+            fileset.push('\\');
+        }
+        fileset.push(c);
+    }
+    fileset.push('"');
+    fileset
+}
+
+const ERROR_MESSAGES: ErrorMessages = ErrorMessages {
+    not_a_repository: &["There is no jj repo"],
+    merge_conflict: &[],
+    auth_failure: &["failed to authenticate", "Permission denied (publickey"],
+};
+
+pub struct JjActions {
+    pub current_dir: String,
+}
+
+impl VersionControlActions for JjActions {
+    fn executable_name(&self) -> &'static str {
+        "jj"
+    }
+
+    fn current_dir(&self) -> &str {
+        &self.current_dir[..]
+    }
+
+    fn classify_error(&self, error: VcsError) -> VcsError {
+        error.classify(&ERROR_MESSAGES)
+    }
+
+    fn support(&self, capability: Capability) -> Support {
+        match capability {
+            Capability::Merge => {
+                Support::Differs("creates a new change on top of @ and target")
+            }
+            Capability::Pull => Support::Differs(
+                "only runs jj git fetch and does not rebase @ onto the fetched changes",
+            ),
+            Capability::CreateTag => {
+                Support::Unsupported("jj can't create tags")
+            }
+            Capability::DeleteTag => {
+                Support::Unsupported("jj can't delete tags")
+            }
+            Capability::CommitHunks => Support::Unsupported(
+                "use jj split to commit part of a change",
+            ),
+            Capability::Stash => Support::Unsupported(
+                "the working copy is already a change, use jj new to set it aside",
+            ),
+            Capability::ListBranches
+            | Capability::CreateBranch
+            | Capability::CloseBranch => Support::Differs("uses bookmarks"),
+            Capability::Amend => {
+                Support::Differs("squashes the selected files into @-")
+            }
+            Capability::CherryPick => Support::Differs(
+                "duplicates the revisions onto @ and never stops on conflicts",
+            ),
+            Capability::Backout => Support::Differs(
+                "backs out onto a new child of @ and never stops on conflicts",
+            ),
+            Capability::EditHistory => Support::Unsupported(
+                "jj rewrites history with squash, describe and rebase instead",
+            ),
+            Capability::Submodules => {
+                Support::Unsupported("jj ignores git submodules")
+            }
+            Capability::Worktrees => Support::Unsupported(
+                "jj keeps several working copies with jj workspace",
+            ),
+            Capability::Reflog => Support::Unsupported(
+                "jj records operations instead, undo them with jj op restore",
+            ),
+            Capability::Bisect => Support::Unsupported(
+                "jj has no bisect to mark revisions with",
+            ),
+            Capability::Rebase => Support::Differs(
+                "never stops, conflicts are recorded in the rebased changes",
+            ),
+            _ => Support::Supported,
+        }
+    }
+
+    fn set_root(&mut self) -> Result<(), VcsError> {
+        let mut command = self.command();
+        let dir = self.run_command(command.arg("root"))?;
+
+        let dir = dir
+            .lines()
+            .next()
+            .expect("root directory is an empty string");
+        self.current_dir = dir.to_owned();
+
+        Ok(())
+    }
+
+    fn get_root(&self) -> &str {
+        &self.current_dir[..]
+    }
+
+    fn switch_root(&mut self, _dir: &str) -> Result<(), VcsError> {
+        Err(unsupported_error(self, "switching worktrees"))
+    }
+
+    fn get_current_changed_files(&self) -> Result<Vec<Entry>, VcsError> {
+        let output = self.run_command(self.command().args([
+            "diff",
+            "--summary",
+            "--color",
+            "never",
+        ]))?;
+        Ok(parse_summary(&output))
+    }
+
+    fn get_tracked_files(&self) -> Result<Vec<Entry>, VcsError> {
+        let output = self.run_command(self.command().args(["file", "list"]))?;
+        let files = output
+            .lines()
+            .filter(|f| !f.is_empty())
+            .map(|f| Entry {
+                filename: String::from(f),
+                selected: false,
+                state: State::Clean,
+            })
+            .collect();
+        Ok(files)
+    }
+
+    fn get_revision_changed_files(
+        &self,
+        target: &str,
+    ) -> Result<Vec<Entry>, VcsError> {
+        let output = self.run_command(
+            self.command()
+                .args(["diff", "--summary", "--color", "never", "-r"])
+                .arg(target),
+        )?;
+        Ok(parse_summary(&output))
+    }
+
+    fn version(&self) -> Result<String, VcsError> {
+        self.run_command(self.command().arg("--version"))
+    }
+
+    fn status(&self) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.args(["status", "--color", "always"]);
+        })
+    }
+
+    fn reflog(&self) -> Box<dyn ActionTask> {
+        unsupported(self, "listing previous working copy revisions")
+    }
+
+    fn current_export(&self) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.args(["show", "--color", "always"]);
+        })
+    }
+
+    fn log(&self, count: usize) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            let count_str = format!("{}", count);
+            let template = "\"\x1e\" ++ change_id ++ \"\x1f\" ++ change_id.short() ++ \"\x1f\" ++ parents.map(|c| c.change_id()).join(\" \") ++ \"\x1f\" ++ author.name() ++ \"\x1f\" ++ author.timestamp().format(\"%Y-%m-%d\") ++ \"\x1f\" ++ separate(\" \", bookmarks, tags) ++ \"\x1f\" ++ separate(\" \", if(conflict, \"(conflict)\"), if(empty, \"(empty)\"), if(immutable, \"(immutable)\")) ++ \"\x1f\" ++ description.first_line()";
+            command
+                .arg("log")
+                .arg("--color")
+                .arg("never")
+                .arg("--template")
+                .arg(template)
+                .arg("-n")
+                .arg(&count_str);
+        })
+    }
+
+    fn blame(
+        &self,
+        filename: &str,
+        revision: Option<&str>,
+    ) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            let template = "commit.change_id() ++ \"\x1f\" ++ commit.change_id().short() ++ \"\x1f\" ++ commit.author().name() ++ \"\x1f\" ++ commit.author().timestamp().format(\"%Y-%m-%d\") ++ \"\x1f\" ++ line_number ++ \"\x1f\" ++ content";
+            command.args(["file", "annotate", "--template", template]);
+            if let Some(revision) = revision {
+                command.arg("-r").arg(revision);
+            }
+            command.arg(filename);
+        })
+    }
+
+    fn get_parent_revision(&self, revision: &str) -> Result<String, VcsError> {
+        let output = self.run_command(
+            self.command()
+                .args(["log", "--no-graph", "-r"])
+                .arg(format!("{}-", revision))
+                .args(["-T", "change_id ++ \"\\n\""]),
+        )?;
+        Ok(String::from(output.lines().next().unwrap_or("").trim()))
+    }
+
+    fn current_diff_all(&self) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.args(["diff", "--color", "always"]);
+        })
+    }
+
+    fn current_diff_selected(&self, entries: &[Entry]) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.args(["diff", "--color", "always", "--"]);
+            for e in entries.iter().filter(|e| e.selected) {
+                command.arg(fileset(&e.filename));
+            }
+        })
+    }
+
+    fn revision_changes(&self, target: &str) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command
+                .args(["diff", "--summary", "--color", "always", "-r"])
+                .arg(target);
+        })
+    }
+
+    fn revision_diff_all(&self, target: &str) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command
+                .args(["diff", "--color", "always", "-r"])
+                .arg(target);
+        })
+    }
+
+    fn revision_diff_selected(
+        &self,
+        target: &str,
+        entries: &[Entry],
+    ) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command
+                .args(["diff", "--color", "always", "-r"])
+                .arg(target)
+                .arg("--");
+
+            for e in entries.iter().filter(|e| e.selected) {
+                command.arg(fileset(&e.filename));
+            }
+        })
+    }
+
+    fn commit_all(&self, message: &str) -> Box<dyn ActionTask> {
+        let mut tasks = task_vec();
+        tasks.push(task(self, |command| {
+            command.arg("describe").arg("-m").arg(message);
+        }));
+        tasks.push(task(self, |command| {
+            command.arg("new");
+        }));
+        serial(tasks)
+    }
+
+    fn commit_selected(
+        &self,
+        message: &str,
+        entries: &[Entry],
+    ) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.arg("commit").arg("-m").arg(message).arg("--");
+            for e in entries.iter().filter(|e| e.selected) {
+                command.arg(fileset(&e.filename));
+            }
+        })
+    }
+
+    fn get_current_patch(
+        &self,
+        _entries: &[Entry],
+    ) -> Result<Vec<FilePatch>, VcsError> {
+        Err(unsupported_error(self, "committing hunks"))
+    }
+
+    fn commit_hunks(
+        &self,
+        _message: &str,
+        _files: &[FilePatch],
+    ) -> Box<dyn ActionTask> {
+        unsupported(self, "committing hunks")
+    }
+
+    fn get_last_commit_message(&self) -> Result<String, VcsError> {
+        self.run_command(self.command().args([
+            "log",
+            "--no-graph",
+            "-r",
+            "@-",
+            "-T",
+            "description",
+        ]))
+    }
+
+    fn is_last_commit_published(&self) -> Result<bool, VcsError> {
+        let output = self.run_command(self.command().args([
+            "log",
+            "--no-graph",
+            "-r",
+            "@- & immutable()",
+            "-T",
+            "commit_id",
+        ]))?;
+        Ok(!output.trim().is_empty())
+    }
+
+    fn amend(&self, message: &str, entries: &[Entry]) -> Box<dyn ActionTask> {
+        if !entries.iter().any(|e| e.selected) {
+            return task(self, |command| {
+                command.args(["describe", "-r", "@-", "-m"]).arg(message);
+            });
+        }
+
+        task(self, |command| {
+            command.arg("squash").arg("-m").arg(message).arg("--");
+            for e in entries.iter().filter(|e| e.selected) {
+                command.arg(fileset(&e.filename));
+            }
+        })
+    }
+
+    fn revert_all(&self) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.arg("restore");
+        })
+    }
+
+    fn revert_selected(&self, entries: &[Entry]) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.arg("restore").arg("--");
+            for e in entries.iter().filter(|e| e.selected) {
+                command.arg(fileset(&e.filename));
+            }
+        })
+    }
+
+    fn update(&self, target: &str) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.arg("new").arg(target);
+        })
+    }
+
+    fn merge(&self, target: &str) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.arg("new").arg("@").arg(target);
+        })
+    }
+
+    fn rebase(&self, target: &str) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.args(["rebase", "-d"]).arg(target);
+        })
+    }
+
+    fn rebase_continue(&self) -> Box<dyn ActionTask> {
+        unsupported(self, "continuing a rebase since it never stops")
+    }
+
+    fn rebase_skip(&self) -> Box<dyn ActionTask> {
+        unsupported(self, "skipping a change since rebases never stop")
+    }
+
+    fn rebase_abort(&self) -> Box<dyn ActionTask> {
+        unsupported(self, "aborting a rebase since it never stops")
+    }
+
+    fn cherry_pick(&self, revisions: &[String]) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.arg("duplicate").args(revisions).args(["-d", "@"]);
+        })
+    }
+
+    fn cherry_pick_continue(&self) -> Box<dyn ActionTask> {
+        unsupported(self, "continuing a duplicate since it never stops")
+    }
+
+    fn cherry_pick_abort(&self) -> Box<dyn ActionTask> {
+        unsupported(self, "aborting a duplicate since it never stops")
+    }
+
+    fn backout(&self, revision: &str, message: &str) -> Box<dyn ActionTask> {
+        // conflicts are recorded in the backout instead of stopping on them
+        // so there is never a backout in progress to commit
+        let mut tasks = task_vec();
+        tasks.push(task(self, |command| {
+            command
+                .args(["backout", "-r"])
+                .arg(revision)
+                .args(["-d", "@"]);
+        }));
+        // the backout is the newest child of @ since it was just created
+        tasks.push(task(self, |command| {
+            command
+                .args(["describe", "-r", "latest(@+)", "-m"])
+                .arg(message);
+        }));
+        chain(tasks)
+    }
+
+    fn bisect_start(&self, _bad: &str, _good: &str) -> Box<dyn ActionTask> {
+        unsupported(self, "bisecting")
+    }
+
+    fn bisect_good(&self) -> Box<dyn ActionTask> {
+        unsupported(self, "bisecting")
+    }
+
+    fn bisect_bad(&self) -> Box<dyn ActionTask> {
+        unsupported(self, "bisecting")
+    }
+
+    fn bisect_skip(&self) -> Box<dyn ActionTask> {
+        unsupported(self, "bisecting")
+    }
+
+    fn bisect_run(
+        &self,
+        _command: &str,
+        _args: &[String],
+    ) -> Box<dyn ActionTask> {
+        unsupported(self, "bisecting")
+    }
+
+    fn bisect_reset(&self) -> Box<dyn ActionTask> {
+        unsupported(self, "bisecting")
+    }
+
+    fn get_history(&self, _base: &str) -> Result<Vec<PlanEntry>, VcsError> {
+        Err(unsupported_error(self, "editing history"))
+    }
+
+    fn edit_history(
+        &self,
+        _base: &str,
+        _plan: &[PlanEntry],
+    ) -> Box<dyn ActionTask> {
+        unsupported(self, "editing history")
+    }
+
+    fn conflicts(&self) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.args(["resolve", "--list", "--color", "always"]);
+        })
+    }
+
+    fn take_other(&self) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.args(["resolve", "--tool", ":theirs"]);
+        })
+    }
+
+    fn take_local(&self) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.args(["resolve", "--tool", ":ours"]);
+        })
+    }
+
+    fn fetch(&self, remote: Option<&str>) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.args(["git", "fetch"]);
+            match remote {
+                Some(remote) => command.args(["--remote", remote]),
+                None => command.arg("--all-remotes"),
+            };
+        })
+    }
+
+    fn pull(&self, remote: Option<&str>) -> Box<dyn ActionTask> {
+        self.fetch(remote)
+    }
+
+    fn push(&self, remote: Option<&str>) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.args(["git", "push"]);
+            if let Some(remote) = remote {
+                command.args(["--remote", remote]);
+            }
+        })
+    }
+
+    fn list_remotes(&self) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.args(["git", "remote", "list"]);
+        })
+    }
+
+    fn add_remote(&self, name: &str, url: &str) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.args(["git", "remote", "add", name, url]);
+        })
+    }
+
+    fn rename_remote(&self, name: &str, new_name: &str) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.args(["git", "remote", "rename", name, new_name]);
+        })
+    }
+
+    fn remove_remote(&self, name: &str) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.args(["git", "remote", "remove", name]);
+        })
+    }
+
+    fn set_remote_url(&self, name: &str, url: &str) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.args(["git", "remote", "set-url", name, url]);
+        })
+    }
+
+    fn list_tags(&self) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.args([
+                "tag",
+                "list",
+                "--color",
+                "never",
+                "--template",
+                concat!(
+                    "name ++ if(normal_target, \" \" ++ ",
+                    "normal_target.commit_id().short() ++ \" \" ++ ",
+                    "normal_target.committer().timestamp().format(\"%Y-%m-%d\")) ++ \"\\n\"",
+                ),
+            ]);
+        })
+    }
+
+    fn get_tags(&self) -> Result<Vec<String>, VcsError> {
+        let output = self.run_command(self.command().args([
+            "tag",
+            "list",
+            "--color",
+            "never",
+            "--template",
+            "name ++ \"\\n\"",
+        ]))?;
+        Ok(output.lines().map(String::from).collect())
+    }
+
+    fn create_tag(
+        &self,
+        _name: &str,
+        _message: Option<&str>,
+        _force: bool,
+        _remote: Option<&str>,
+    ) -> Box<dyn ActionTask> {
+        unsupported(self, "creating tags")
+    }
+
+    fn delete_tag(
+        &self,
+        _name: &str,
+        _remote: Option<&str>,
+    ) -> Box<dyn ActionTask> {
+        unsupported(self, "deleting tags")
+    }
+
+    fn list_submodules(&self) -> Box<dyn ActionTask> {
+        unsupported(self, "submodules")
+    }
+
+    fn init_submodules(&self) -> Box<dyn ActionTask> {
+        unsupported(self, "submodules")
+    }
+
+    fn update_submodules(&self) -> Box<dyn ActionTask> {
+        unsupported(self, "submodules")
+    }
+
+    fn sync_submodules(&self) -> Box<dyn ActionTask> {
+        unsupported(self, "submodules")
+    }
+
+    fn get_superproject_root(&self) -> Result<String, VcsError> {
+        Err(unsupported_error(self, "submodules"))
+    }
+
+    fn list_worktrees(&self) -> Box<dyn ActionTask> {
+        unsupported(self, "worktrees")
+    }
+
+    fn add_worktree(&self, _path: &str, _target: &str) -> Box<dyn ActionTask> {
+        unsupported(self, "worktrees")
+    }
+
+    fn remove_worktree(&self, _path: &str) -> Box<dyn ActionTask> {
+        unsupported(self, "worktrees")
+    }
+
+    fn prune_worktrees(&self) -> Box<dyn ActionTask> {
+        unsupported(self, "worktrees")
+    }
+
+    fn list_branches(&self) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.args([
+                "bookmark",
+                "list",
+                "--color",
+                "never",
+                "--template",
+                "name ++ \"\\n\"",
+            ]);
+        })
+    }
+
+    fn create_branch(
+        &self,
+        name: &str,
+        _remote: Option<&str>,
+    ) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command
+                .arg("bookmark")
+                .arg("create")
+                .arg(name)
+                .arg("-r")
+                .arg("@");
+        })
+    }
+
+    fn create_branch_at(
+        &self,
+        name: &str,
+        revision: &str,
+    ) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command
+                .arg("bookmark")
+                .arg("create")
+                .arg(name)
+                .arg("-r")
+                .arg(revision);
+        })
+    }
+
+    fn close_branch(
+        &self,
+        name: &str,
+        _remote: Option<&str>,
+    ) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.arg("bookmark").arg("delete").arg(name);
+        })
+    }
+
+    fn list_stashes(&self) -> Box<dyn ActionTask> {
+        unsupported(self, "stashes")
+    }
+
+    fn stash_all(&self, _message: &str) -> Box<dyn ActionTask> {
+        unsupported(self, "stashes")
+    }
+
+    fn stash_selected(
+        &self,
+        _message: &str,
+        _entries: &[Entry],
+    ) -> Box<dyn ActionTask> {
+        unsupported(self, "stashes")
+    }
+
+    fn show_stash(&self, _name: &str) -> Box<dyn ActionTask> {
+        unsupported(self, "stashes")
+    }
+
+    fn apply_stash(&self, _name: &str) -> Box<dyn ActionTask> {
+        unsupported(self, "stashes")
+    }
+
+    fn pop_stash(&self, _name: &str) -> Box<dyn ActionTask> {
+        unsupported(self, "stashes")
+    }
+
+    fn drop_stash(&self, _name: &str) -> Box<dyn ActionTask> {
+        unsupported(self, "stashes")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renamed_path_resolves_the_compact_notation() {
+        assert_eq!(renamed_path("src/{old.rs => new.rs}"), "src/new.rs");
+        assert_eq!(renamed_path("{a => b}/file.txt"), "b/file.txt");
+        assert_eq!(renamed_path("src/{old => }/file.rs"), "src/file.rs");
+        assert_eq!(renamed_path("old.txt => new.txt"), "new.txt");
+        assert_eq!(renamed_path("plain {braces}.txt"), "plain {braces}.txt");
+        assert_eq!(renamed_path("file.txt"), "file.txt");
+    }
+
+    #[test]
+    fn summary_lines_become_entries() {
+        let entries =
+            parse_summary("M src/lib.rs\nA new.txt\nR {a => b}.txt\n\n");
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].filename, "src/lib.rs");
+        assert!(matches!(entries[0].state, State::Modified));
+        assert_eq!(entries[1].filename, "new.txt");
+        assert!(matches!(entries[1].state, State::Added));
+        assert_eq!(entries[2].filename, "b.txt");
+        assert!(matches!(entries[2].state, State::Renamed));
+    }
+
+    #[test]
+    fn fileset_quotes_operators_and_escapes() {
+        assert_eq!(fileset("a.txt"), r#"root-file:"a.txt""#);
+        assert_eq!(fileset("a | b (1).txt"), r#"root-file:"a | b (1).txt""#);
+        assert_eq!(
+            fileset(r#"say "hi"\.txt"#),
+            r#"root-file:"say \"hi\"\\.txt""#
+        );
+    }
+}
@@ -0,0 +1,21 @@
+//! The version control layer of verco
+//!
+//! Each backend implements `VersionControlActions` whose actions return
+//! `ActionTask`s. Tasks are polled with an `async_process::Executor` and
+//! can be combined with `action::serial` and `action::parallel`.
+
+pub mod action;
+pub mod async_process;
+pub mod blame;
+pub mod fossil_actions;
+pub mod git2_actions;
+pub mod git_actions;
+pub mod hg_actions;
+pub mod history_edit;
+pub mod jj_actions;
+pub mod log_entry;
+pub mod patch;
+pub mod select;
+pub mod svn_actions;
+pub mod vcs_error;
+pub mod version_control_actions;
@@ -0,0 +1,205 @@
+/// Separates the graph prefix from the fields of a log record
+pub const RECORD_SEPARATOR: char = '\x1e';
+/// Separates the fields of a log record
+pub const FIELD_SEPARATOR: char = '\x1f';
+
+/// A single revision from a log output
+///
+/// Backends emit one record per revision as
+/// `graph RS id US short_id US parents US author US date US refs US branch US
+/// subject` where `RS` and `US` are `RECORD_SEPARATOR` and `FIELD_SEPARATOR`
+/// and `parents` is space separated. Lines without a `RECORD_SEPARATOR` are
+/// graph continuation lines that belong to the revision above them.
+#[derive(Clone, Default)]
+pub struct LogEntry {
+    pub graph: String,
+    pub id: String,
+    pub short_id: String,
+    pub parents: Vec<String>,
+    pub author: String,
+    pub date: String,
+    pub refs: String,
+    /// Branch, phase or any other backend specific revision state
+    pub branch: String,
+    pub subject: String,
+    /// Graph only lines drawn below this revision's line
+    pub graph_lines: Vec<String>,
+}
+
+impl LogEntry {
+    pub fn parse(line: &str) -> Option<Self> {
+        let separator = line.find(RECORD_SEPARATOR)?;
+        let graph = &line[..separator];
+        let mut fields = line[(separator + RECORD_SEPARATOR.len_utf8())..]
+            .split(FIELD_SEPARATOR);
+        let mut next = || fields.next().unwrap_or("").trim();
+
+        Some(Self {
+            graph: String::from(graph.trim_end()),
+            id: String::from(next()),
+            short_id: String::from(next()),
+            parents: next().split_whitespace().map(String::from).collect(),
+            author: String::from(next()),
+            date: String::from(next()),
+            refs: String::from(next()),
+            branch: String::from(next()),
+            subject: String::from(next()),
+            graph_lines: Vec::new(),
+        })
+    }
+
+    pub fn parse_all(output: &str) -> Vec<Self> {
+        let mut entries: Vec<Self> = Vec::new();
+        for line in output.lines() {
+            if let Some(entry) = Self::parse(line) {
+                entries.push(entry);
+            } else if let Some(entry) = entries.last_mut() {
+                if !line.trim().is_empty() {
+                    entry.graph_lines.push(String::from(line.trim_end()));
+                }
+            }
+        }
+        entries
+    }
+
+    /// Formats the record for a single revision in the format expected by
+    /// `parse` for backends that build their log in-process
+    pub fn format_record(&self) -> String {
+        let fields = [
+            &self.id[..],
+            &self.short_id[..],
+            &self.parents.join(" ")[..],
+            &self.author[..],
+            &self.date[..],
+            &self.refs[..],
+            &self.branch[..],
+            &self.subject[..],
+        ];
+
+        let mut record = self.graph.clone();
+        record.push(RECORD_SEPARATOR);
+        for (i, field) in fields.iter().enumerate() {
+            if i > 0 {
+                record.push(FIELD_SEPARATOR);
+            }
+            record.push_str(field);
+        }
+        record
+    }
+}
+
+/// Formats seconds since the unix epoch, already shifted to the wanted
+/// timezone, as `YYYY-MM-DD`
+pub(crate) fn format_epoch_date(seconds: i64) -> String {
+    let days = seconds.div_euclid(24 * 60 * 60);
+
+    // civil date from days since unix epoch
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds a record line from `graph` and `fields` joined with the
+    /// separators
+    fn record(graph: &str, fields: &[&str]) -> String {
+        format!(
+            "{}{}{}",
+            graph,
+            RECORD_SEPARATOR,
+            fields.join(&FIELD_SEPARATOR.to_string())
+        )
+    }
+
+    const FIELDS: [&str; 8] = [
+        "4f2a9c1e",
+        "4f2a9c1",
+        "1a2b3c4 5d6e7f8",
+        "alice",
+        "2024-01-02",
+        "HEAD -> main, origin/main",
+        "draft",
+        "merge the parser",
+    ];
+
+    #[test]
+    fn record_fields_are_parsed() {
+        let entry = LogEntry::parse(&record("* | ", &FIELDS)).unwrap();
+        assert_eq!(entry.graph, "* |");
+        assert_eq!(entry.id, "4f2a9c1e");
+        assert_eq!(entry.short_id, "4f2a9c1");
+        assert_eq!(entry.parents, ["1a2b3c4", "5d6e7f8"]);
+        assert_eq!(entry.author, "alice");
+        assert_eq!(entry.date, "2024-01-02");
+        assert_eq!(entry.refs, "HEAD -> main, origin/main");
+        assert_eq!(entry.branch, "draft");
+        assert_eq!(entry.subject, "merge the parser");
+    }
+
+    #[test]
+    fn line_without_record_separator_is_not_a_record() {
+        assert!(LogEntry::parse("| |").is_none());
+        assert!(LogEntry::parse("").is_none());
+    }
+
+    #[test]
+    fn missing_fields_are_empty_and_extra_ones_ignored() {
+        let entry =
+            LogEntry::parse(&record("o", &["abc", "ab", "", "bob"])).unwrap();
+        assert_eq!(entry.author, "bob");
+        assert!(entry.parents.is_empty());
+        assert!(entry.date.is_empty());
+        assert!(entry.refs.is_empty());
+        assert!(entry.subject.is_empty());
+
+        let mut fields = FIELDS.to_vec();
+        fields.push("unexpected");
+        let entry = LogEntry::parse(&record("o", &fields)).unwrap();
+        assert_eq!(entry.subject, "merge the parser");
+    }
+
+    #[test]
+    fn empty_subject_and_refs_survive_a_round_trip() {
+        let mut fields = FIELDS;
+        fields[5] = "";
+        fields[7] = "";
+        let line = record("@", &fields);
+        let entry = LogEntry::parse(&line).unwrap();
+        assert!(entry.refs.is_empty());
+        assert!(entry.subject.is_empty());
+        assert_eq!(entry.format_record(), line);
+    }
+
+    #[test]
+    fn graph_lines_belong_to_the_record_above() {
+        let output = format!(
+            "| ignored before any record\n{}\n|\\\n| |  \n\n{}\n",
+            record("*", &FIELDS),
+            record("| *", &["b", "b", "", "", "", "", "", "second"])
+        );
+        let entries = LogEntry::parse_all(&output);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].graph_lines, ["|\\", "| |"]);
+        assert_eq!(entries[1].graph, "| *");
+        assert_eq!(entries[1].subject, "second");
+        assert!(entries[1].graph_lines.is_empty());
+    }
+
+    #[test]
+    fn epoch_dates_are_formatted() {
+        assert_eq!(format_epoch_date(0), "1970-01-01");
+        assert_eq!(format_epoch_date(951_782_400), "2000-02-29");
+        assert_eq!(format_epoch_date(-1), "1969-12-31");
+    }
+}
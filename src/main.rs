@@ -1,18 +1,29 @@
-mod action;
+mod action_kind;
 mod application;
-mod async_process;
+mod config;
 mod custom_actions;
-mod git_actions;
-mod hg_actions;
+mod history_view;
+mod hunk_view;
 mod input;
 mod repositories;
 mod scroll_view;
-mod select;
+mod select_view;
 mod tui;
 mod tui_util;
-mod version_control_actions;
+
+use verco::history_edit;
 
 fn main() {
+    // git and hg run verco as their editor while editing history
+    let args: Vec<_> = std::env::args().collect();
+    if args.get(1).map(String::as_str) == Some(history_edit::EDITOR_FLAG) {
+        if let Err(error) = history_edit::run_editor(&args[2..]) {
+            eprintln!("{}", error);
+            std::process::exit(1);
+        }
+        return;
+    }
+
     if !crossterm::tty::IsTty::is_tty(&std::io::stdin()) {
         eprintln!("not tty");
         return;
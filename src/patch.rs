@@ -0,0 +1,703 @@
+/// The kind of a line inside a hunk
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LineKind {
+    Context,
+    Added,
+    Removed,
+    /// The `\ No newline at end of file` marker for the line above it
+    NoNewline,
+}
+
+#[derive(Clone)]
+pub struct HunkLine {
+    pub kind: LineKind,
+    /// The line as it appears in the diff, including its prefix
+    pub text: String,
+    /// Only meaningful for added and removed lines
+    pub selected: bool,
+}
+
+#[derive(Clone)]
+pub struct Hunk {
+    pub old_start: usize,
+    pub old_count: usize,
+    pub new_start: usize,
+    pub new_count: usize,
+    /// Whatever follows the line ranges in the hunk header
+    pub section: String,
+    pub lines: Vec<HunkLine>,
+}
+
+impl Hunk {
+    fn parse_header(line: &str) -> Option<Self> {
+        let ranges = line.strip_prefix("@@ -")?;
+        let end = ranges.find(" @@")?;
+        let section = &ranges[(end + 3)..];
+        let (old, new) = ranges[..end].split_once(" +")?;
+
+        fn parse_range(range: &str) -> Option<(usize, usize)> {
+            match range.split_once(',') {
+                Some((start, count)) => {
+                    Some((start.parse().ok()?, count.parse().ok()?))
+                }
+                None => Some((range.parse().ok()?, 1)),
+            }
+        }
+
+        let (old_start, old_count) = parse_range(old)?;
+        let (new_start, new_count) = parse_range(new)?;
+        Some(Self {
+            old_start,
+            old_count,
+            new_start,
+            new_count,
+            section: String::from(section),
+            lines: Vec::new(),
+        })
+    }
+
+    pub fn header(&self) -> String {
+        format!(
+            "@@ -{},{} +{},{} @@{}",
+            self.old_start,
+            self.old_count,
+            self.new_start,
+            self.new_count,
+            self.section
+        )
+    }
+
+    fn changes(&self) -> impl Iterator<Item = &HunkLine> {
+        self.lines.iter().filter(|l| l.is_change())
+    }
+
+    /// Whether any of its changes is selected
+    pub fn is_selected(&self) -> bool {
+        self.changes().any(|l| l.selected)
+    }
+
+    /// Whether only some of its changes are selected
+    pub fn is_partially_selected(&self) -> bool {
+        self.is_selected() && self.changes().any(|l| !l.selected)
+    }
+
+    pub fn set_selected(&mut self, selected: bool) {
+        for line in self.lines.iter_mut().filter(|l| l.is_change()) {
+            line.selected = selected;
+        }
+    }
+}
+
+impl HunkLine {
+    pub fn is_change(&self) -> bool {
+        matches!(self.kind, LineKind::Added | LineKind::Removed)
+    }
+}
+
+/// The changes to a single file in a unified diff
+#[derive(Clone)]
+pub struct FilePatch {
+    /// Every line before the first hunk, like `diff --git` and `+++`
+    pub header: Vec<String>,
+    pub filename: String,
+    pub hunks: Vec<Hunk>,
+}
+
+impl FilePatch {
+    fn new(header_line: &str) -> Self {
+        Self {
+            header: vec![String::from(header_line)],
+            filename: String::new(),
+            hunks: Vec::new(),
+        }
+    }
+
+    /// Whether the file doesn't exist before the patch
+    pub fn is_new_file(&self) -> bool {
+        self.header
+            .iter()
+            .any(|l| l.starts_with("new file mode") || l == "--- /dev/null")
+    }
+
+    fn parse_filename(&mut self, line: &str) {
+        let (path, prefix) = if let Some(path) = line.strip_prefix("+++ ") {
+            (path, "b/")
+        } else if let Some(path) = line.strip_prefix("--- ") {
+            (path, "a/")
+        } else {
+            return;
+        };
+
+        // remove any trailing timestamp
+        let path = path.split('\t').next().unwrap_or(path);
+        if path != "/dev/null" {
+            let path = path.strip_prefix(prefix).unwrap_or(path);
+            self.filename = String::from(path);
+        }
+    }
+}
+
+/// Splits a unified diff, as output by `git diff` or `hg diff`, into files
+/// and hunks
+pub fn parse_patch(diff: &str) -> Vec<FilePatch> {
+    let mut files: Vec<FilePatch> = Vec::new();
+    for line in diff.lines() {
+        if line.starts_with("diff ") {
+            files.push(FilePatch::new(line));
+            continue;
+        }
+
+        let file = match files.last_mut() {
+            Some(file) => file,
+            None => {
+                // plain diffs may start directly with the file names
+                if line.starts_with("--- ") {
+                    let mut file = FilePatch::new(line);
+                    file.parse_filename(line);
+                    files.push(file);
+                }
+                continue;
+            }
+        };
+
+        if let Some(hunk) = Hunk::parse_header(line) {
+            file.hunks.push(hunk);
+            continue;
+        }
+
+        let hunk = match file.hunks.last_mut() {
+            Some(hunk) => hunk,
+            None => {
+                file.header.push(String::from(line));
+                file.parse_filename(line);
+                continue;
+            }
+        };
+
+        let kind = match line.chars().next() {
+            Some('+') => LineKind::Added,
+            Some('-') => LineKind::Removed,
+            Some('\\') => LineKind::NoNewline,
+            _ => LineKind::Context,
+        };
+        hunk.lines.push(HunkLine {
+            kind,
+            text: String::from(line),
+            selected: false,
+        });
+    }
+
+    files
+}
+
+/// Builds a patch with only the selected changes of `files`
+///
+/// Unselected added lines are left out and unselected removed lines become
+/// context so the patch applies to the same base as the original diff
+pub fn format_selected(files: &[FilePatch]) -> String {
+    let mut patch = String::new();
+    for file in files {
+        if !file.hunks.iter().any(|h| h.is_selected()) {
+            continue;
+        }
+
+        // a deletion with lines left unselected only modifies the file
+        let is_partial = file.hunks.iter().any(|h| !h.is_selected())
+            || file.hunks.iter().any(|h| h.is_partially_selected());
+        for line in &file.header {
+            if is_partial && line.starts_with("+++ /dev/null") {
+                patch.push_str("+++ b/");
+                patch.push_str(&file.filename);
+            } else if is_partial && line.starts_with("deleted file mode") {
+                continue;
+            } else {
+                patch.push_str(line);
+            }
+            patch.push('\n');
+        }
+
+        let mut offset = 0;
+        for hunk in file.hunks.iter().filter(|h| h.is_selected()) {
+            let mut lines: Vec<String> = Vec::new();
+            let mut old_count = 0;
+            let mut new_count = 0;
+            let mut kept_previous = false;
+            let mut kept_removed = false;
+            for (i, line) in hunk.lines.iter().enumerate() {
+                let text = match (line.kind, line.selected) {
+                    (LineKind::Context, _) | (LineKind::Removed, false) => {
+                        old_count += 1;
+                        new_count += 1;
+                        format!(" {}", &line.text[1.min(line.text.len())..])
+                    }
+                    (LineKind::Added, true) => {
+                        new_count += 1;
+                        line.text.clone()
+                    }
+                    (LineKind::Removed, true) => {
+                        old_count += 1;
+                        line.text.clone()
+                    }
+                    (LineKind::Added, false) => {
+                        kept_previous = false;
+                        continue;
+                    }
+                    (LineKind::NoNewline, _) => {
+                        let adds_after = hunk.lines[i..]
+                            .iter()
+                            .any(|l| l.kind == LineKind::Added && l.selected);
+                        if kept_previous && kept_removed && adds_after {
+                            // the old last line is kept but the selected
+                            // lines after it need it to end with a newline
+                            if let Some(context) = lines.pop() {
+                                let text = &context[1..];
+                                lines.push(format!("-{}", text));
+                                lines.push(line.text.clone());
+                                lines.push(format!("+{}", text));
+                            }
+                        } else if kept_previous {
+                            lines.push(line.text.clone());
+                        }
+                        continue;
+                    }
+                };
+                kept_removed = line.kind == LineKind::Removed && !line.selected;
+                kept_previous = true;
+                lines.push(text);
+            }
+
+            // an empty range starts at the line before it
+            let new_start = if new_count == 0 {
+                (hunk.old_start as isize + offset - 1).max(0) as usize
+            } else if old_count == 0 {
+                (hunk.old_start as isize + offset + 1) as usize
+            } else {
+                (hunk.old_start as isize + offset) as usize
+            };
+            offset += new_count as isize - old_count as isize;
+
+            let header = Hunk {
+                old_start: hunk.old_start,
+                old_count,
+                new_start,
+                new_count,
+                section: hunk.section.clone(),
+                lines: Vec::new(),
+            }
+            .header();
+            patch.push_str(&header);
+            patch.push('\n');
+            for line in lines {
+                patch.push_str(&line);
+                patch.push('\n');
+            }
+        }
+    }
+    patch
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, io::Write, process};
+
+    use super::*;
+
+    const GIT_DIFF: &str = "\
+diff --git a/src/main.rs b/src/main.rs
+index 3b18e51..a0a8e7c 100644
+--- a/src/main.rs
++++ b/src/main.rs
+@@ -1,3 +1,3 @@ fn main() {
+ one
+-two
++three
+ four
+@@ -10 +10,2 @@
+ ten
++eleven
+\\ No newline at end of file
+diff --git a/new.txt b/new.txt
+new file mode 100644
+index 0000000..e69de29
+--- /dev/null
++++ b/new.txt
+@@ -0,0 +1 @@
++hello
+";
+
+    const HG_DIFF: &str = "\
+diff --git a/old.txt b/old.txt
+deleted file mode 100644
+--- a/old.txt
++++ /dev/null
+@@ -1,2 +0,0 @@
+-first
+-second
+diff --git a/README b/README
+--- a/README
++++ b/README
+@@ -2,1 +2,1 @@
+-before
++after
+";
+
+    #[test]
+    fn git_diff_is_split_into_files_and_hunks() {
+        let files = parse_patch(GIT_DIFF);
+        assert_eq!(files.len(), 2);
+
+        let main = &files[0];
+        assert_eq!(main.filename, "src/main.rs");
+        assert_eq!(main.header.len(), 4);
+        assert!(!main.is_new_file());
+        assert_eq!(main.hunks.len(), 2);
+
+        let hunk = &main.hunks[0];
+        assert_eq!(
+            (
+                hunk.old_start,
+                hunk.old_count,
+                hunk.new_start,
+                hunk.new_count
+            ),
+            (1, 3, 1, 3)
+        );
+        assert_eq!(hunk.section, " fn main() {");
+        let kinds: Vec<_> = hunk.lines.iter().map(|l| l.kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                LineKind::Context,
+                LineKind::Removed,
+                LineKind::Added,
+                LineKind::Context
+            ]
+        );
+
+        let hunk = &main.hunks[1];
+        assert_eq!(
+            (
+                hunk.old_start,
+                hunk.old_count,
+                hunk.new_start,
+                hunk.new_count
+            ),
+            (10, 1, 10, 2)
+        );
+        assert_eq!(hunk.lines[2].kind, LineKind::NoNewline);
+
+        let new = &files[1];
+        assert_eq!(new.filename, "new.txt");
+        assert!(new.is_new_file());
+        assert_eq!(new.hunks[0].header(), "@@ -0,0 +1,1 @@");
+    }
+
+    #[test]
+    fn hg_git_diff_keeps_the_name_of_deleted_files() {
+        let files = parse_patch(HG_DIFF);
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].filename, "old.txt");
+        assert_eq!(files[0].hunks[0].lines.len(), 2);
+        assert_eq!(files[1].filename, "README");
+        assert_eq!(files[1].hunks[0].header(), "@@ -2,1 +2,1 @@");
+    }
+
+    #[test]
+    fn plain_diff_without_diff_line_is_parsed() {
+        let files = parse_patch("--- a.txt\t2024-01-01\n+++ a.txt\t2024-01-02\n@@ -1 +1 @@\n-a\n+b\n");
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].filename, "a.txt");
+        assert_eq!(files[0].hunks[0].lines.len(), 2);
+    }
+
+    /// Selects the changes at `lines` of hunk `hunk` in every file
+    fn select(files: &mut [FilePatch], hunk: usize, lines: &[usize]) {
+        for file in files {
+            for &i in lines {
+                file.hunks[hunk].lines[i].selected = true;
+            }
+        }
+    }
+
+    /// Checks that `git apply --cached --check` accepts `patch` in a
+    /// repository whose index holds `files`
+    fn assert_applies(name: &str, files: &[(&str, &str)], patch: &str) {
+        assert_applies_with(name, files, patch, &[]);
+    }
+
+    fn assert_applies_with(
+        name: &str,
+        files: &[(&str, &str)],
+        patch: &str,
+        args: &[&str],
+    ) {
+        let dir = env::temp_dir().join(format!(
+            "verco-patch-{}-{}",
+            name,
+            process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        let git = |args: &[&str]| {
+            let mut command = process::Command::new("git");
+            command.current_dir(&dir).args(args);
+            command
+        };
+        git(&["init", "-q"]).status().unwrap();
+        for (filename, contents) in files {
+            fs::write(dir.join(filename), contents).unwrap();
+            git(&["add", "--", filename]).status().unwrap();
+        }
+
+        let mut child = git(&["apply", "--cached", "--check"])
+            .args(args)
+            .arg("-")
+            .stdin(process::Stdio::piped())
+            .stderr(process::Stdio::piped())
+            .spawn()
+            .unwrap();
+        child
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(patch.as_bytes())
+            .unwrap();
+        let output = child.wait_with_output().unwrap();
+        let _ = fs::remove_dir_all(&dir);
+        assert!(
+            output.status.success(),
+            "git apply rejected the patch:\n{}\n{}",
+            patch,
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    const LINES: &str = "l1\nl2\nl3\nl4\nl5\nl6\nl7\nl8\nl9\nl10\n";
+
+    const MODIFIED: &str = "\
+diff --git a/a.txt b/a.txt
+--- a/a.txt
++++ b/a.txt
+@@ -1,3 +1,5 @@
+ l1
++a
++b
+ l2
+ l3
+@@ -5,3 +7,2 @@
+ l5
+-l6
+ l7
+@@ -9,2 +10,3 @@
+ l9
++c
+ l10
+";
+
+    #[test]
+    fn later_hunks_are_moved_by_the_selected_changes_before_them() {
+        let mut files = parse_patch(MODIFIED);
+        select(&mut files, 0, &[1]);
+        select(&mut files, 1, &[1]);
+        select(&mut files, 2, &[1]);
+        let patch = format_selected(&files);
+        assert_eq!(
+            patch,
+            "\
+diff --git a/a.txt b/a.txt
+--- a/a.txt
++++ b/a.txt
+@@ -1,3 +1,4 @@
+ l1
++a
+ l2
+ l3
+@@ -5,3 +6,2 @@
+ l5
+-l6
+ l7
+@@ -9,2 +9,3 @@
+ l9
++c
+ l10
+"
+        );
+        assert_applies("offset", &[("a.txt", LINES)], &patch);
+    }
+
+    #[test]
+    fn unselected_hunks_do_not_move_later_ones() {
+        let mut files = parse_patch(MODIFIED);
+        select(&mut files, 1, &[1]);
+        let patch = format_selected(&files);
+        assert!(patch.contains("\n@@ -5,3 +5,2 @@\n l5\n-l6\n l7\n"));
+        assert!(!patch.contains("+a"));
+        assert!(!patch.contains("+c"));
+        assert_applies("unselected", &[("a.txt", LINES)], &patch);
+    }
+
+    const EMPTY_RANGES: &str = "\
+diff --git a/a.txt b/a.txt
+--- a/a.txt
++++ b/a.txt
+@@ -2,0 +3,2 @@
++x
++y
+@@ -5,2 +6,1 @@
+-l5
+-l6
++z
+";
+
+    /// Without context git only applies these with `--unidiff-zero`
+    fn assert_applies_zero(name: &str, patch: &str) {
+        assert_applies_with(
+            name,
+            &[("a.txt", LINES)],
+            patch,
+            &["--unidiff-zero"],
+        );
+    }
+
+    #[test]
+    fn empty_ranges_start_at_the_line_before() {
+        let mut files = parse_patch(EMPTY_RANGES);
+        select(&mut files, 0, &[0]);
+        select(&mut files, 1, &[0, 1]);
+        let patch = format_selected(&files);
+        assert!(patch.contains("\n@@ -2,0 +3,1 @@\n+x\n@@ -5,2 +5,0 @@\n"));
+        assert_applies_zero("empty-new", &patch);
+
+        let mut files = parse_patch(EMPTY_RANGES);
+        select(&mut files, 1, &[0, 1]);
+        let patch = format_selected(&files);
+        assert!(patch.contains("\n@@ -5,2 +4,0 @@\n-l5\n-l6\n"));
+        assert_applies_zero("empty-new-only", &patch);
+
+        let mut files = parse_patch(EMPTY_RANGES);
+        select(&mut files, 0, &[1]);
+        let patch = format_selected(&files);
+        assert!(patch.ends_with("@@ -2,0 +3,1 @@\n+y\n"));
+        assert_applies_zero("empty-old", &patch);
+    }
+
+    const NO_NEWLINE: &str = "\
+diff --git a/a.txt b/a.txt
+--- a/a.txt
++++ b/a.txt
+@@ -1,2 +1,3 @@
+ l1
+-l2
+\\ No newline at end of file
++l2
++l3
+\\ No newline at end of file
+";
+
+    #[test]
+    fn no_newline_marker_follows_only_kept_lines() {
+        let mut files = parse_patch(NO_NEWLINE);
+        select(&mut files, 0, &[1, 3]);
+        let patch = format_selected(&files);
+        assert!(patch.ends_with(
+            "@@ -1,2 +1,2 @@\n l1\n-l2\n\\ No newline at end of file\n+l2\n"
+        ));
+        assert_applies("no-newline-removed", &[("a.txt", "l1\nl2")], &patch);
+
+        let mut files = parse_patch(NO_NEWLINE);
+        select(&mut files, 0, &[1, 3, 4]);
+        let patch = format_selected(&files);
+        assert!(patch.ends_with(concat!(
+            "@@ -1,2 +1,3 @@\n l1\n-l2\n\\ No newline at end of file\n",
+            "+l2\n+l3\n\\ No newline at end of file\n"
+        )));
+        assert_applies("no-newline-all", &[("a.txt", "l1\nl2")], &patch);
+    }
+
+    #[test]
+    fn unselected_last_line_gets_a_newline_before_added_lines() {
+        let mut files = parse_patch(NO_NEWLINE);
+        select(&mut files, 0, &[4]);
+        let patch = format_selected(&files);
+        assert!(patch.ends_with(concat!(
+            "@@ -1,2 +1,3 @@\n l1\n-l2\n\\ No newline at end of file\n",
+            "+l2\n+l3\n\\ No newline at end of file\n"
+        )));
+        assert_applies("no-newline-kept", &[("a.txt", "l1\nl2")], &patch);
+    }
+
+    const DELETED: &str = "\
+diff --git a/old.txt b/old.txt
+deleted file mode 100644
+--- a/old.txt
++++ /dev/null
+@@ -1,2 +0,0 @@
+-first
+-second
+";
+
+    #[test]
+    fn partial_deletion_only_modifies_the_file() {
+        let mut files = parse_patch(DELETED);
+        select(&mut files, 0, &[0]);
+        let patch = format_selected(&files);
+        assert_eq!(
+            patch,
+            "\
+diff --git a/old.txt b/old.txt
+--- a/old.txt
++++ b/old.txt
+@@ -1,2 +1,1 @@
+-first
+ second
+"
+        );
+        assert_applies(
+            "partial-delete",
+            &[("old.txt", "first\nsecond\n")],
+            &patch,
+        );
+
+        let mut files = parse_patch(DELETED);
+        select(&mut files, 0, &[0, 1]);
+        let patch = format_selected(&files);
+        assert_eq!(patch, DELETED);
+        assert_applies(
+            "full-delete",
+            &[("old.txt", "first\nsecond\n")],
+            &patch,
+        );
+    }
+
+    const NEW_FILE: &str = "\
+diff --git a/new.txt b/new.txt
+new file mode 100644
+--- /dev/null
++++ b/new.txt
+@@ -0,0 +1,3 @@
++a
++b
++c
+";
+
+    #[test]
+    fn partial_new_file_is_still_created() {
+        let mut files = parse_patch(NEW_FILE);
+        select(&mut files, 0, &[0, 2]);
+        let patch = format_selected(&files);
+        assert_eq!(
+            patch,
+            "\
+diff --git a/new.txt b/new.txt
+new file mode 100644
+--- /dev/null
++++ b/new.txt
+@@ -0,0 +1,2 @@
++a
++c
+"
+        );
+        assert_applies("partial-new", &[], &patch);
+    }
+}
@@ -1,13 +1,162 @@
-use std::{env, path::Path};
+use std::{
+    env,
+    path::{Path, PathBuf},
+};
 
-use crate::{
-    git_actions::GitActions, hg_actions::HgActions,
+use verco::{
+    fossil_actions::{FossilActions, CHECKOUT_FILES},
+    git2_actions::Git2Actions,
+    git_actions::GitActions,
+    hg_actions::HgActions,
+    jj_actions::JjActions,
+    svn_actions::SvnActions,
+    vcs_error::VcsError,
     version_control_actions::VersionControlActions,
 };
 
+use crate::config::{Config, GitBackend};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum VersionControlKind {
+    Jj,
+    Git,
+    Hg,
+    Fossil,
+    Svn,
+}
+
+impl VersionControlKind {
+    /// All kinds in the order they are probed when several of them have
+    /// markers in the same directory. jj comes first because its
+    /// repositories are usually colocated with a Git one.
+    pub const ALL: &'static [Self] =
+        &[Self::Jj, Self::Git, Self::Hg, Self::Fossil, Self::Svn];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Jj => "jj",
+            Self::Git => "git",
+            Self::Hg => "hg",
+            Self::Fossil => "fossil",
+            Self::Svn => "svn",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|k| k.name() == name)
+    }
+
+    /// Files or directories whose presence marks a repository root
+    pub fn markers(self) -> &'static [&'static str] {
+        match self {
+            Self::Jj => &[".jj"],
+            Self::Git => &[".git"],
+            Self::Hg => &[".hg"],
+            Self::Fossil => CHECKOUT_FILES,
+            Self::Svn => &[".svn"],
+        }
+    }
+
+    fn open(
+        self,
+        dir: &str,
+    ) -> Result<Box<dyn VersionControlActions>, VcsError> {
+        let current_dir = String::from(dir);
+        let mut version_control: Box<dyn VersionControlActions> = match self {
+            Self::Jj => Box::new(JjActions { current_dir }),
+            Self::Git => {
+                let mut git_actions = GitActions { current_dir };
+                git_actions.set_root()?;
+                let config = Config::load(git_actions.get_root());
+                if config.git_backend == GitBackend::Libgit2 {
+                    // the git executable remains the fallback if libgit2
+                    // can't open the repository
+                    if let Ok(git2_actions) =
+                        Git2Actions::open(git_actions.clone())
+                    {
+                        return Ok(Box::new(git2_actions));
+                    }
+                }
+                return Ok(Box::new(git_actions));
+            }
+            Self::Hg => Box::new(HgActions { current_dir }),
+            Self::Fossil => Box::new(FossilActions { current_dir }),
+            Self::Svn => Box::new(SvnActions { current_dir }),
+        };
+        version_control.set_root()?;
+        Ok(version_control)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct Args {
+    dir: Option<String>,
+    vcs: Option<String>,
+}
+
+/// Parses the command line `args`, without the program name
+fn parse_args<I>(mut it: I) -> Result<Args, String>
+where
+    I: Iterator<Item = String>,
+{
+    let mut args = Args {
+        dir: None,
+        vcs: None,
+    };
+
+    while let Some(arg) = it.next() {
+        if arg == "--vcs" {
+            match it.next() {
+                Some(vcs) => args.vcs = Some(vcs),
+                None => return Err(String::from("--vcs expects a value")),
+            }
+        } else if let Some(vcs) = arg.strip_prefix("--vcs=") {
+            args.vcs = Some(String::from(vcs));
+        } else if args.dir.is_none() {
+            args.dir = Some(arg);
+        } else {
+            return Err(format!("unexpected argument {:?}", arg));
+        }
+    }
+
+    Ok(args)
+}
+
+fn kind_names() -> String {
+    let names: Vec<_> =
+        VersionControlKind::ALL.iter().map(|k| k.name()).collect();
+    names.join("|")
+}
+
+/// Walks up from `start` listing every directory with a repository marker,
+/// nearest first and in the order of `VersionControlKind::ALL` within one
+fn find_markers(
+    start: &Path,
+) -> Vec<(PathBuf, VersionControlKind, &'static str)> {
+    let mut found = Vec::new();
+    for dir in start.ancestors() {
+        for &kind in VersionControlKind::ALL {
+            if let Some(marker) =
+                kind.markers().iter().find(|m| dir.join(m).exists())
+            {
+                found.push((dir.to_path_buf(), kind, *marker));
+            }
+        }
+    }
+    found
+}
+
 pub fn get_current_version_control() -> Option<Box<dyn VersionControlActions>> {
-    let mut args = env::args();
-    if let Some(dir) = args.nth(1) {
+    let args = match parse_args(env::args().skip(1)) {
+        Ok(args) => args,
+        Err(error) => {
+            eprintln!("{}", error);
+            eprintln!("usage: verco [--vcs {}] [directory]", kind_names());
+            return None;
+        }
+    };
+
+    if let Some(dir) = args.dir {
         let dir = Path::new(&dir);
         if dir.canonicalize().is_err() {
             eprintln!("{:?} is not a valid directory", dir);
@@ -27,22 +176,137 @@ pub fn get_current_version_control() -> Option<Box<dyn VersionControlActions>> {
         }
     };
 
-    // first try Git because it's the most common and also responds the fastest
-    let mut git_actions = Box::from(GitActions {
-        current_dir: current_dir.into(),
-    });
-    if git_actions.set_root().is_ok() {
-        return Some(git_actions);
+    if let Some(vcs) = args.vcs {
+        let kind = match VersionControlKind::from_name(&vcs) {
+            Some(kind) => kind,
+            None => {
+                eprintln!("unknown vcs {:?}, expected {}", vcs, kind_names());
+                return None;
+            }
+        };
+
+        return match kind.open(current_dir) {
+            Ok(version_control) => Some(version_control),
+            Err(error) => {
+                eprintln!(
+                    "no {} repository found at {}: {}",
+                    kind.name(),
+                    current_dir,
+                    error
+                );
+                None
+            }
+        };
     }
 
-    // otherwise try Mercurial
-    let mut hg_actions = Box::from(HgActions {
-        current_dir: current_dir.into(),
-    });
-    if hg_actions.set_root().is_ok() {
-        return Some(hg_actions);
+    // pick the backend of the nearest repository marker that opens
+    let mut failures = Vec::new();
+    for (dir, kind, marker) in find_markers(Path::new(current_dir)) {
+        let dir = match dir.to_str() {
+            Some(dir) => dir,
+            None => continue,
+        };
+
+        match kind.open(dir) {
+            Ok(version_control) => return Some(version_control),
+            Err(error) => failures.push((
+                kind,
+                format!("found {} in {}: {}", marker, dir, error),
+            )),
+        }
     }
 
-    eprintln!("no repository found");
+    eprintln!("no repository found from {}", current_dir);
+    for &kind in VersionControlKind::ALL {
+        let mut kind_failures =
+            failures.iter().filter(|(k, _)| *k == kind).peekable();
+        if kind_failures.peek().is_none() {
+            eprintln!(
+                "  {}: no {} in any parent directory",
+                kind.name(),
+                kind.markers().join(" or ")
+            );
+        }
+        for (_, failure) in kind_failures {
+            eprintln!("  {}: {}", kind.name(), failure);
+        }
+    }
     None
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, process};
+
+    use super::*;
+
+    fn args(line: &[&str]) -> Result<Args, String> {
+        parse_args(line.iter().map(|a| String::from(*a)))
+    }
+
+    #[test]
+    fn vcs_is_parsed_in_both_forms() {
+        let expected = Args {
+            dir: Some(String::from("repo")),
+            vcs: Some(String::from("hg")),
+        };
+        assert_eq!(args(&["--vcs", "hg", "repo"]).as_ref(), Ok(&expected));
+        assert_eq!(args(&["repo", "--vcs=hg"]), Ok(expected));
+        let expected = Args {
+            dir: None,
+            vcs: None,
+        };
+        assert_eq!(args(&[]), Ok(expected));
+    }
+
+    #[test]
+    fn bad_arguments_are_reported() {
+        assert!(args(&["--vcs"]).is_err());
+        assert!(args(&["a", "b"]).is_err());
+    }
+
+    #[test]
+    fn kinds_are_found_by_name() {
+        for &kind in VersionControlKind::ALL {
+            assert_eq!(VersionControlKind::from_name(kind.name()), Some(kind));
+        }
+        assert!(VersionControlKind::from_name("cvs").is_none());
+    }
+
+    #[test]
+    fn markers_are_found_nearest_first() {
+        let root =
+            env::temp_dir().join(format!("verco-markers-{}", process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let nested = root.join("outer/inner/src");
+        fs::create_dir_all(&nested).unwrap();
+        fs::create_dir_all(root.join("outer/.git")).unwrap();
+        fs::create_dir_all(root.join("outer/.jj")).unwrap();
+        fs::create_dir_all(root.join("outer/inner/.hg")).unwrap();
+        fs::write(root.join("outer/inner/_FOSSIL_"), "").unwrap();
+
+        let found: Vec<_> = find_markers(&nested)
+            .into_iter()
+            .filter(|(dir, _, _)| dir.starts_with(&root))
+            .map(|(dir, kind, marker)| {
+                (dir.strip_prefix(&root).unwrap().to_path_buf(), kind, marker)
+            })
+            .collect();
+        let _ = fs::remove_dir_all(&root);
+
+        let expected = [
+            ("outer/inner", VersionControlKind::Hg, ".hg"),
+            ("outer/inner", VersionControlKind::Fossil, "_FOSSIL_"),
+            ("outer", VersionControlKind::Jj, ".jj"),
+            ("outer", VersionControlKind::Git, ".git"),
+        ];
+        assert_eq!(found.len(), expected.len());
+        for ((dir, kind, marker), (e_dir, e_kind, e_marker)) in
+            found.iter().zip(expected)
+        {
+            assert_eq!(dir, Path::new(e_dir));
+            assert_eq!(*kind, e_kind);
+            assert_eq!(*marker, e_marker);
+        }
+    }
+}
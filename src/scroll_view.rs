@@ -2,25 +2,64 @@ use crossterm::{
     cursor,
     event::{KeyCode, KeyEvent, KeyModifiers},
     handle_command,
-    style::{ResetColor, SetBackgroundColor},
+    style::{Print, ResetColor, SetBackgroundColor, SetForegroundColor},
     terminal::{Clear, ClearType},
     Result,
 };
 
 use std::io::Write;
 
+use verco::{
+    blame::BlameLine, log_entry::LogEntry,
+    version_control_actions::format_truncated,
+};
+
 use crate::{
-    action::ActionKind,
+    action_kind::ActionKind,
     input,
     tui_util::{
         draw_filter_bar, fuzzy_matches, move_cursor, AvailableSize,
-        TerminalSize, SELECTED_BG_COLOR,
+        TerminalSize, LOG_COLORS, SELECTED_BG_COLOR,
     },
 };
 
+const LOG_AUTHOR_WIDTH: usize = 10;
+const BLAME_TAB: &str = "    ";
+
+/// Prints as much of `text` as fits in `remaining_width` chars
+fn print_fitting<W>(
+    write: &mut W,
+    text: &str,
+    remaining_width: &mut usize,
+) -> Result<()>
+where
+    W: Write,
+{
+    let slice_end = text
+        .char_indices()
+        .nth(*remaining_width)
+        .map(|(i, _)| i)
+        .unwrap_or(text.len());
+    let text = &text[..slice_end];
+    *remaining_width -= text.chars().count();
+    handle_command!(write, Print(text))
+}
+
+/// A log line is either a revision or one of the graph only lines below it
+#[derive(Clone, Copy)]
+struct LogRow {
+    entry_index: usize,
+    graph_line_index: Option<usize>,
+}
+
 pub struct ScrollView {
     action_kind: ActionKind,
     content: String,
+    log_entries: Vec<LogEntry>,
+    log_rows: Vec<LogRow>,
+    /// Ids of the log entries marked to act on several of them at once
+    marked_ids: Vec<String>,
+    blame_lines: Vec<BlameLine>,
     scroll: usize,
     cursor: Option<usize>,
     is_filtering: bool,
@@ -32,6 +71,10 @@ impl Default for ScrollView {
         Self {
             action_kind: ActionKind::Quit,
             content: String::with_capacity(1024 * 4),
+            log_entries: Vec::new(),
+            log_rows: Vec::new(),
+            marked_ids: Vec::new(),
+            blame_lines: Vec::new(),
             scroll: 0,
             cursor: None,
             is_filtering: false,
@@ -41,8 +84,50 @@ impl Default for ScrollView {
 }
 
 impl ScrollView {
-    pub fn cursor(&self) -> Option<usize> {
-        self.cursor
+    pub fn action_kind(&self) -> ActionKind {
+        self.action_kind
+    }
+
+    /// Returns the line under the cursor taking the filter into account
+    pub fn selected_line(&self) -> Option<&str> {
+        let cursor = self.cursor?;
+        self.filtered_lines().nth(cursor).map(|(_, line)| line)
+    }
+
+    /// Returns the log entry under the cursor when showing a log. Graph only
+    /// lines select the revision above them.
+    pub fn selected_log_entry(&self) -> Option<&LogEntry> {
+        let cursor = self.cursor?;
+        let (index, _) = self.filtered_lines().nth(cursor)?;
+        let row = self.log_rows.get(index)?;
+        self.log_entries.get(row.entry_index)
+    }
+
+    /// Returns the blamed line under the cursor when showing a blame
+    pub fn selected_blame_line(&self) -> Option<&BlameLine> {
+        let cursor = self.cursor?;
+        let (index, _) = self.filtered_lines().nth(cursor)?;
+        self.blame_lines.get(index)
+    }
+
+    /// Returns the marked log entries in log order
+    pub fn marked_log_entries(&self) -> impl Iterator<Item = &LogEntry> {
+        self.log_entries
+            .iter()
+            .filter(move |e| self.marked_ids.contains(&e.id))
+    }
+
+    fn toggle_mark(&mut self) {
+        let id = match self.selected_log_entry() {
+            Some(entry) => entry.id.clone(),
+            None => return,
+        };
+        match self.marked_ids.iter().position(|i| *i == id) {
+            Some(index) => {
+                self.marked_ids.remove(index);
+            }
+            None => self.marked_ids.push(id),
+        }
     }
 
     pub fn set_content(
@@ -53,7 +138,91 @@ impl ScrollView {
     ) {
         self.content.clear();
         self.content.push_str(content);
+        self.log_entries.clear();
+        self.log_rows.clear();
+        self.marked_ids.clear();
+        self.blame_lines.clear();
+
+        self.on_content_changed(action_kind, terminal_size);
+    }
 
+    pub fn set_blame_content(
+        &mut self,
+        lines: Vec<BlameLine>,
+        action_kind: ActionKind,
+        terminal_size: TerminalSize,
+    ) {
+        self.content.clear();
+        self.log_entries.clear();
+        self.log_rows.clear();
+        self.marked_ids.clear();
+
+        // the text content is kept around for filtering only
+        for line in &lines {
+            for field in &[&line.short_revision, &line.author, &line.text] {
+                self.content.push_str(field);
+                self.content.push(' ');
+            }
+            self.content.push('\n');
+        }
+        self.blame_lines = lines;
+
+        self.on_content_changed(action_kind, terminal_size);
+    }
+
+    pub fn set_log_content(
+        &mut self,
+        entries: Vec<LogEntry>,
+        action_kind: ActionKind,
+        terminal_size: TerminalSize,
+    ) {
+        self.content.clear();
+        self.log_rows.clear();
+        self.blame_lines.clear();
+
+        // the text content is kept around for filtering only
+        for (entry_index, entry) in entries.iter().enumerate() {
+            self.log_rows.push(LogRow {
+                entry_index,
+                graph_line_index: None,
+            });
+            self.content.push_str(&entry.graph);
+            for field in &[
+                &entry.short_id,
+                &entry.date,
+                &entry.author,
+                &entry.refs,
+                &entry.branch,
+                &entry.subject,
+            ] {
+                self.content.push(' ');
+                self.content.push_str(field);
+            }
+            self.content.push('\n');
+
+            for (graph_line_index, graph_line) in
+                entry.graph_lines.iter().enumerate()
+            {
+                self.log_rows.push(LogRow {
+                    entry_index,
+                    graph_line_index: Some(graph_line_index),
+                });
+                self.content.push_str(graph_line);
+                self.content.push('\n');
+            }
+        }
+        self.marked_ids
+            .retain(|id| entries.iter().any(|e| e.id == *id));
+        self.log_entries = entries;
+
+        self.on_content_changed(action_kind, terminal_size);
+    }
+
+    fn on_content_changed(
+        &mut self,
+        action_kind: ActionKind,
+        terminal_size: TerminalSize,
+    ) {
         self.is_filtering = false;
         self.filter.clear();
 
@@ -79,13 +248,12 @@ impl ScrollView {
     where
         W: Write,
     {
-        let line_formatter = self.action_kind.line_formatter();
         let available_size = AvailableSize::from_temrinal_size(terminal_size);
 
         handle_command!(write, cursor::MoveTo(0, 1))?;
         handle_command!(write, ResetColor)?;
 
-        for (i, line) in self
+        for (i, (index, line)) in self
             .filtered_lines()
             .enumerate()
             .skip(self.scroll)
@@ -99,13 +267,13 @@ impl ScrollView {
                     )?;
                 }
 
-                line_formatter(write, line, available_size)?;
+                self.draw_line(write, index, line, available_size)?;
                 handle_command!(write, Clear(ClearType::UntilNewLine))?;
                 handle_command!(write, cursor::MoveToNextLine(1))?;
                 handle_command!(write, ResetColor)?;
             } else {
                 handle_command!(write, Clear(ClearType::CurrentLine))?;
-                line_formatter(write, line, available_size)?;
+                self.draw_line(write, index, line, available_size)?;
                 handle_command!(write, cursor::MoveToNextLine(1))?;
             }
         }
@@ -219,6 +387,13 @@ impl ScrollView {
                 }
                 self.draw_content(write, terminal_size)?;
             }
+            KeyEvent {
+                code: KeyCode::Char(' '),
+                ..
+            } if !self.is_filtering && !self.log_entries.is_empty() => {
+                self.toggle_mark();
+                self.draw_content(write, terminal_size)?;
+            }
             KeyEvent {
                 code: KeyCode::Char('/'),
                 ..
@@ -240,7 +415,7 @@ impl ScrollView {
                 code: KeyCode::Backspace,
                 ..
             } => {
-                if self.filter.len() > 0 {
+                if !self.filter.is_empty() {
                     self.filter.remove(self.filter.len() - 1);
                 }
                 self.on_filter_changed(write, terminal_size)?;
@@ -259,7 +434,7 @@ impl ScrollView {
                 code: KeyCode::Char('c'),
                 modifiers: KeyModifiers::CONTROL,
             } => {
-                if self.is_filtering || self.filter.len() > 0 {
+                if self.is_filtering || !self.filter.is_empty() {
                     self.is_filtering = false;
                     self.filter.clear();
                     self.on_filter_changed(write, terminal_size)?;
@@ -284,10 +459,99 @@ impl ScrollView {
         Ok(true)
     }
 
-    fn filtered_lines(&self) -> impl Iterator<Item = &str> {
+    fn draw_line<W>(
+        &self,
+        write: &mut W,
+        index: usize,
+        line: &str,
+        available_size: AvailableSize,
+    ) -> Result<()>
+    where
+        W: Write,
+    {
+        if let Some(line) = self.blame_lines.get(index) {
+            return self.draw_blame_line(write, line, available_size);
+        }
+        let row = match self.log_rows.get(index) {
+            Some(row) => *row,
+            None => return handle_command!(write, Print(line)),
+        };
+
+        let entry = &self.log_entries[row.entry_index];
+        let mut remaining_width = available_size.width - 1;
+        handle_command!(write, SetForegroundColor(LOG_COLORS[0]))?;
+        if !self.marked_ids.is_empty() {
+            let is_marked = row.graph_line_index.is_none()
+                && self.marked_ids.contains(&entry.id);
+            let mark = if is_marked { "+ " } else { "  " };
+            print_fitting(write, mark, &mut remaining_width)?;
+        }
+        if let Some(graph_line_index) = row.graph_line_index {
+            let graph_line = &entry.graph_lines[graph_line_index];
+            return print_fitting(write, graph_line, &mut remaining_width);
+        }
+
+        let author = format_truncated(&entry.author, LOG_AUTHOR_WIDTH);
+        let mut refs = entry.refs.clone();
+        if !entry.branch.is_empty() {
+            if !refs.is_empty() {
+                refs.push(' ');
+            }
+            refs.push_str(&entry.branch);
+        }
+
+        let columns = [
+            &entry.graph[..],
+            &entry.short_id[..],
+            &entry.date[..],
+            &author[..],
+            &refs[..],
+            &entry.subject[..],
+        ];
+        for (column, color) in columns.iter().zip(LOG_COLORS.iter()) {
+            handle_command!(write, SetForegroundColor(*color))?;
+            print_fitting(write, column, &mut remaining_width)?;
+            print_fitting(write, " ", &mut remaining_width)?;
+        }
+        Ok(())
+    }
+
+    fn draw_blame_line<W>(
+        &self,
+        write: &mut W,
+        line: &BlameLine,
+        available_size: AvailableSize,
+    ) -> Result<()>
+    where
+        W: Write,
+    {
+        let line_number_width = self.blame_lines.len().to_string().len();
+        let line_number =
+            format!("{:>1$}", line.line_number, line_number_width);
+        let author = format_truncated(&line.author, LOG_AUTHOR_WIDTH);
+        let text = line.text.replace('\t', BLAME_TAB);
+
+        let columns = [
+            &line_number[..],
+            &line.short_revision[..],
+            &line.date[..],
+            &author[..],
+            &text[..],
+        ];
+        let mut remaining_width = available_size.width - 1;
+        for (column, color) in columns.iter().zip(LOG_COLORS.iter()) {
+            handle_command!(write, SetForegroundColor(*color))?;
+            print_fitting(write, column, &mut remaining_width)?;
+            print_fitting(write, " ", &mut remaining_width)?;
+        }
+        Ok(())
+    }
+
+    fn filtered_lines(&self) -> impl Iterator<Item = (usize, &str)> {
         self.content
             .lines()
-            .filter(move |line| fuzzy_matches(line, &self.filter[..]))
+            .enumerate()
+            .filter(move |(_, line)| fuzzy_matches(line, &self.filter[..]))
     }
 
     fn content_height(&self, available_size: AvailableSize) -> usize {
@@ -296,7 +560,7 @@ impl ScrollView {
         } else {
             let width = available_size.width;
             self.filtered_lines()
-                .map(|l| (l.len() + width - 1) / width)
+                .map(|(_, l)| l.len().div_ceil(width))
                 .sum()
         }
     }
@@ -0,0 +1,1097 @@
+use std::{fs, path::Path};
+
+use crate::{
+    action::{
+        chain, lazy, map_output, parallel, ready, serial, task_vec,
+        ActionResult, ActionTask,
+    },
+    blame::BlameLine,
+    history_edit::PlanEntry,
+    log_entry::LogEntry,
+    patch::FilePatch,
+    select::{Entry, State},
+    vcs_error::{ErrorMessages, VcsError},
+    version_control_actions::{
+        task, unsupported, unsupported_error, Capability, Support,
+        VersionControlActions,
+    },
+};
+
+fn item_to_state(item: &str, props: &str) -> State {
+    match item {
+        "unversioned" => State::Untracked,
+        "modified" | "replaced" | "obstructed" => State::Modified,
+        "added" => State::Added,
+        "deleted" => State::Deleted,
+        "conflicted" => State::Unmerged,
+        "missing" | "incomplete" => State::Missing,
+        "ignored" => State::Ignored,
+        "external" => State::Clean,
+        _ => match props {
+            "modified" => State::Modified,
+            "conflicted" => State::Unmerged,
+            _ => State::Unmodified,
+        },
+    }
+}
+
+fn str_to_state(s: &str) -> State {
+    match s {
+        "M" => State::Modified,
+        "A" => State::Added,
+        "D" => State::Deleted,
+        "C" => State::Unmerged,
+        _ => State::Unmodified,
+    }
+}
+
+fn xml_unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Returns the value of attribute `name` inside the first tag of `xml`
+fn xml_attribute<'a>(xml: &'a str, name: &str) -> Option<&'a str> {
+    let tag = &xml[..xml.find('>')?];
+    let pattern = format!(" {}=\"", name);
+    let start = tag.find(&pattern)? + pattern.len();
+    let len = tag[start..].find('"')?;
+    Some(&tag[start..(start + len)])
+}
+
+/// Returns the text inside the first `<name>...</name>` element of `xml`
+fn xml_element<'a>(xml: &'a str, name: &str) -> Option<&'a str> {
+    let open = format!("<{}", name);
+    let close = format!("</{}>", name);
+    let start = xml.find(&open)?;
+    let start = start + xml[start..].find('>')? + 1;
+    let len = xml[start..].find(&close)?;
+    Some(&xml[start..(start + len)])
+}
+
+fn parse_status_xml(output: &str) -> Vec<Entry> {
+    output
+        .split("<entry")
+        .skip(1)
+        .filter_map(|entry| {
+            let path = xml_attribute(entry, "path")?;
+            let wc_status = &entry[entry.find("<wc-status")?..];
+            let item = xml_attribute(wc_status, "item").unwrap_or("");
+            let props = xml_attribute(wc_status, "props").unwrap_or("");
+            Some(Entry {
+                filename: xml_unescape(path),
+                selected: false,
+                state: item_to_state(item, props),
+            })
+        })
+        .filter(|e| !matches!(e.state, State::Unmodified))
+        .collect()
+}
+
+/// Deletes unversioned files since svn has no way to remove a single one
+fn remove_unversioned(root: &str, filenames: &[String]) -> ActionResult {
+    let mut output = String::new();
+    for filename in filenames {
+        let path = Path::new(root).join(filename);
+        let result = if path.is_dir() {
+            fs::remove_dir_all(&path)
+        } else {
+            fs::remove_file(&path)
+        };
+        match result {
+            Ok(()) => {
+                output.push_str(&format!("removed {}\n", filename));
+            }
+            Err(error) => return ActionResult::from_err(error.to_string()),
+        }
+    }
+    ActionResult::from_ok(output)
+}
+
+/// Renders `svn log --xml` into log records
+fn format_log_xml(output: String) -> String {
+    let mut log = String::new();
+    for entry in output.split("<logentry").skip(1) {
+        let revision = xml_attribute(entry, "revision").unwrap_or("");
+        let date = xml_element(entry, "date").unwrap_or("");
+        let message = xml_element(entry, "msg").unwrap_or("");
+        let message = xml_unescape(message);
+
+        let parents = match revision.parse::<usize>() {
+            Ok(revision) if revision > 1 => vec![(revision - 1).to_string()],
+            _ => Vec::new(),
+        };
+
+        let entry = LogEntry {
+            graph: String::from("o"),
+            id: String::from(revision),
+            short_id: String::from(revision),
+            parents,
+            author: xml_unescape(xml_element(entry, "author").unwrap_or("")),
+            date: String::from(date.get(..10).unwrap_or(date)),
+            subject: String::from(message.lines().next().unwrap_or("").trim()),
+            ..Default::default()
+        };
+        log.push_str(&entry.format_record());
+        log.push('\n');
+    }
+    log
+}
+
+/// Renders `svn blame -v` into blame records
+///
+/// Each line starts with the revision, the author and a date like
+/// `2024-01-02 03:04:05 +0000 (Tue, 02 Jan 2024)` before the text.
+fn format_blame_verbose(output: String) -> String {
+    let mut blame = String::new();
+    for (i, line) in output.lines().enumerate() {
+        let mut rest = line.trim_start();
+        let mut next_field = || {
+            let (field, after) = rest.split_once(' ').unwrap_or((rest, ""));
+            rest = after.trim_start();
+            field
+        };
+        let revision = next_field();
+        let author = next_field();
+        let date = rest.get(..10).unwrap_or("");
+        let text = rest.split_once(") ").map(|(_, t)| t).unwrap_or("");
+
+        let line = BlameLine {
+            revision: String::from(revision),
+            short_revision: String::from(revision),
+            author: String::from(author),
+            date: String::from(date),
+            line_number: i + 1,
+            text: String::from(text),
+        };
+        blame.push_str(&line.format_record());
+        blame.push('\n');
+    }
+    blame
+}
+
+fn is_revision(target: &str) -> bool {
+    target.chars().all(|c| c.is_ascii_digit())
+        || matches!(target, "HEAD" | "BASE" | "COMMITTED" | "PREV")
+        || target.starts_with('{')
+}
+
+/// The repository url of the tag `name`
+fn tag_url(name: &str) -> String {
+    format!("^/tags/{}", name)
+}
+
+/// Turns `svn list --verbose` lines into a tag name, its revision, its date
+/// and its author
+fn format_tag_list(output: String) -> String {
+    let mut tags = String::new();
+    for line in output.lines() {
+        let fields: Vec<_> = line.split_whitespace().collect();
+        if fields.len() < 6 {
+            continue;
+        }
+        let name = fields[fields.len() - 1].trim_end_matches('/');
+        if name == "." {
+            continue;
+        }
+        tags.push_str(&format!(
+            "{} r{} {} {}\n",
+            name,
+            fields[0],
+            fields[fields.len() - 4..fields.len() - 1].join(" "),
+            fields[1]
+        ));
+    }
+    tags
+}
+
+/// Maps a branch name as listed by `list_branches` to its repository url
+fn branch_url(name: &str) -> String {
+    if name.starts_with("^/") || name.contains("://") {
+        String::from(name)
+    } else if name == "trunk" {
+        String::from("^/trunk")
+    } else {
+        format!("^/branches/{}", name)
+    }
+}
+
+const ERROR_MESSAGES: ErrorMessages = ErrorMessages {
+    not_a_repository: &["is not a working copy"],
+    merge_conflict: &["remains in conflict", "Summary of conflicts"],
+    auth_failure: &["E170001", "E215004", "Authentication failed"],
+};
+
+pub struct SvnActions {
+    pub current_dir: String,
+}
+
+impl SvnActions {
+    fn relative_url(&self) -> Result<String, VcsError> {
+        let output = self.run_command(self.command().args([
+            "info",
+            "--show-item",
+            "relative-url",
+        ]))?;
+        Ok(String::from(output.trim()))
+    }
+}
+
+impl VersionControlActions for SvnActions {
+    fn executable_name(&self) -> &'static str {
+        "svn"
+    }
+
+    fn current_dir(&self) -> &str {
+        &self.current_dir[..]
+    }
+
+    fn classify_error(&self, error: VcsError) -> VcsError {
+        error.classify(&ERROR_MESSAGES)
+    }
+
+    fn support(&self, capability: Capability) -> Support {
+        match capability {
+            Capability::Fetch => {
+                Support::Unsupported("there is no local history to fetch into")
+            }
+            Capability::Pull => Support::Differs("runs svn update"),
+            Capability::Push => {
+                Support::Unsupported("commits are sent directly to the server")
+            }
+            Capability::CreateTag | Capability::CreateBranch => {
+                Support::Differs("commits a copy in the repository")
+            }
+            Capability::CloseBranch => {
+                Support::Differs("commits the deletion of the branch")
+            }
+            Capability::DeleteTag => {
+                Support::Differs("commits the deletion of the tag")
+            }
+            Capability::CommitHunks => {
+                Support::Unsupported("svn can only commit whole files")
+            }
+            Capability::Stash => {
+                Support::Unsupported("svn shelving is still experimental")
+            }
+            Capability::Amend
+            | Capability::Rebase
+            | Capability::EditHistory => Support::Unsupported(
+                "commits can't be changed once sent to the server",
+            ),
+            Capability::CherryPick => Support::Unsupported(
+                "svn merge -c needs the branch the revisions come from",
+            ),
+            Capability::Backout => Support::Differs(
+                "reverse merges the revision and commits it to the server",
+            ),
+            Capability::Bisect => {
+                Support::Unsupported("svn has no bisect command")
+            }
+            Capability::Remotes => Support::Unsupported(
+                "the working copy talks to the server it was checked out from",
+            ),
+            Capability::Submodules => Support::Unsupported(
+                "externals are checked out and updated with svn update",
+            ),
+            Capability::Worktrees => Support::Unsupported(
+                "checkouts of the same repository are independent",
+            ),
+            Capability::Reflog => Support::Unsupported(
+                "svn keeps no history of working copy updates",
+            ),
+            _ => Support::Supported,
+        }
+    }
+
+    fn set_root(&mut self) -> Result<(), VcsError> {
+        let mut command = self.command();
+        let dir =
+            self.run_command(command.args(["info", "--show-item", "wc-root"]))?;
+
+        let dir = dir
+            .lines()
+            .next()
+            .expect("root directory is an empty string");
+        self.current_dir = dir.to_owned();
+
+        Ok(())
+    }
+
+    fn get_root(&self) -> &str {
+        &self.current_dir[..]
+    }
+
+    fn switch_root(&mut self, _dir: &str) -> Result<(), VcsError> {
+        Err(unsupported_error(self, "switching worktrees"))
+    }
+
+    fn get_current_changed_files(&self) -> Result<Vec<Entry>, VcsError> {
+        let output =
+            self.run_command(self.command().args(["status", "--xml"]))?;
+        Ok(parse_status_xml(&output))
+    }
+
+    fn get_tracked_files(&self) -> Result<Vec<Entry>, VcsError> {
+        let output = self.run_command(self.command().args(["list", "-R"]))?;
+        let files = output
+            .lines()
+            .filter(|f| !f.is_empty() && !f.ends_with('/'))
+            .map(|f| Entry {
+                filename: String::from(f),
+                selected: false,
+                state: State::Clean,
+            })
+            .collect();
+        Ok(files)
+    }
+
+    fn get_revision_changed_files(
+        &self,
+        target: &str,
+    ) -> Result<Vec<Entry>, VcsError> {
+        let output = self.run_command(
+            self.command()
+                .arg("diff")
+                .arg("--summarize")
+                .arg("-c")
+                .arg(target),
+        )?;
+
+        let files = output
+            .lines()
+            .filter(|e| e.len() > 8)
+            .map(|e| {
+                let (state, filename) = e.split_at(8);
+                Entry {
+                    filename: String::from(filename.trim()),
+                    selected: false,
+                    state: str_to_state(&state[..1]),
+                }
+            })
+            .collect();
+        Ok(files)
+    }
+
+    fn version(&self) -> Result<String, VcsError> {
+        self.run_command(self.command().args(["--version", "--quiet"]))
+            .map(|v| format!("svn version {}", v))
+    }
+
+    fn status(&self) -> Box<dyn ActionTask> {
+        let mut tasks = task_vec();
+        tasks.push(task(self, |command| {
+            command.arg("info");
+        }));
+        tasks.push(task(self, |command| {
+            command.arg("status");
+        }));
+        parallel(tasks)
+    }
+
+    fn reflog(&self) -> Box<dyn ActionTask> {
+        unsupported(self, "listing previous working copy revisions")
+    }
+
+    fn current_export(&self) -> Box<dyn ActionTask> {
+        let mut tasks = task_vec();
+        tasks.push(task(self, |command| {
+            command.args(["log", "--verbose", "-r", "COMMITTED"]);
+        }));
+        tasks.push(task(self, |command| {
+            command.args(["diff", "-r", "PREV:COMMITTED"]);
+        }));
+        serial(tasks)
+    }
+
+    fn log(&self, count: usize) -> Box<dyn ActionTask> {
+        let log = task(self, |command| {
+            let count_str = format!("{}", count);
+            command
+                .arg("log")
+                .arg("--xml")
+                .arg("-l")
+                .arg(&count_str)
+                .arg("-r")
+                .arg("HEAD:1");
+        });
+        map_output(log, format_log_xml)
+    }
+
+    fn blame(
+        &self,
+        filename: &str,
+        revision: Option<&str>,
+    ) -> Box<dyn ActionTask> {
+        let blame = task(self, |command| {
+            command.args(["blame", "-v"]);
+            if let Some(revision) = revision {
+                command.arg("-r").arg(revision);
+            }
+            command.arg("--").arg(filename);
+        });
+        map_output(blame, format_blame_verbose)
+    }
+
+    fn get_parent_revision(&self, revision: &str) -> Result<String, VcsError> {
+        match revision.trim_start_matches('r').parse::<usize>() {
+            Ok(revision) if revision > 1 => Ok((revision - 1).to_string()),
+            _ => Err(VcsError::CommandFailed {
+                code: None,
+                stderr: format!("revision {} has no parent", revision),
+            }),
+        }
+    }
+
+    fn current_diff_all(&self) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.arg("diff");
+        })
+    }
+
+    fn current_diff_selected(&self, entries: &[Entry]) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.arg("diff").arg("--");
+            for e in entries.iter().filter(|e| e.selected) {
+                command.arg(&e.filename);
+            }
+        })
+    }
+
+    fn revision_changes(&self, target: &str) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.arg("diff").arg("--summarize").arg("-c").arg(target);
+        })
+    }
+
+    fn revision_diff_all(&self, target: &str) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.arg("diff").arg("-c").arg(target);
+        })
+    }
+
+    fn revision_diff_selected(
+        &self,
+        target: &str,
+        entries: &[Entry],
+    ) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.arg("diff").arg("-c").arg(target).arg("--");
+            for e in entries.iter().filter(|e| e.selected) {
+                command.arg(&e.filename);
+            }
+        })
+    }
+
+    fn commit_all(&self, message: &str) -> Box<dyn ActionTask> {
+        let mut entries = self.get_current_changed_files().unwrap_or_default();
+        for e in &mut entries {
+            e.selected = true;
+        }
+        self.commit_selected(message, &entries)
+    }
+
+    fn commit_selected(
+        &self,
+        message: &str,
+        entries: &[Entry],
+    ) -> Box<dyn ActionTask> {
+        let mut tasks = task_vec();
+        let mut files_to_commit = Vec::new();
+        for e in entries.iter().filter(|e| e.selected) {
+            match e.state {
+                State::Missing => tasks.push(task(self, |command| {
+                    command.arg("delete").arg("--").arg(&e.filename);
+                })),
+                State::Untracked => tasks.push(task(self, |command| {
+                    command.arg("add").arg("--").arg(&e.filename);
+                })),
+                State::Ignored => continue,
+                _ => (),
+            }
+            files_to_commit.push(&e.filename);
+        }
+        tasks.push(task(self, |command| {
+            command.arg("commit").arg("-m").arg(message).arg("--");
+            for file in files_to_commit {
+                command.arg(file);
+            }
+        }));
+        serial(tasks)
+    }
+
+    fn get_current_patch(
+        &self,
+        _entries: &[Entry],
+    ) -> Result<Vec<FilePatch>, VcsError> {
+        Err(unsupported_error(self, "committing hunks"))
+    }
+
+    fn commit_hunks(
+        &self,
+        _message: &str,
+        _files: &[FilePatch],
+    ) -> Box<dyn ActionTask> {
+        unsupported(self, "committing hunks")
+    }
+
+    fn get_last_commit_message(&self) -> Result<String, VcsError> {
+        Err(unsupported_error(self, "amending commits"))
+    }
+
+    fn is_last_commit_published(&self) -> Result<bool, VcsError> {
+        Err(unsupported_error(self, "amending commits"))
+    }
+
+    fn amend(&self, _message: &str, _entries: &[Entry]) -> Box<dyn ActionTask> {
+        unsupported(self, "amending commits")
+    }
+
+    fn revert_all(&self) -> Box<dyn ActionTask> {
+        let mut tasks = task_vec();
+        tasks.push(task(self, |command| {
+            command.args(["revert", "--recursive", "."]);
+        }));
+        tasks.push(task(self, |command| {
+            command.args(["cleanup", "--remove-unversioned"]);
+        }));
+        serial(tasks)
+    }
+
+    fn revert_selected(&self, entries: &[Entry]) -> Box<dyn ActionTask> {
+        let mut tasks = task_vec();
+        let mut files_to_revert = Vec::new();
+        let mut files_to_remove = Vec::new();
+        for e in entries.iter().filter(|e| e.selected) {
+            match e.state {
+                State::Untracked => files_to_remove.push(e.filename.clone()),
+                _ => files_to_revert.push(&e.filename),
+            }
+        }
+        if !files_to_revert.is_empty() {
+            tasks.push(task(self, |command| {
+                command.arg("revert").arg("--");
+                for file in files_to_revert {
+                    command.arg(file);
+                }
+            }));
+        }
+        if !files_to_remove.is_empty() {
+            let root = self.current_dir.clone();
+            tasks.push(lazy(move || {
+                remove_unversioned(&root, &files_to_remove)
+            }));
+        }
+        serial(tasks)
+    }
+
+    fn update(&self, target: &str) -> Box<dyn ActionTask> {
+        if is_revision(target) {
+            task(self, |command| {
+                command.arg("update").arg("-r").arg(target);
+            })
+        } else {
+            task(self, |command| {
+                command.arg("switch").arg(branch_url(target));
+            })
+        }
+    }
+
+    fn merge(&self, target: &str) -> Box<dyn ActionTask> {
+        if is_revision(target) {
+            task(self, |command| {
+                command.arg("merge").arg("-c").arg(target);
+            })
+        } else {
+            task(self, |command| {
+                command.arg("merge").arg(branch_url(target));
+            })
+        }
+    }
+
+    fn rebase(&self, _target: &str) -> Box<dyn ActionTask> {
+        unsupported(self, "rebasing")
+    }
+
+    fn rebase_continue(&self) -> Box<dyn ActionTask> {
+        unsupported(self, "rebasing")
+    }
+
+    fn rebase_skip(&self) -> Box<dyn ActionTask> {
+        unsupported(self, "rebasing")
+    }
+
+    fn rebase_abort(&self) -> Box<dyn ActionTask> {
+        unsupported(self, "rebasing")
+    }
+
+    fn cherry_pick(&self, _revisions: &[String]) -> Box<dyn ActionTask> {
+        unsupported(self, "cherry-picking")
+    }
+
+    fn cherry_pick_continue(&self) -> Box<dyn ActionTask> {
+        unsupported(self, "cherry-picking")
+    }
+
+    fn cherry_pick_abort(&self) -> Box<dyn ActionTask> {
+        unsupported(self, "cherry-picking")
+    }
+
+    fn backout(&self, revision: &str, message: &str) -> Box<dyn ActionTask> {
+        let mut tasks = task_vec();
+        tasks.push(task(self, |command| {
+            command
+                .arg("merge")
+                .arg("-c")
+                .arg(format!("-{}", revision))
+                .arg(".");
+        }));
+        tasks.push(task(self, |command| {
+            command.arg("commit").arg("-m").arg(message);
+        }));
+        chain(tasks)
+    }
+
+    fn bisect_start(&self, _bad: &str, _good: &str) -> Box<dyn ActionTask> {
+        unsupported(self, "bisecting")
+    }
+
+    fn bisect_good(&self) -> Box<dyn ActionTask> {
+        unsupported(self, "bisecting")
+    }
+
+    fn bisect_bad(&self) -> Box<dyn ActionTask> {
+        unsupported(self, "bisecting")
+    }
+
+    fn bisect_skip(&self) -> Box<dyn ActionTask> {
+        unsupported(self, "bisecting")
+    }
+
+    fn bisect_run(
+        &self,
+        _command: &str,
+        _args: &[String],
+    ) -> Box<dyn ActionTask> {
+        unsupported(self, "bisecting")
+    }
+
+    fn bisect_reset(&self) -> Box<dyn ActionTask> {
+        unsupported(self, "bisecting")
+    }
+
+    fn get_history(&self, _base: &str) -> Result<Vec<PlanEntry>, VcsError> {
+        Err(unsupported_error(self, "editing history"))
+    }
+
+    fn edit_history(
+        &self,
+        _base: &str,
+        _plan: &[PlanEntry],
+    ) -> Box<dyn ActionTask> {
+        unsupported(self, "editing history")
+    }
+
+    fn conflicts(&self) -> Box<dyn ActionTask> {
+        let status = task(self, |command| {
+            command.arg("status");
+        });
+        map_output(status, |output| {
+            output
+                .lines()
+                .filter(|l| {
+                    let mut columns = l.chars();
+                    columns.next() == Some('C')
+                        || columns.next() == Some('C')
+                        || columns.nth(4) == Some('C')
+                })
+                .map(|l| format!("{}\n", l))
+                .collect()
+        })
+    }
+
+    fn take_other(&self) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.args(["resolve", "--accept", "theirs-full", "-R", "."]);
+        })
+    }
+
+    fn take_local(&self) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.args(["resolve", "--accept", "mine-full", "-R", "."]);
+        })
+    }
+
+    fn fetch(&self, _remote: Option<&str>) -> Box<dyn ActionTask> {
+        unsupported(self, "fetch")
+    }
+
+    fn pull(&self, _remote: Option<&str>) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command.arg("update");
+        })
+    }
+
+    fn push(&self, _remote: Option<&str>) -> Box<dyn ActionTask> {
+        unsupported(self, "push since commits are sent directly to the server")
+    }
+
+    fn list_tags(&self) -> Box<dyn ActionTask> {
+        let list = task(self, |command| {
+            command.args(["list", "--verbose", "^/tags"]);
+        });
+        map_output(list, format_tag_list)
+    }
+
+    fn get_tags(&self) -> Result<Vec<String>, VcsError> {
+        let output =
+            self.run_command(self.command().args(["list", "^/tags"]))?;
+        Ok(output
+            .lines()
+            .map(|t| String::from(t.trim_end_matches('/')))
+            .collect())
+    }
+
+    fn create_tag(
+        &self,
+        name: &str,
+        message: Option<&str>,
+        force: bool,
+        _remote: Option<&str>,
+    ) -> Box<dyn ActionTask> {
+        let relative_url = match self.relative_url() {
+            Ok(url) => url,
+            Err(error) => return ready(ActionResult::from_error(error)),
+        };
+
+        // deleting a tag that is not there would stop the chain
+        let replace = force
+            && match self.get_tags() {
+                Ok(tags) => tags.iter().any(|t| t == name),
+                Err(_) => true,
+            };
+        let mut tasks = task_vec();
+        if replace {
+            tasks.push(self.delete_tag(name, None));
+        }
+        tasks.push(task(self, |command| {
+            command
+                .arg("copy")
+                .arg(relative_url)
+                .arg(tag_url(name))
+                .arg("-m")
+                .arg(message.map_or_else(
+                    || format!("create tag {}", name),
+                    String::from,
+                ));
+        }));
+        chain(tasks)
+    }
+
+    fn delete_tag(
+        &self,
+        name: &str,
+        _remote: Option<&str>,
+    ) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command
+                .arg("delete")
+                .arg(tag_url(name))
+                .arg("-m")
+                .arg(format!("delete tag {}", name));
+        })
+    }
+
+    fn list_submodules(&self) -> Box<dyn ActionTask> {
+        unsupported(self, "submodules")
+    }
+
+    fn init_submodules(&self) -> Box<dyn ActionTask> {
+        unsupported(self, "submodules")
+    }
+
+    fn update_submodules(&self) -> Box<dyn ActionTask> {
+        unsupported(self, "submodules")
+    }
+
+    fn sync_submodules(&self) -> Box<dyn ActionTask> {
+        unsupported(self, "submodules")
+    }
+
+    fn get_superproject_root(&self) -> Result<String, VcsError> {
+        Err(unsupported_error(self, "submodules"))
+    }
+
+    fn list_worktrees(&self) -> Box<dyn ActionTask> {
+        unsupported(self, "worktrees")
+    }
+
+    fn add_worktree(&self, _path: &str, _target: &str) -> Box<dyn ActionTask> {
+        unsupported(self, "worktrees")
+    }
+
+    fn remove_worktree(&self, _path: &str) -> Box<dyn ActionTask> {
+        unsupported(self, "worktrees")
+    }
+
+    fn prune_worktrees(&self) -> Box<dyn ActionTask> {
+        unsupported(self, "worktrees")
+    }
+
+    fn list_remotes(&self) -> Box<dyn ActionTask> {
+        unsupported(self, "remotes")
+    }
+
+    fn add_remote(&self, _name: &str, _url: &str) -> Box<dyn ActionTask> {
+        unsupported(self, "remotes")
+    }
+
+    fn rename_remote(
+        &self,
+        _name: &str,
+        _new_name: &str,
+    ) -> Box<dyn ActionTask> {
+        unsupported(self, "remotes")
+    }
+
+    fn remove_remote(&self, _name: &str) -> Box<dyn ActionTask> {
+        unsupported(self, "remotes")
+    }
+
+    fn set_remote_url(&self, _name: &str, _url: &str) -> Box<dyn ActionTask> {
+        unsupported(self, "remotes")
+    }
+
+    fn list_branches(&self) -> Box<dyn ActionTask> {
+        let list = task(self, |command| {
+            command.args(["list", "^/branches"]);
+        });
+        map_output(list, |output| {
+            let mut branches = String::from("trunk\n");
+            for branch in output.lines() {
+                branches.push_str(branch.trim_end_matches('/'));
+                branches.push('\n');
+            }
+            branches
+        })
+    }
+
+    fn create_branch(
+        &self,
+        name: &str,
+        _remote: Option<&str>,
+    ) -> Box<dyn ActionTask> {
+        let relative_url = match self.relative_url() {
+            Ok(url) => url,
+            Err(error) => return ready(ActionResult::from_error(error)),
+        };
+
+        let mut tasks = task_vec();
+        tasks.push(task(self, |command| {
+            command
+                .arg("copy")
+                .arg(relative_url)
+                .arg(branch_url(name))
+                .arg("-m")
+                .arg(format!("create branch {}", name));
+        }));
+        tasks.push(self.update(name));
+        serial(tasks)
+    }
+
+    fn create_branch_at(
+        &self,
+        name: &str,
+        revision: &str,
+    ) -> Box<dyn ActionTask> {
+        let relative_url = match self.relative_url() {
+            Ok(url) => url,
+            Err(error) => return ready(ActionResult::from_error(error)),
+        };
+
+        task(self, |command| {
+            command
+                .arg("copy")
+                .arg("-r")
+                .arg(revision)
+                .arg(relative_url)
+                .arg(branch_url(name))
+                .arg("-m")
+                .arg(format!("create branch {}", name));
+        })
+    }
+
+    fn close_branch(
+        &self,
+        name: &str,
+        _remote: Option<&str>,
+    ) -> Box<dyn ActionTask> {
+        task(self, |command| {
+            command
+                .arg("delete")
+                .arg(branch_url(name))
+                .arg("-m")
+                .arg(format!("close branch {}", name));
+        })
+    }
+
+    fn list_stashes(&self) -> Box<dyn ActionTask> {
+        unsupported(self, "stashes")
+    }
+
+    fn stash_all(&self, _message: &str) -> Box<dyn ActionTask> {
+        unsupported(self, "stashes")
+    }
+
+    fn stash_selected(
+        &self,
+        _message: &str,
+        _entries: &[Entry],
+    ) -> Box<dyn ActionTask> {
+        unsupported(self, "stashes")
+    }
+
+    fn show_stash(&self, _name: &str) -> Box<dyn ActionTask> {
+        unsupported(self, "stashes")
+    }
+
+    fn apply_stash(&self, _name: &str) -> Box<dyn ActionTask> {
+        unsupported(self, "stashes")
+    }
+
+    fn pop_stash(&self, _name: &str) -> Box<dyn ActionTask> {
+        unsupported(self, "stashes")
+    }
+
+    fn drop_stash(&self, _name: &str) -> Box<dyn ActionTask> {
+        unsupported(self, "stashes")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STATUS_XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<status>
+<target
+   path=".">
+<entry
+   path="a &amp; b.txt">
+<wc-status
+   item="modified"
+   revision="3"
+   props="none">
+<commit
+   revision="2">
+<author>alice</author>
+<date>2024-01-02T03:04:05.000000Z</date>
+</commit>
+</wc-status>
+</entry>
+<entry
+   path="new.txt">
+<wc-status
+   props="none"
+   item="unversioned">
+</wc-status>
+</entry>
+<entry
+   path="props.txt">
+<wc-status
+   item="normal"
+   revision="3"
+   props="modified">
+</wc-status>
+</entry>
+<entry
+   path="clean.txt">
+<wc-status
+   item="normal"
+   revision="3"
+   props="none">
+</wc-status>
+</entry>
+<entry
+   path="gone.txt">
+<wc-status
+   item="missing"
+   props="none">
+</wc-status>
+</entry>
+</target>
+</status>
+"#;
+
+    const LOG_XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<log>
+<logentry
+   revision="2">
+<author>bob &lt;bob@example.com&gt;</author>
+<date>2024-01-03T10:00:00.000000Z</date>
+<msg>fix &quot;quotes&quot; &amp; more
+
+with a body</msg>
+</logentry>
+<logentry
+   revision="1">
+<author>alice</author>
+<date>2024-01-02T03:04:05.000000Z</date>
+<msg>initial</msg>
+</logentry>
+</log>
+"#;
+
+    #[test]
+    fn status_xml_skips_unmodified_entries() {
+        let entries = parse_status_xml(STATUS_XML);
+        let filenames: Vec<_> =
+            entries.iter().map(|e| &e.filename[..]).collect();
+        assert_eq!(
+            filenames,
+            ["a & b.txt", "new.txt", "props.txt", "gone.txt"]
+        );
+        assert!(matches!(entries[0].state, State::Modified));
+        assert!(matches!(entries[1].state, State::Untracked));
+        assert!(matches!(entries[2].state, State::Modified));
+        assert!(matches!(entries[3].state, State::Missing));
+    }
+
+    #[test]
+    fn status_xml_without_entries_is_empty() {
+        let output = "<?xml version=\"1.0\"?>\n<status>\n<target\n   path=\".\">\n</target>\n</status>\n";
+        assert!(parse_status_xml(output).is_empty());
+    }
+
+    #[test]
+    fn log_xml_renders_records() {
+        let log = format_log_xml(String::from(LOG_XML));
+        let entries = LogEntry::parse_all(&log);
+        assert_eq!(entries.len(), 2);
+
+        assert_eq!(entries[0].id, "2");
+        assert_eq!(entries[0].short_id, "2");
+        assert_eq!(entries[0].parents, ["1"]);
+        assert_eq!(entries[0].author, "bob <bob@example.com>");
+        assert_eq!(entries[0].date, "2024-01-03");
+        assert_eq!(entries[0].subject, "fix \"quotes\" & more");
+
+        assert_eq!(entries[1].id, "1");
+        assert!(entries[1].parents.is_empty());
+        assert_eq!(entries[1].subject, "initial");
+    }
+
+    #[test]
+    fn xml_helpers_read_the_first_match() {
+        let xml =
+            "<entry\n   path=\"x\"\n   kind=\"file\">\n<name>n</name></entry>";
+        assert_eq!(xml_attribute(xml, "path"), Some("x"));
+        assert_eq!(xml_attribute(xml, "kind"), Some("file"));
+        assert_eq!(xml_attribute(xml, "missing"), None);
+        assert_eq!(xml_element(xml, "name"), Some("n"));
+        assert_eq!(xml_unescape("&amp;lt;"), "&lt;");
+    }
+}
@@ -16,16 +16,31 @@ use std::{
     time::Duration,
 };
 
+use verco::{
+    action::{ActionResult, ActionTask},
+    blame::BlameLine,
+    history_edit::PlanEntry,
+    patch::FilePatch,
+    select::Entry,
+    vcs_error::VcsError,
+    version_control_actions::{Capability, Support},
+};
+
 use crate::{
-    action::{ActionKind, ActionResult, ActionTask},
+    action_kind::ActionKind,
     application::{ActionFuture, Application},
+    history_view::edit_plan,
+    hunk_view::select_hunks,
     input::{self, Event},
     scroll_view::ScrollView,
-    select::{select, Entry},
-    tui_util::{show_header, Header, HeaderKind, TerminalSize, ENTRY_COLOR},
+    select_view::select,
+    tui_util::{
+        show_header, Header, HeaderKind, TerminalSize, DIMMED_COLOR,
+        ENTRY_COLOR,
+    },
 };
 
-const VERSION: &'static str = env!("CARGO_PKG_VERSION");
+const VERSION: &str = env!("CARGO_PKG_VERSION");
 
 pub fn show_tui(mut app: Application) {
     let stdout = stdout();
@@ -47,6 +62,8 @@ where
     previous_action_kind: ActionKind,
     current_action_kind: ActionKind,
     current_key_chord: Vec<char>,
+    /// The file shown by the last blame so its lines can be re-blamed
+    blame_filename: Option<String>,
 
     write: W,
     terminal_size: TerminalSize,
@@ -62,6 +79,7 @@ where
             previous_action_kind: ActionKind::Quit,
             current_action_kind: ActionKind::Quit,
             current_key_chord: Vec::new(),
+            blame_filename: None,
             write,
             terminal_size: Default::default(),
             scroll_view: Default::default(),
@@ -73,9 +91,16 @@ where
         app: &Application,
         kind: HeaderKind,
     ) -> Result<()> {
+        let state = if app.version_control.is_bisect_in_progress() {
+            Some("bisecting")
+        } else {
+            None
+        };
         let header = Header {
             action_name: self.current_action_kind.name(),
             directory_name: app.version_control.get_root(),
+            state,
+            upstream: app.upstream.as_deref(),
         };
         show_header(&mut self.write, header, kind, self.terminal_size)
     }
@@ -89,6 +114,24 @@ where
         select(&mut self.write, entries)
     }
 
+    fn show_hunk_select_ui(
+        &mut self,
+        app: &Application,
+        files: &mut [FilePatch],
+    ) -> Result<bool> {
+        self.show_header(app, HeaderKind::Waiting)?;
+        select_hunks(&mut self.write, files)
+    }
+
+    fn show_plan_edit_ui(
+        &mut self,
+        app: &Application,
+        entries: &mut [PlanEntry],
+    ) -> Result<bool> {
+        self.show_header(app, HeaderKind::Waiting)?;
+        edit_plan(&mut self.write, entries)
+    }
+
     fn show_action(
         &mut self,
         app: &mut Application,
@@ -102,6 +145,74 @@ where
         self.show_result(app, result)
     }
 
+    /// Makes the repository at `dir` the active one and shows its status
+    fn switch_root(&mut self, app: &mut Application, dir: &str) -> Result<()> {
+        match app.version_control.switch_root(dir) {
+            Ok(()) => {
+                // results and targets from the previous root no longer apply
+                app.clear_action_results();
+                self.blame_filename = None;
+                self.write.queue(SetTitle(app.version_control.get_root()))?;
+                let action = app.version_control.status();
+                self.show_action(app, action)
+            }
+            Err(error) => {
+                self.show_result(app, &ActionResult::from_error(error))
+            }
+        }
+    }
+
+    /// Asks for the remote to sync with, or to publish tags and branches to
+    /// when `is_sync` is false, pre-filled with the remote under the cursor
+    /// or else the upstream of the current branch. An empty input stands for
+    /// the default and backends without remotes skip the question.
+    fn handle_remote_input(
+        &mut self,
+        app: &Application,
+        is_sync: bool,
+    ) -> Result<Option<String>> {
+        let asks = if is_sync {
+            !matches!(
+                app.version_control.support(Capability::Remotes),
+                Support::Unsupported(_)
+            )
+        } else {
+            app.version_control.publishes_refs()
+        };
+        if !asks {
+            return Ok(Some(String::new()));
+        }
+
+        let remote = if self.previous_action_kind == ActionKind::ListRemotes {
+            self.previous_target(app)
+        } else {
+            None
+        };
+        let remote =
+            remote.or_else(|| app.version_control.get_upstream_remote());
+        if is_sync {
+            return self.handle_optional_input(
+                app,
+                "remote",
+                remote.as_deref(),
+            );
+        }
+
+        // refs stay local only when asked to with `-`
+        let remote =
+            remote.or_else(|| app.version_control.get_default_remote());
+        let input = self.handle_optional_input(
+            app,
+            "remote (- keeps it local)",
+            remote.as_deref(),
+        )?;
+        Ok(input.map(|input| match input.trim() {
+            "-" => String::new(),
+            "" => remote.unwrap_or_default(),
+            input => String::from(input),
+        }))
+    }
+
     fn show_empty_entries(&mut self, app: &Application) -> Result<()> {
         self.show_header(app, HeaderKind::Error)?;
         self.write.queue(Print("nothing to select"))?;
@@ -116,28 +227,81 @@ where
 
     fn action_context<F>(
         &mut self,
+        app: &mut Application,
         action: ActionKind,
         callback: F,
     ) -> Result<HandleChordResult>
     where
-        F: FnOnce(&mut Self) -> Result<()>,
+        F: FnOnce(&mut Self, &mut Application) -> Result<()>,
     {
         self.previous_action_kind = self.current_action_kind;
         self.current_action_kind = action;
-        callback(self).map(|_| HandleChordResult::Handled)
+
+        let support = action
+            .capability()
+            .map(|c| app.version_control.support(c))
+            .unwrap_or(Support::Supported);
+        if let Support::Unsupported(reason) = support {
+            let result = ActionResult::from_err(format!(
+                "{} does not support {}: {}",
+                app.version_control.executable_name(),
+                action.name(),
+                reason
+            ));
+            return self
+                .show_result(app, &result)
+                .map(|_| HandleChordResult::Handled);
+        }
+
+        callback(self, app).map(|_| HandleChordResult::Handled)
     }
 
-    fn previous_target<'a>(&self, app: &'a Application) -> Option<&'a str> {
+    fn previous_target(&self, app: &Application) -> Option<String> {
         let previous_result =
             app.get_cached_action_result(self.previous_action_kind);
-        if !previous_result.success {
+        if !previous_result.success
+            || self.scroll_view.action_kind() != self.previous_action_kind
+        {
             return None;
         }
 
+        if self.previous_action_kind.is_log() {
+            return self.scroll_view.selected_log_entry().map(|e| e.id.clone());
+        }
+        if self.previous_action_kind.is_blame() {
+            return self
+                .scroll_view
+                .selected_blame_line()
+                .map(|l| l.revision.clone());
+        }
+
         self.scroll_view
-            .cursor()
-            .and_then(|c| previous_result.output.lines().nth(c))
+            .selected_line()
             .and_then(|l| self.previous_action_kind.parse_target(l))
+            .map(String::from)
+    }
+
+    /// The revisions marked in the previous log oldest first or else the
+    /// previous target
+    fn previous_targets(&self, app: &Application) -> Vec<String> {
+        let previous_result =
+            app.get_cached_action_result(self.previous_action_kind);
+        if previous_result.success
+            && self.previous_action_kind.is_log()
+            && self.scroll_view.action_kind() == self.previous_action_kind
+        {
+            let mut ids: Vec<_> = self
+                .scroll_view
+                .marked_log_entries()
+                .map(|e| e.id.clone())
+                .collect();
+            if !ids.is_empty() {
+                ids.reverse();
+                return ids;
+            }
+        }
+
+        self.previous_target(app).into_iter().collect()
     }
 
     fn show(&mut self, app: &mut Application) -> Result<()> {
@@ -161,7 +325,9 @@ where
         }
 
         loop {
-            if app.poll_and_check_action(self.current_action_kind) {
+            let just_finished =
+                app.poll_and_check_action(self.current_action_kind);
+            if just_finished || app.poll_upstream() {
                 let result =
                     app.get_cached_action_result(self.current_action_kind);
                 self.show_result(app, result)?;
@@ -196,7 +362,7 @@ where
                         continue;
                     }
 
-                    if self.current_key_chord.len() == 0 {
+                    if self.current_key_chord.is_empty() {
                         break;
                     }
 
@@ -253,16 +419,16 @@ where
                 self.show_result(app, &help)?;
                 Ok(HandleChordResult::Handled)
             }
-            ['s'] => self.action_context(ActionKind::Status, |s| {
+            ['s'] => self.action_context(app, ActionKind::Status, |s, app| {
                 let action = app.version_control.status();
                 s.show_action(app, action)
             }),
             ['l'] => Ok(HandleChordResult::Unhandled),
-            ['l', 'l'] => self.action_context(ActionKind::Log, |s| {
+            ['l', 'l'] => self.action_context(app, ActionKind::Log, |s, app| {
                 let action = app.version_control.log(s.terminal_size.height as usize);
                 s.show_action(app, action)
             }),
-            ['l', 'c'] => self.action_context(ActionKind::LogCount, |s| {
+            ['l', 'c'] => self.action_context(app, ActionKind::LogCount, |s, app| {
                 if let Some(input) = s.handle_input(app, "logs to show", None)? {
                     if let Ok(count) = input.trim().parse() {
                         let action = app.version_control.log(count);
@@ -279,20 +445,24 @@ where
                     s.show_previous_action_result(app)
                 }
             }),
+            ['l', 'r'] => self.action_context(app, ActionKind::Reflog, |s, app| {
+                let action = app.version_control.reflog();
+                s.show_action(app, action)
+            }),
             ['e'] => Ok(HandleChordResult::Unhandled),
-            ['e', 'e'] => self.action_context(ActionKind::CurrentFullRevision, |s| {
+            ['e', 'e'] => self.action_context(app, ActionKind::CurrentFullRevision, |s, app| {
                 let action =  app.version_control.current_export();
                 s.show_action(app, action)
             }),
             ['d'] => Ok(HandleChordResult::Unhandled),
-            ['d', 'd'] => self.action_context(ActionKind::CurrentDiffAll, |s| {
+            ['d', 'd'] => self.action_context(app, ActionKind::CurrentDiffAll, |s, app| {
                 let action =  app.version_control.current_diff_all();
                 s.show_action(app, action)
             }),
-            ['d', 's'] => self.action_context(ActionKind::CurrentDiffSelected, |s| {
+            ['d', 's'] => self.action_context(app, ActionKind::CurrentDiffSelected, |s, app| {
                 match app.version_control.get_current_changed_files() {
                     Ok(mut entries) => {
-                        if entries.len() == 0 {
+                        if entries.is_empty() {
                             s.show_empty_entries(app)
                         } else if s.show_select_ui(app, &mut entries[..])? {
                             let action =  app.version_control.current_diff_selected(&entries);
@@ -301,31 +471,31 @@ where
                             s.show_previous_action_result(app)
                         }
                     }
-                    Err(error) => s.show_result(app, &ActionResult::from_err(error)),
+                    Err(error) => s.show_result(app, &ActionResult::from_error(error)),
                 }
             }),
             ['D'] => Ok(HandleChordResult::Unhandled),
-            ['D', 'C'] => self.action_context(ActionKind::RevisionChanges, |s| {
-                if let Some(input) = s.handle_input(app, "show changes from", s.previous_target(app))? {
+            ['D', 'C'] => self.action_context(app, ActionKind::RevisionChanges, |s, app| {
+                if let Some(input) = s.handle_input(app, "show changes from", s.previous_target(app).as_deref())? {
                     let action =  app.version_control.revision_changes(input.trim());
                     s.show_action(app, action)
                 } else {
                     s.show_previous_action_result(app)
                 }
             }),
-            ['D', 'D'] => self.action_context(ActionKind::RevisionDiffAll, |s| {
-                if let Some(input) = s.handle_input(app, "show diff from", s.previous_target(app))? {
+            ['D', 'D'] => self.action_context(app, ActionKind::RevisionDiffAll, |s, app| {
+                if let Some(input) = s.handle_input(app, "show diff from", s.previous_target(app).as_deref())? {
                     let action =  app.version_control.revision_diff_all(input.trim());
                     s.show_action(app, action)
                 } else {
                     s.show_previous_action_result(app)
                 }
             }),
-            ['D', 'S'] => self.action_context(ActionKind::RevisionDiffSelected, |s| {
-                if let Some(input) = s.handle_input(app, "show diff from", s.previous_target(app))? {
+            ['D', 'S'] => self.action_context(app, ActionKind::RevisionDiffSelected, |s, app| {
+                if let Some(input) = s.handle_input(app, "show diff from", s.previous_target(app).as_deref())? {
                     match app.version_control.get_revision_changed_files(input.trim()) {
                         Ok(mut entries) => {
-                            if entries.len() == 0 {
+                            if entries.is_empty() {
                                 s.show_empty_entries(app)
                             } else if s.show_select_ui(app, &mut entries[..])? {
                                 let action =  app.version_control.revision_diff_selected(input.trim(), &entries);
@@ -334,25 +504,70 @@ where
                                 s.show_previous_action_result(app)
                             }
                         }
-                        Err(error) => s.show_result(app, &ActionResult::from_err(error)),
+                        Err(error) => s.show_result(app, &ActionResult::from_error(error)),
+                    }
+                } else {
+                    s.show_previous_action_result(app)
+                }
+            }),
+            ['a'] => Ok(HandleChordResult::Unhandled),
+            ['a', 'a'] => self.action_context(app, ActionKind::Blame, |s, app| {
+                match app.version_control.get_tracked_files() {
+                    Ok(mut entries) => {
+                        if entries.is_empty() {
+                            s.show_empty_entries(app)
+                        } else if s.show_select_ui(app, &mut entries[..])? {
+                            match entries.into_iter().find(|e| e.selected) {
+                                Some(entry) => {
+                                    let action = app.version_control.blame(&entry.filename, None);
+                                    s.blame_filename = Some(entry.filename);
+                                    s.show_action(app, action)
+                                }
+                                None => s.show_previous_action_result(app),
+                            }
+                        } else {
+                            s.show_previous_action_result(app)
+                        }
+                    }
+                    Err(error) => s.show_result(app, &ActionResult::from_error(error)),
+                }
+            }),
+            ['a', 'p'] => self.action_context(app, ActionKind::BlameParent, |s, app| {
+                let filename = match s.blame_filename.clone() {
+                    Some(filename) => filename,
+                    None => {
+                        let result = ActionResult::from_err(String::from(
+                            "nothing blamed yet, blame a file with aa first",
+                        ));
+                        return s.show_result(app, &result);
+                    }
+                };
+                if let Some(input) = s.handle_input(app, "blame parent of", s.previous_target(app).as_deref())? {
+                    match app.version_control.get_parent_revision(input.trim()) {
+                        Ok(parent) => {
+                            let action = app.version_control.blame(&filename, Some(&parent));
+                            s.show_action(app, action)
+                        }
+                        Err(error) => s.show_result(app, &ActionResult::from_error(error)),
                     }
                 } else {
                     s.show_previous_action_result(app)
                 }
             }),
             ['c'] => Ok(HandleChordResult::Unhandled),
-            ['c', 'c'] => self.action_context(ActionKind::CommitAll, |s| {
-                if let Some(input) = s.handle_input(app, "commit message", None)? {
+            ['c', 'c'] => self.action_context(app, ActionKind::CommitAll, |s, app| {
+                let message = app.version_control.get_backout_message();
+                if let Some(input) = s.handle_input(app, "commit message", message.as_deref())? {
                     let action =  app.version_control.commit_all(input.trim());
                     s.show_action(app, action)
                 } else {
                     s.show_previous_action_result(app)
                 }
             }),
-            ['c', 's'] => self.action_context(ActionKind::CommitSelected, |s| {
+            ['c', 's'] => self.action_context(app, ActionKind::CommitSelected, |s, app| {
                 match app.version_control.get_current_changed_files() {
                     Ok(mut entries) => {
-                        if entries.len() == 0 {
+                        if entries.is_empty() {
                             s.show_empty_entries(app)
                         } else if s.show_select_ui(app, &mut entries[..])? {
                             s.show_header(app, HeaderKind::Waiting)?;
@@ -368,35 +583,211 @@ where
                             s.show_previous_action_result(app)
                         }
                     }
-                    Err(error) => s.show_result(app, &ActionResult::from_err(error)),
+                    Err(error) => s.show_result(app, &ActionResult::from_error(error)),
+                }
+            }),
+            ['c', 'h'] => self.action_context(app, ActionKind::CommitHunks, |s, app| {
+                match app.version_control.get_current_changed_files() {
+                    Ok(mut entries) => {
+                        if entries.is_empty() {
+                            s.show_empty_entries(app)
+                        } else if s.show_select_ui(app, &mut entries[..])? {
+                            match app.version_control.get_current_patch(&entries) {
+                                Ok(mut files) => {
+                                    if files.iter().all(|f| f.hunks.is_empty()) {
+                                        s.show_empty_entries(app)
+                                    } else if s.show_hunk_select_ui(app, &mut files[..])? {
+                                        s.show_header(app, HeaderKind::Waiting)?;
+                                        if let Some(input) =
+                                            s.handle_input(app, "commit message", None)?
+                                        {
+                                            let action =  app.version_control.commit_hunks(input.trim(), &files);
+                                            s.show_action(app, action)
+                                        } else {
+                                            s.show_previous_action_result(app)
+                                        }
+                                    } else {
+                                        s.show_previous_action_result(app)
+                                    }
+                                }
+                                Err(error) => s.show_result(app, &ActionResult::from_error(error)),
+                            }
+                        } else {
+                            s.show_previous_action_result(app)
+                        }
+                    }
+                    Err(error) => s.show_result(app, &ActionResult::from_error(error)),
                 }
             }),
-            ['u'] => self.action_context(ActionKind::Update, |s| {
-                if let Some(input) = s.handle_input(app, "update to", s.previous_target(app))? {
+            ['c', 'a'] => self.action_context(app, ActionKind::Amend, |s, app| {
+                match app.version_control.is_last_commit_published() {
+                    Ok(true) => {
+                        let result = ActionResult::from_err(String::from(
+                            "the last commit was already pushed and amending it would rewrite published history",
+                        ));
+                        return s.show_result(app, &result);
+                    }
+                    Ok(false) => (),
+                    Err(error) => return s.show_result(app, &ActionResult::from_error(error)),
+                }
+                let message = match app.version_control.get_last_commit_message() {
+                    Ok(message) => message,
+                    Err(error) => return s.show_result(app, &ActionResult::from_error(error)),
+                };
+                match app.version_control.get_current_changed_files() {
+                    Ok(mut entries) => {
+                        // cancelling the file selection amends only the message
+                        if !entries.is_empty() && !s.show_select_ui(app, &mut entries[..])? {
+                            for e in &mut entries {
+                                e.selected = false;
+                            }
+                        }
+                        s.show_header(app, HeaderKind::Waiting)?;
+                        if let Some(input) =
+                            s.handle_input(app, "commit message", Some(message.trim()))?
+                        {
+                            let action =  app.version_control.amend(input.trim(), &entries);
+                            s.show_action(app, action)
+                        } else {
+                            s.show_previous_action_result(app)
+                        }
+                    }
+                    Err(error) => s.show_result(app, &ActionResult::from_error(error)),
+                }
+            }),
+            ['u'] => self.action_context(app, ActionKind::Update, |s, app| {
+                if let Some(input) = s.handle_input(app, "update to", s.previous_target(app).as_deref())? {
                     let action =  app.version_control.update(input.trim());
                     s.show_action(app, action)
                 } else {
                     s.show_previous_action_result(app)
                 }
             }),
-            ['m'] => self.action_context(ActionKind::Merge, |s| {
-                if let Some(input) = s.handle_input(app, "merge with", s.previous_target(app))? {
+            ['m'] => self.action_context(app, ActionKind::Merge, |s, app| {
+                if let Some(input) = s.handle_input(app, "merge with", s.previous_target(app).as_deref())? {
                     let action =  app.version_control.merge(input.trim());
                     s.show_action(app, action)
                 } else {
                     s.show_previous_action_result(app)
                 }
             }),
+            ['B'] => Ok(HandleChordResult::Unhandled),
+            ['B', 'B'] => self.action_context(app, ActionKind::Rebase, |s, app| {
+                if let Some(input) = s.handle_input(app, "rebase onto", s.previous_target(app).as_deref())? {
+                    let action =  app.version_control.rebase(input.trim());
+                    s.show_action(app, action)
+                } else {
+                    s.show_previous_action_result(app)
+                }
+            }),
+            ['B', 'C'] => self.action_context(app, ActionKind::RebaseContinue, |s, app| {
+                let action =  app.version_control.rebase_continue();
+                s.show_action(app, action)
+            }),
+            ['B', 'S'] => self.action_context(app, ActionKind::RebaseSkip, |s, app| {
+                let action =  app.version_control.rebase_skip();
+                s.show_action(app, action)
+            }),
+            ['B', 'A'] => self.action_context(app, ActionKind::RebaseAbort, |s, app| {
+                let action =  app.version_control.rebase_abort();
+                s.show_action(app, action)
+            }),
+            ['B', 'E'] => self.action_context(app, ActionKind::EditHistory, |s, app| {
+                if let Some(input) = s.handle_input(app, "edit history after", s.previous_target(app).as_deref())? {
+                    match app.version_control.get_history(input.trim()) {
+                        Ok(mut entries) => {
+                            if entries.is_empty() {
+                                s.show_empty_entries(app)
+                            } else if s.show_plan_edit_ui(app, &mut entries[..])? {
+                                let action =  app.version_control.edit_history(input.trim(), &entries);
+                                s.show_action(app, action)
+                            } else {
+                                s.show_previous_action_result(app)
+                            }
+                        }
+                        Err(error) => s.show_result(app, &ActionResult::from_error(error)),
+                    }
+                } else {
+                    s.show_previous_action_result(app)
+                }
+            }),
+            ['g'] => Ok(HandleChordResult::Unhandled),
+            ['g', 'g'] => self.action_context(app, ActionKind::CherryPick, |s, app| {
+                let targets = s.previous_targets(app).join(" ");
+                if let Some(input) = s.handle_input(app, "cherry-pick", Some(&targets))? {
+                    let revisions: Vec<_> = input.split_whitespace().map(String::from).collect();
+                    let action =  app.version_control.cherry_pick(&revisions);
+                    s.show_action(app, action)
+                } else {
+                    s.show_previous_action_result(app)
+                }
+            }),
+            ['g', 'c'] => self.action_context(app, ActionKind::CherryPickContinue, |s, app| {
+                let action =  app.version_control.cherry_pick_continue();
+                s.show_action(app, action)
+            }),
+            ['g', 'a'] => self.action_context(app, ActionKind::CherryPickAbort, |s, app| {
+                let action =  app.version_control.cherry_pick_abort();
+                s.show_action(app, action)
+            }),
+            ['i'] => Ok(HandleChordResult::Unhandled),
+            ['i', 'i'] => self.action_context(app, ActionKind::BisectStart, |s, app| {
+                // with two marked revisions the newest is bad and the oldest good
+                let targets = s.previous_targets(app);
+                let good_target = if targets.len() > 1 { targets.first() } else { None };
+                if let Some(bad) = s.handle_input(app, "bad revision", targets.last().map(String::as_str))? {
+                    s.show_header(app, HeaderKind::Waiting)?;
+                    if let Some(good) = s.handle_input(app, "good revision", good_target.map(String::as_str))? {
+                        let action = app.version_control.bisect_start(bad.trim(), good.trim());
+                        s.show_action(app, action)
+                    } else {
+                        s.show_previous_action_result(app)
+                    }
+                } else {
+                    s.show_previous_action_result(app)
+                }
+            }),
+            ['i', 'g'] => self.action_context(app, ActionKind::BisectGood, |s, app| {
+                let action = app.version_control.bisect_good();
+                s.show_action(app, action)
+            }),
+            ['i', 'b'] => self.action_context(app, ActionKind::BisectBad, |s, app| {
+                let action = app.version_control.bisect_bad();
+                s.show_action(app, action)
+            }),
+            ['i', 's'] => self.action_context(app, ActionKind::BisectSkip, |s, app| {
+                let action = app.version_control.bisect_skip();
+                s.show_action(app, action)
+            }),
+            ['i', 'r'] => self.action_context(app, ActionKind::BisectRun, |s, app| {
+                if let Some(input) = s.handle_input(app, "test command", None)? {
+                    let mut words = input.split_whitespace().map(String::from);
+                    match words.next() {
+                        Some(command) => {
+                            let args: Vec<_> = words.collect();
+                            let action = app.version_control.bisect_run(&command, &args);
+                            s.show_action(app, action)
+                        }
+                        None => s.show_previous_action_result(app),
+                    }
+                } else {
+                    s.show_previous_action_result(app)
+                }
+            }),
+            ['i', 'a'] => self.action_context(app, ActionKind::BisectReset, |s, app| {
+                let action = app.version_control.bisect_reset();
+                s.show_action(app, action)
+            }),
             ['R'] => Ok(HandleChordResult::Unhandled),
-            ['R', 'A'] => self.action_context(ActionKind::RevertAll, |s| {
+            ['R', 'A'] => self.action_context(app, ActionKind::RevertAll, |s, app| {
                 let action =  app.version_control.revert_all();
                 s.show_action(app, action)
             }),
             ['r'] => Ok(HandleChordResult::Unhandled),
-            ['r', 's'] => self.action_context(ActionKind::RevertSelected, |s| {
+            ['r', 's'] => self.action_context(app, ActionKind::RevertSelected, |s, app| {
                 match app.version_control.get_current_changed_files() {
                     Ok(mut entries) => {
-                        if entries.len() == 0 {
+                        if entries.is_empty() {
                             s.show_empty_entries(app)
                         } else if s.show_select_ui(app, &mut entries[..])? {
                             let action =  app.version_control.revert_selected(&entries);
@@ -405,65 +796,346 @@ where
                             s.show_previous_action_result(app)
                         }
                     }
-                    Err(error) => s.show_result(app, &ActionResult::from_err(error)),
+                    Err(error) => s.show_result(app, &ActionResult::from_error(error)),
                 }
             }),
-            ['r', 'r'] => self.action_context(ActionKind::UnresolvedConflicts, |s| {
+            ['r', 'b'] => self.action_context(app, ActionKind::Backout, |s, app| {
+                if let Some(revision) = s.handle_input(app, "back out revision", s.previous_target(app).as_deref())? {
+                    let revision = revision.trim();
+                    let message = format!("back out {}", revision);
+                    s.show_header(app, HeaderKind::Waiting)?;
+                    if let Some(input) = s.handle_input(app, "commit message", Some(&message))? {
+                        let action = app.version_control.backout(revision, input.trim());
+                        s.show_action(app, action)
+                    } else {
+                        s.show_previous_action_result(app)
+                    }
+                } else {
+                    s.show_previous_action_result(app)
+                }
+            }),
+            ['r', 'r'] => self.action_context(app, ActionKind::UnresolvedConflicts, |s, app| {
                 let action =  app.version_control.conflicts();
                 s.show_action(app, action)
             }),
-            ['r', 'o'] => self.action_context(ActionKind::MergeTakingOther, |s| {
+            ['r', 'o'] => self.action_context(app, ActionKind::MergeTakingOther, |s, app| {
                 let action =  app.version_control.take_other();
                 s.show_action(app, action)
             }),
-            ['r', 'l'] => self.action_context(ActionKind::MergeTakingLocal, |s| {
+            ['r', 'l'] => self.action_context(app, ActionKind::MergeTakingLocal, |s, app| {
                 let action =  app.version_control.take_local();
                 s.show_action(app, action)
             }),
-            ['f'] => self.action_context(ActionKind::Fetch, |s| {
-                let action =  app.version_control.fetch();
-                s.show_action(app, action)
+            ['f'] => self.action_context(app, ActionKind::Fetch, |s, app| {
+                if let Some(remote) = s.handle_remote_input(app, true)? {
+                    let action =  app.version_control.fetch(remote_arg(&remote));
+                    s.show_action(app, action)
+                } else {
+                    s.show_previous_action_result(app)
+                }
             }),
-            ['p'] => self.action_context(ActionKind::Pull, |s| {
-                let action =  app.version_control.pull();
-                s.show_action(app, action)
+            ['p'] => self.action_context(app, ActionKind::Pull, |s, app| {
+                if let Some(remote) = s.handle_remote_input(app, true)? {
+                    let action =  app.version_control.pull(remote_arg(&remote));
+                    s.show_action(app, action)
+                } else {
+                    s.show_previous_action_result(app)
+                }
+            }),
+            ['P'] => self.action_context(app, ActionKind::Push, |s, app| {
+                if let Some(remote) = s.handle_remote_input(app, true)? {
+                    let action =  app.version_control.push(remote_arg(&remote));
+                    s.show_action(app, action)
+                } else {
+                    s.show_previous_action_result(app)
+                }
             }),
-            ['P'] => self.action_context(ActionKind::Push, |s| {
-                let action =  app.version_control.push();
+            ['o'] => Ok(HandleChordResult::Unhandled),
+            ['o', 'o'] => self.action_context(app, ActionKind::ListRemotes, |s, app| {
+                let action = app.version_control.list_remotes();
                 s.show_action(app, action)
             }),
+            ['o', 'n'] => self.action_context(app, ActionKind::NewRemote, |s, app| {
+                if let Some(name) = s.handle_input(app, "new remote name", None)? {
+                    s.show_header(app, HeaderKind::Waiting)?;
+                    if let Some(url) = s.handle_input(app, "remote url", None)? {
+                        let action = app.version_control.add_remote(name.trim(), url.trim());
+                        s.show_action(app, action)
+                    } else {
+                        s.show_previous_action_result(app)
+                    }
+                } else {
+                    s.show_previous_action_result(app)
+                }
+            }),
+            ['o', 'r'] => self.action_context(app, ActionKind::RenameRemote, |s, app| {
+                if let Some(name) = s.handle_input(app, "remote to rename", s.previous_target(app).as_deref())? {
+                    s.show_header(app, HeaderKind::Waiting)?;
+                    if let Some(new_name) = s.handle_input(app, "new remote name", None)? {
+                        let action = app.version_control.rename_remote(name.trim(), new_name.trim());
+                        s.show_action(app, action)
+                    } else {
+                        s.show_previous_action_result(app)
+                    }
+                } else {
+                    s.show_previous_action_result(app)
+                }
+            }),
+            ['o', 'd'] => self.action_context(app, ActionKind::RemoveRemote, |s, app| {
+                if let Some(name) = s.handle_input(app, "remote to remove", s.previous_target(app).as_deref())? {
+                    let action = app.version_control.remove_remote(name.trim());
+                    s.show_action(app, action)
+                } else {
+                    s.show_previous_action_result(app)
+                }
+            }),
+            ['o', 'u'] => self.action_context(app, ActionKind::SetRemoteUrl, |s, app| {
+                if let Some(name) = s.handle_input(app, "remote to change", s.previous_target(app).as_deref())? {
+                    s.show_header(app, HeaderKind::Waiting)?;
+                    if let Some(url) = s.handle_input(app, "new remote url", None)? {
+                        let action = app.version_control.set_remote_url(name.trim(), url.trim());
+                        s.show_action(app, action)
+                    } else {
+                        s.show_previous_action_result(app)
+                    }
+                } else {
+                    s.show_previous_action_result(app)
+                }
+            }),
             ['t'] => Ok(HandleChordResult::Unhandled),
-            ['t', 'n'] => self.action_context(ActionKind::NewTag, |s| {
-                if let Some(input) = s.handle_input(app, "new tag name", None)? {
-                    let action =  app.version_control.create_tag(input.trim());
+            ['t', 't'] => self.action_context(app, ActionKind::ListTags, |s, app| {
+                let action = app.version_control.list_tags();
+                s.show_action(app, action)
+            }),
+            ['t', 'n'] => self.action_context(app, ActionKind::NewTag, |s, app| {
+                let name = match s.handle_input(app, "new tag name", None)? {
+                    Some(name) => String::from(name.trim()),
+                    None => return s.show_previous_action_result(app),
+                };
+
+                let force = app
+                    .version_control
+                    .get_tags()
+                    .map(|tags| tags.contains(&name))
+                    .unwrap_or(false);
+                if force {
+                    s.show_header(app, HeaderKind::Waiting)?;
+                    let prompt = format!("tag {} exists, overwrite it? (y/n)", name);
+                    match s.handle_input(app, &prompt, None)? {
+                        Some(answer) if answer.trim().eq_ignore_ascii_case("y") => (),
+                        _ => return s.show_previous_action_result(app),
+                    }
+                }
+
+                s.show_header(app, HeaderKind::Waiting)?;
+                let message = match s.handle_optional_input(app, "tag message, empty for a lightweight tag", None)? {
+                    Some(message) => message,
+                    None => return s.show_previous_action_result(app),
+                };
+                let message = Some(message.trim()).filter(|m| !m.is_empty());
+
+                s.show_header(app, HeaderKind::Waiting)?;
+                if let Some(remote) = s.handle_remote_input(app, false)? {
+                    let action = app.version_control.create_tag(&name, message, force, remote_arg(&remote));
                     s.show_action(app, action)
                 } else {
                     s.show_previous_action_result(app)
                 }
             }),
+            ['t', 'd'] => self.action_context(app, ActionKind::DeleteTag, |s, app| {
+                if let Some(input) = s.handle_input(app, "tag to delete", s.previous_target(app).as_deref())? {
+                    s.show_header(app, HeaderKind::Waiting)?;
+                    if let Some(remote) = s.handle_remote_input(app, false)? {
+                        let action = app.version_control.delete_tag(input.trim(), remote_arg(&remote));
+                        s.show_action(app, action)
+                    } else {
+                        s.show_previous_action_result(app)
+                    }
+                } else {
+                    s.show_previous_action_result(app)
+                }
+            }),
             ['b'] => Ok(HandleChordResult::Unhandled),
-            ['b', 'b'] => self.action_context(ActionKind::ListBranches, |s| {
+            ['b', 'b'] => self.action_context(app, ActionKind::ListBranches, |s, app| {
                 let action =  app.version_control.list_branches();
                 s.show_action(app, action)
             }),
-            ['b', 'n'] => self.action_context(ActionKind::NewBranch, |s| {
+            ['b', 'n'] => self.action_context(app, ActionKind::NewBranch, |s, app| {
                 if let Some(input) = s.handle_input(app, "new branch name", None)? {
-                    let action =  app.version_control.create_branch(input.trim());
+                    s.show_header(app, HeaderKind::Waiting)?;
+                    if let Some(remote) = s.handle_remote_input(app, false)? {
+                        let action =  app.version_control.create_branch(input.trim(), remote_arg(&remote));
+                        s.show_action(app, action)
+                    } else {
+                        s.show_previous_action_result(app)
+                    }
+                } else {
+                    s.show_previous_action_result(app)
+                }
+            }),
+            ['b', 'a'] => self.action_context(app, ActionKind::NewBranchAt, |s, app| {
+                if let Some(revision) = s.handle_input(app, "branch at", s.previous_target(app).as_deref())? {
+                    s.show_header(app, HeaderKind::Waiting)?;
+                    if let Some(name) = s.handle_input(app, "new branch name", None)? {
+                        let action = app.version_control.create_branch_at(name.trim(), revision.trim());
+                        s.show_action(app, action)
+                    } else {
+                        s.show_previous_action_result(app)
+                    }
+                } else {
+                    s.show_previous_action_result(app)
+                }
+            }),
+            ['b', 'd'] => self.action_context(app, ActionKind::DeleteBranch, |s, app| {
+                if let Some(input) = s.handle_input(app, "branch to delete", s.previous_target(app).as_deref())? {
+                    s.show_header(app, HeaderKind::Waiting)?;
+                    if let Some(remote) = s.handle_remote_input(app, false)? {
+                        let action =  app.version_control.close_branch(input.trim(), remote_arg(&remote));
+                        s.show_action(app, action)
+                    } else {
+                        s.show_previous_action_result(app)
+                    }
+                } else {
+                    s.show_previous_action_result(app)
+                }
+            }),
+            ['S'] => Ok(HandleChordResult::Unhandled),
+            ['S', 'S'] => self.action_context(app, ActionKind::ListSubmodules, |s, app| {
+                let action = app.version_control.list_submodules();
+                s.show_action(app, action)
+            }),
+            ['S', 'i'] => self.action_context(app, ActionKind::InitSubmodules, |s, app| {
+                let action = app.version_control.init_submodules();
+                s.show_action(app, action)
+            }),
+            ['S', 'u'] => self.action_context(app, ActionKind::UpdateSubmodules, |s, app| {
+                let action = app.version_control.update_submodules();
+                s.show_action(app, action)
+            }),
+            ['S', 'y'] => self.action_context(app, ActionKind::SyncSubmodules, |s, app| {
+                let action = app.version_control.sync_submodules();
+                s.show_action(app, action)
+            }),
+            ['S', 'e'] => self.action_context(app, ActionKind::EnterSubmodule, |s, app| {
+                if let Some(input) = s.handle_input(app, "enter submodule", s.previous_target(app).as_deref())? {
+                    s.switch_root(app, input.trim())
+                } else {
+                    s.show_previous_action_result(app)
+                }
+            }),
+            ['S', 'o'] => self.action_context(app, ActionKind::LeaveSubmodule, |s, app| {
+                match app.version_control.get_superproject_root() {
+                    Ok(root) => s.switch_root(app, &root),
+                    Err(error) => s.show_result(app, &ActionResult::from_error(error)),
+                }
+            }),
+            ['w'] => Ok(HandleChordResult::Unhandled),
+            ['w', 'w'] => self.action_context(app, ActionKind::ListWorktrees, |s, app| {
+                let action = app.version_control.list_worktrees();
+                s.show_action(app, action)
+            }),
+            ['w', 'n'] => self.action_context(app, ActionKind::NewWorktree, |s, app| {
+                let target = s.previous_target(app);
+                if let Some(path) = s.handle_input(app, "new worktree path", None)? {
+                    s.show_header(app, HeaderKind::Waiting)?;
+                    if let Some(input) = s.handle_input(app, "at branch or revision", target.as_deref())? {
+                        let action = app.version_control.add_worktree(path.trim(), input.trim());
+                        s.show_action(app, action)
+                    } else {
+                        s.show_previous_action_result(app)
+                    }
+                } else {
+                    s.show_previous_action_result(app)
+                }
+            }),
+            ['w', 'd'] => self.action_context(app, ActionKind::RemoveWorktree, |s, app| {
+                if let Some(input) = s.handle_input(app, "worktree to remove", s.previous_target(app).as_deref())? {
+                    let action = app.version_control.remove_worktree(input.trim());
+                    s.show_action(app, action)
+                } else {
+                    s.show_previous_action_result(app)
+                }
+            }),
+            ['w', 'p'] => self.action_context(app, ActionKind::PruneWorktrees, |s, app| {
+                let action = app.version_control.prune_worktrees();
+                s.show_action(app, action)
+            }),
+            ['w', 's'] => self.action_context(app, ActionKind::SwitchWorktree, |s, app| {
+                if let Some(input) = s.handle_input(app, "switch to worktree", s.previous_target(app).as_deref())? {
+                    s.switch_root(app, input.trim())
+                } else {
+                    s.show_previous_action_result(app)
+                }
+            }),
+            ['z'] => Ok(HandleChordResult::Unhandled),
+            ['z', 'z'] => self.action_context(app, ActionKind::ListStashes, |s, app| {
+                let action =  app.version_control.list_stashes();
+                s.show_action(app, action)
+            }),
+            ['z', 'a'] => self.action_context(app, ActionKind::StashAll, |s, app| {
+                if let Some(input) = s.handle_input(app, "stash message", None)? {
+                    let action =  app.version_control.stash_all(input.trim());
                     s.show_action(app, action)
                 } else {
                     s.show_previous_action_result(app)
                 }
             }),
-            ['b', 'd'] => self.action_context(ActionKind::DeleteBranch, |s| {
-                if let Some(input) = s.handle_input(app, "branch to delete", s.previous_target(app))? {
-                    let action =  app.version_control.close_branch(input.trim());
+            ['z', 's'] => self.action_context(app, ActionKind::StashSelected, |s, app| {
+                match app.version_control.get_current_changed_files() {
+                    Ok(mut entries) => {
+                        if entries.is_empty() {
+                            s.show_empty_entries(app)
+                        } else if s.show_select_ui(app, &mut entries[..])? {
+                            s.show_header(app, HeaderKind::Waiting)?;
+                            if let Some(input) =
+                                s.handle_input(app, "stash message", None)?
+                            {
+                                let action =  app.version_control.stash_selected(input.trim(), &entries);
+                                s.show_action(app, action)
+                            } else {
+                                s.show_previous_action_result(app)
+                            }
+                        } else {
+                            s.show_previous_action_result(app)
+                        }
+                    }
+                    Err(error) => s.show_result(app, &ActionResult::from_error(error)),
+                }
+            }),
+            ['z', 'd'] => self.action_context(app, ActionKind::ShowStash, |s, app| {
+                if let Some(input) = s.handle_input(app, "show stash", s.previous_target(app).as_deref())? {
+                    let action =  app.version_control.show_stash(input.trim());
+                    s.show_action(app, action)
+                } else {
+                    s.show_previous_action_result(app)
+                }
+            }),
+            ['z', 'k'] => self.action_context(app, ActionKind::ApplyStash, |s, app| {
+                if let Some(input) = s.handle_input(app, "apply stash", s.previous_target(app).as_deref())? {
+                    let action =  app.version_control.apply_stash(input.trim());
+                    s.show_action(app, action)
+                } else {
+                    s.show_previous_action_result(app)
+                }
+            }),
+            ['z', 'p'] => self.action_context(app, ActionKind::PopStash, |s, app| {
+                if let Some(input) = s.handle_input(app, "pop stash", s.previous_target(app).as_deref())? {
+                    let action =  app.version_control.pop_stash(input.trim());
                     s.show_action(app, action)
                 } else {
                     s.show_previous_action_result(app)
                 }
             }),
-            ['x'] => self.action_context(ActionKind::CustomAction, |s| {
-                if app.custom_actions.len() > 0 {
+            ['z', 'x'] => self.action_context(app, ActionKind::DropStash, |s, app| {
+                if let Some(input) = s.handle_input(app, "drop stash", s.previous_target(app).as_deref())? {
+                    let action =  app.version_control.drop_stash(input.trim());
+                    s.show_action(app, action)
+                } else {
+                    s.show_previous_action_result(app)
+                }
+            }),
+            ['x'] => self.action_context(app, ActionKind::CustomAction, |s, app| {
+                if !app.custom_actions.is_empty() {
                     for c in &app.custom_actions {
                         s.write
                             .queue(SetForegroundColor(ENTRY_COLOR))?
@@ -524,7 +1196,7 @@ where
                             .zip(
                                 self.current_key_chord
                                     .iter()
-                                    .map(|c| *c)
+                                    .copied()
                                     .chain(iter::repeat('\0')),
                             )
                             .all(|(a, b)| a == b)
@@ -575,6 +1247,17 @@ where
         app: &Application,
         prompt: &str,
         initial: Option<&str>,
+    ) -> Result<Option<String>> {
+        let res = self.handle_optional_input(app, prompt, initial)?;
+        Ok(res.filter(|line| !line.is_empty()))
+    }
+
+    /// Like `handle_input` but an empty line is a valid answer
+    fn handle_optional_input(
+        &mut self,
+        app: &Application,
+        prompt: &str,
+        initial: Option<&str>,
     ) -> Result<Option<String>> {
         self.show_header(app, HeaderKind::Waiting)?;
         execute!(
@@ -586,21 +1269,8 @@ where
             cursor::Show,
         )?;
 
-        let initial = if let Some(initial) = initial {
-            initial
-        } else {
-            ""
-        };
-        let res = match input::read_line(initial) {
-            Ok(line) => {
-                if line.len() > 0 {
-                    Some(line)
-                } else {
-                    None
-                }
-            }
-            Err(_error) => None,
-        };
+        let initial = initial.unwrap_or_default();
+        let res = input::read_line(initial).ok().flatten();
         self.write.execute(cursor::Hide)?;
         Ok(res)
     }
@@ -618,11 +1288,55 @@ where
             self.show_header(app, HeaderKind::Error)?;
         }
 
-        self.scroll_view.set_content(
-            &result.output[..],
-            self.current_action_kind,
-            self.terminal_size,
-        );
+        if self.current_action_kind.is_log() && result.success {
+            let entries = app.version_control.parse_log(&result.output[..]);
+            self.scroll_view.set_log_content(
+                entries,
+                self.current_action_kind,
+                self.terminal_size,
+            );
+        } else if self.current_action_kind.is_blame() && result.success {
+            let lines = BlameLine::parse_all(&result.output[..]);
+            self.scroll_view.set_blame_content(
+                lines,
+                self.current_action_kind,
+                self.terminal_size,
+            );
+        } else {
+            let mut hints: Vec<_> = result
+                .error
+                .clone()
+                .map(|e| app.version_control.classify_error(e))
+                .and_then(|e| error_hint(&e))
+                .into_iter()
+                .collect();
+            // the lines of a selectable output are targets so reminders
+            // would get in the way
+            let is_selectable =
+                result.success && self.current_action_kind.can_select_output();
+            if !is_selectable {
+                hints.extend(in_progress_hints(app));
+            }
+
+            if hints.is_empty() {
+                self.scroll_view.set_content(
+                    &result.output[..],
+                    self.current_action_kind,
+                    self.terminal_size,
+                );
+            } else {
+                let output = format!(
+                    "{}\n\n{}",
+                    hints.join("\n"),
+                    result.output.trim_start()
+                );
+                self.scroll_view.set_content(
+                    &output[..],
+                    self.current_action_kind,
+                    self.terminal_size,
+                );
+            }
+        }
         self.scroll_view
             .draw_content(&mut self.write, self.terminal_size)
     }
@@ -663,72 +1377,265 @@ where
             .queue(Print("press a key and peform an action"))?
             .queue(cursor::MoveToNextLine(2))?;
 
-        Self::show_help_action(&mut write, "h", ActionKind::Help)?;
-        Self::show_help_action(&mut write, "q", ActionKind::Quit)?;
+        Self::show_help_action(&mut write, app, "h", ActionKind::Help)?;
+        Self::show_help_action(&mut write, app, "q", ActionKind::Quit)?;
 
         write.queue(cursor::MoveToNextLine(1))?;
 
-        Self::show_help_action(&mut write, "s", ActionKind::Status)?;
-        Self::show_help_action(&mut write, "ll", ActionKind::Log)?;
-        Self::show_help_action(&mut write, "lc", ActionKind::LogCount)?;
+        Self::show_help_action(&mut write, app, "s", ActionKind::Status)?;
+        Self::show_help_action(&mut write, app, "ll", ActionKind::Log)?;
+        Self::show_help_action(&mut write, app, "lc", ActionKind::LogCount)?;
+        Self::show_help_action(&mut write, app, "lr", ActionKind::Reflog)?;
 
         Self::show_help_action(
             &mut write,
+            app,
             "ee",
             ActionKind::CurrentFullRevision,
         )?;
-        Self::show_help_action(&mut write, "dd", ActionKind::CurrentDiffAll)?;
         Self::show_help_action(
             &mut write,
+            app,
+            "dd",
+            ActionKind::CurrentDiffAll,
+        )?;
+        Self::show_help_action(
+            &mut write,
+            app,
             "ds",
             ActionKind::CurrentDiffSelected,
         )?;
-        Self::show_help_action(&mut write, "DC", ActionKind::RevisionChanges)?;
-        Self::show_help_action(&mut write, "DD", ActionKind::RevisionDiffAll)?;
         Self::show_help_action(
             &mut write,
+            app,
+            "DC",
+            ActionKind::RevisionChanges,
+        )?;
+        Self::show_help_action(
+            &mut write,
+            app,
+            "DD",
+            ActionKind::RevisionDiffAll,
+        )?;
+        Self::show_help_action(
+            &mut write,
+            app,
             "DS",
             ActionKind::RevisionDiffSelected,
         )?;
 
+        Self::show_help_action(&mut write, app, "aa", ActionKind::Blame)?;
+        Self::show_help_action(&mut write, app, "ap", ActionKind::BlameParent)?;
+
         write.queue(cursor::MoveToNextLine(1))?;
 
-        Self::show_help_action(&mut write, "cc", ActionKind::CommitAll)?;
-        Self::show_help_action(&mut write, "cs", ActionKind::CommitSelected)?;
-        Self::show_help_action(&mut write, "u", ActionKind::Update)?;
-        Self::show_help_action(&mut write, "m", ActionKind::Merge)?;
-        Self::show_help_action(&mut write, "RA", ActionKind::RevertAll)?;
-        Self::show_help_action(&mut write, "rs", ActionKind::RevertSelected)?;
+        Self::show_help_action(&mut write, app, "cc", ActionKind::CommitAll)?;
+        Self::show_help_action(
+            &mut write,
+            app,
+            "cs",
+            ActionKind::CommitSelected,
+        )?;
+        Self::show_help_action(&mut write, app, "ch", ActionKind::CommitHunks)?;
+        Self::show_help_action(&mut write, app, "ca", ActionKind::Amend)?;
+        Self::show_help_action(&mut write, app, "u", ActionKind::Update)?;
+        Self::show_help_action(&mut write, app, "m", ActionKind::Merge)?;
+        Self::show_help_action(&mut write, app, "BB", ActionKind::Rebase)?;
+        Self::show_help_action(
+            &mut write,
+            app,
+            "BC",
+            ActionKind::RebaseContinue,
+        )?;
+        Self::show_help_action(&mut write, app, "BS", ActionKind::RebaseSkip)?;
+        Self::show_help_action(&mut write, app, "BA", ActionKind::RebaseAbort)?;
+        Self::show_help_action(&mut write, app, "BE", ActionKind::EditHistory)?;
+        Self::show_help_action(&mut write, app, "gg", ActionKind::CherryPick)?;
+        Self::show_help_action(
+            &mut write,
+            app,
+            "gc",
+            ActionKind::CherryPickContinue,
+        )?;
+        Self::show_help_action(
+            &mut write,
+            app,
+            "ga",
+            ActionKind::CherryPickAbort,
+        )?;
+        Self::show_help_action(&mut write, app, "ii", ActionKind::BisectStart)?;
+        Self::show_help_action(&mut write, app, "ig", ActionKind::BisectGood)?;
+        Self::show_help_action(&mut write, app, "ib", ActionKind::BisectBad)?;
+        Self::show_help_action(&mut write, app, "is", ActionKind::BisectSkip)?;
+        Self::show_help_action(&mut write, app, "ir", ActionKind::BisectRun)?;
+        Self::show_help_action(&mut write, app, "ia", ActionKind::BisectReset)?;
+        Self::show_help_action(&mut write, app, "RA", ActionKind::RevertAll)?;
+        Self::show_help_action(
+            &mut write,
+            app,
+            "rs",
+            ActionKind::RevertSelected,
+        )?;
+        Self::show_help_action(&mut write, app, "rb", ActionKind::Backout)?;
 
         write.queue(cursor::MoveToNextLine(1))?;
 
         Self::show_help_action(
             &mut write,
+            app,
             "rr",
             ActionKind::UnresolvedConflicts,
         )?;
-        Self::show_help_action(&mut write, "ro", ActionKind::MergeTakingOther)?;
-        Self::show_help_action(&mut write, "rl", ActionKind::MergeTakingLocal)?;
+        Self::show_help_action(
+            &mut write,
+            app,
+            "ro",
+            ActionKind::MergeTakingOther,
+        )?;
+        Self::show_help_action(
+            &mut write,
+            app,
+            "rl",
+            ActionKind::MergeTakingLocal,
+        )?;
+
+        write.queue(cursor::MoveToNextLine(1))?;
+
+        Self::show_help_action(&mut write, app, "f", ActionKind::Fetch)?;
+        Self::show_help_action(&mut write, app, "p", ActionKind::Pull)?;
+        Self::show_help_action(&mut write, app, "P", ActionKind::Push)?;
+
+        write.queue(cursor::MoveToNextLine(1))?;
+
+        Self::show_help_action(&mut write, app, "oo", ActionKind::ListRemotes)?;
+        Self::show_help_action(&mut write, app, "on", ActionKind::NewRemote)?;
+        Self::show_help_action(
+            &mut write,
+            app,
+            "or",
+            ActionKind::RenameRemote,
+        )?;
+        Self::show_help_action(
+            &mut write,
+            app,
+            "od",
+            ActionKind::RemoveRemote,
+        )?;
+        Self::show_help_action(
+            &mut write,
+            app,
+            "ou",
+            ActionKind::SetRemoteUrl,
+        )?;
+
+        write.queue(cursor::MoveToNextLine(1))?;
+
+        Self::show_help_action(&mut write, app, "tt", ActionKind::ListTags)?;
+        Self::show_help_action(&mut write, app, "tn", ActionKind::NewTag)?;
+        Self::show_help_action(&mut write, app, "td", ActionKind::DeleteTag)?;
+
+        write.queue(cursor::MoveToNextLine(1))?;
+
+        Self::show_help_action(
+            &mut write,
+            app,
+            "SS",
+            ActionKind::ListSubmodules,
+        )?;
+        Self::show_help_action(
+            &mut write,
+            app,
+            "Si",
+            ActionKind::InitSubmodules,
+        )?;
+        Self::show_help_action(
+            &mut write,
+            app,
+            "Su",
+            ActionKind::UpdateSubmodules,
+        )?;
+        Self::show_help_action(
+            &mut write,
+            app,
+            "Sy",
+            ActionKind::SyncSubmodules,
+        )?;
+        Self::show_help_action(
+            &mut write,
+            app,
+            "Se",
+            ActionKind::EnterSubmodule,
+        )?;
+        Self::show_help_action(
+            &mut write,
+            app,
+            "So",
+            ActionKind::LeaveSubmodule,
+        )?;
 
         write.queue(cursor::MoveToNextLine(1))?;
 
-        Self::show_help_action(&mut write, "f", ActionKind::Fetch)?;
-        Self::show_help_action(&mut write, "p", ActionKind::Pull)?;
-        Self::show_help_action(&mut write, "P", ActionKind::Push)?;
+        Self::show_help_action(
+            &mut write,
+            app,
+            "ww",
+            ActionKind::ListWorktrees,
+        )?;
+        Self::show_help_action(&mut write, app, "wn", ActionKind::NewWorktree)?;
+        Self::show_help_action(
+            &mut write,
+            app,
+            "wd",
+            ActionKind::RemoveWorktree,
+        )?;
+        Self::show_help_action(
+            &mut write,
+            app,
+            "wp",
+            ActionKind::PruneWorktrees,
+        )?;
+        Self::show_help_action(
+            &mut write,
+            app,
+            "ws",
+            ActionKind::SwitchWorktree,
+        )?;
 
         write.queue(cursor::MoveToNextLine(1))?;
 
-        Self::show_help_action(&mut write, "tn", ActionKind::NewTag)?;
+        Self::show_help_action(
+            &mut write,
+            app,
+            "bb",
+            ActionKind::ListBranches,
+        )?;
+        Self::show_help_action(&mut write, app, "bn", ActionKind::NewBranch)?;
+        Self::show_help_action(&mut write, app, "ba", ActionKind::NewBranchAt)?;
+        Self::show_help_action(
+            &mut write,
+            app,
+            "bd",
+            ActionKind::DeleteBranch,
+        )?;
 
         write.queue(cursor::MoveToNextLine(1))?;
 
-        Self::show_help_action(&mut write, "bb", ActionKind::ListBranches)?;
-        Self::show_help_action(&mut write, "bn", ActionKind::NewBranch)?;
-        Self::show_help_action(&mut write, "bd", ActionKind::DeleteBranch)?;
+        Self::show_help_action(&mut write, app, "zz", ActionKind::ListStashes)?;
+        Self::show_help_action(&mut write, app, "za", ActionKind::StashAll)?;
+        Self::show_help_action(
+            &mut write,
+            app,
+            "zs",
+            ActionKind::StashSelected,
+        )?;
+        Self::show_help_action(&mut write, app, "zd", ActionKind::ShowStash)?;
+        Self::show_help_action(&mut write, app, "zk", ActionKind::ApplyStash)?;
+        Self::show_help_action(&mut write, app, "zp", ActionKind::PopStash)?;
+        Self::show_help_action(&mut write, app, "zx", ActionKind::DropStash)?;
 
         write.queue(cursor::MoveToNextLine(1))?;
 
-        Self::show_help_action(&mut write, "x", ActionKind::CustomAction)?;
+        Self::show_help_action(&mut write, app, "x", ActionKind::CustomAction)?;
 
         write.flush()?;
         Ok(ActionResult::from_ok(String::from_utf8(write)?))
@@ -736,22 +1643,103 @@ where
 
     fn show_help_action<HW>(
         write: &mut HW,
+        app: &Application,
         shortcut: &str,
         action: ActionKind,
     ) -> Result<()>
     where
         HW: Write,
     {
+        let support = action
+            .capability()
+            .map(|c| app.version_control.support(c))
+            .unwrap_or(Support::Supported);
+        let shortcut_color = match support {
+            Support::Unsupported(_) => DIMMED_COLOR,
+            _ => ENTRY_COLOR,
+        };
+
         queue!(
             write,
-            SetForegroundColor(ENTRY_COLOR),
+            SetForegroundColor(shortcut_color),
             Print('\t'),
             Print(shortcut),
             ResetColor,
             Print('\t'),
             Print('\t'),
-            Print(action.name()),
-            cursor::MoveToNextLine(1),
-        )
+        )?;
+        match support {
+            Support::Supported => write.queue(Print(action.name()))?,
+            Support::Differs(semantics) => write
+                .queue(Print(action.name()))?
+                .queue(SetForegroundColor(DIMMED_COLOR))?
+                .queue(Print(" ("))?
+                .queue(Print(semantics))?
+                .queue(Print(")"))?
+                .queue(ResetColor)?,
+            Support::Unsupported(reason) => write
+                .queue(SetForegroundColor(DIMMED_COLOR))?
+                .queue(Print(action.name()))?
+                .queue(Print(" (unsupported: "))?
+                .queue(Print(reason))?
+                .queue(Print(")"))?
+                .queue(ResetColor)?,
+        };
+        write.queue(cursor::MoveToNextLine(1))?;
+        Ok(())
+    }
+}
+
+/// The remote typed for `handle_remote_input`, if not the default
+fn remote_arg(input: &str) -> Option<&str> {
+    Some(input.trim()).filter(|r| !r.is_empty())
+}
+
+/// Reminders of the operations stopped halfway and how to resume them
+fn in_progress_hints(app: &Application) -> Vec<String> {
+    let mut hints = Vec::new();
+    if app.version_control.is_rebase_in_progress() {
+        hints.push(String::from(
+            "a rebase is in progress, continue it with BC, skip the current commit with BS or abort it with BA",
+        ));
+    }
+    if app.version_control.is_bisect_in_progress() {
+        hints.push(String::from(
+            "a bisect is in progress, mark the current revision with ig (good), ib (bad) or is (skip), run a test command with ir or reset it with ia",
+        ));
+    }
+    if app.version_control.is_cherry_pick_in_progress() {
+        hints.push(String::from(
+            "a cherry-pick is in progress, continue it with gc or abort it with ga",
+        ));
+    }
+    if app.version_control.is_backout_in_progress() {
+        hints.push(String::from(
+            "a backout is in progress, commit it with cc once conflicts are resolved or drop it with RA",
+        ));
+    }
+    hints
+}
+
+/// What the user can do about a failed action
+fn error_hint(error: &VcsError) -> Option<String> {
+    match error {
+        VcsError::MissingExecutable(program) => Some(format!(
+            "make sure {} is installed and in your PATH",
+            program
+        )),
+        VcsError::NotARepository(_) => Some(String::from(
+            "the repository is gone, restart verco from inside a repository",
+        )),
+        VcsError::MergeConflict(_) => Some(String::from(
+            "resolve conflicts with rr (list), ro (take other) or rl (take local)",
+        )),
+        VcsError::AuthFailure(_) => Some(String::from(
+            "check the credentials or ssh keys for the remote",
+        )),
+        VcsError::NonUtf8Output(_) => {
+            Some(String::from("the output could not be shown as utf8"))
+        }
+        VcsError::CommandFailed { .. } => None,
     }
 }
@@ -18,6 +18,11 @@ pub const ENTRY_COLOR: Color = Color::Rgb {
     b: 100,
 };
 
+pub const DIMMED_COLOR: Color = Color::Rgb {
+    r: 110,
+    g: 110,
+    b: 110,
+};
 pub const LOG_COLORS: &[Color] = &[
     Color::White,
     Color::Rgb {
@@ -76,14 +81,24 @@ pub enum HeaderKind {
 pub struct Header<'a> {
     pub action_name: &'a str,
     pub directory_name: &'a str,
+    /// An ongoing operation like a bisect to show next to the action
+    pub state: Option<&'a str>,
+    /// The branch the current one tracks and how far apart they are
+    pub upstream: Option<&'a str>,
 }
 
 impl<'a> Header<'a> {
+    fn state_length(&self) -> usize {
+        self.state.map(|s| s.len() + 3).unwrap_or(0)
+            + self.upstream.map(|u| u.len() + 3).unwrap_or(0)
+    }
+
     pub fn full_length(&self) -> usize {
         HEADER_PREFIX.len()
             + self.directory_name.len()
             + 3
             + self.action_name.len()
+            + self.state_length()
     }
 
     pub fn min_length(&self) -> usize {
@@ -91,6 +106,7 @@ impl<'a> Header<'a> {
             + self.directory_name.len().min(DIR_NAME_MAX_LENGTH)
             + 3
             + self.action_name.len()
+            + self.state_length()
     }
 }
 
@@ -149,6 +165,13 @@ where
         header_prefix = HEADER_PREFIX;
         directory_name = &header.directory_name
             [(header.directory_name.len() - DIR_NAME_MAX_LENGTH)..];
+    } else if header.upstream.is_some() {
+        // the upstream is the first thing to go on narrow windows
+        let header = Header {
+            upstream: None,
+            ..header
+        };
+        return show_header(write, header, kind, terminal_size);
     } else {
         panic!("window too small");
     }
@@ -169,6 +192,22 @@ where
         Print(' '),
         SetBackgroundColor(background_color),
         SetForegroundColor(HEADER_COLOR),
+    )?;
+    for state in header.state.iter().chain(header.upstream.iter()) {
+        queue!(
+            write,
+            Print(' '),
+            SetBackgroundColor(background_dark_color),
+            SetForegroundColor(ACTION_COLOR),
+            Print(' '),
+            Print(state),
+            Print(' '),
+            SetBackgroundColor(background_color),
+            SetForegroundColor(HEADER_COLOR),
+        )?;
+    }
+    queue!(
+        write,
         Print(" ".repeat(padding)),
         SetBackgroundColor(background_dark_color),
         SetForegroundColor(ACTION_COLOR),
@@ -273,7 +312,7 @@ pub fn draw_filter_bar<W>(
 where
     W: Write,
 {
-    if !active && filter.len() == 0 {
+    if !active && filter.is_empty() {
         return Ok(());
     }
 
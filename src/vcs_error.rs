@@ -0,0 +1,236 @@
+use std::{ffi::OsStr, fmt, io, string::FromUtf8Error};
+
+/// Why a version control operation failed
+#[derive(Clone, Debug)]
+pub enum VcsError {
+    /// The executable could not be found
+    MissingExecutable(String),
+    /// The directory is not inside a repository of the backend
+    NotARepository(String),
+    /// The operation stopped with conflicts left to resolve
+    MergeConflict(String),
+    /// A remote rejected the credentials or none were available
+    AuthFailure(String),
+    /// The command output was not valid utf8
+    NonUtf8Output(String),
+    /// Any other failure with the exit code, if there was one
+    CommandFailed { code: Option<i32>, stderr: String },
+}
+
+/// Known error messages a backend prints for each kind of failure
+pub struct ErrorMessages {
+    pub not_a_repository: &'static [&'static str],
+    pub merge_conflict: &'static [&'static str],
+    pub auth_failure: &'static [&'static str],
+}
+
+impl VcsError {
+    pub fn from_spawn(program: &OsStr, error: io::Error) -> Self {
+        match error.kind() {
+            io::ErrorKind::NotFound => {
+                Self::MissingExecutable(program.to_string_lossy().into_owned())
+            }
+            _ => Self::CommandFailed {
+                code: None,
+                stderr: error.to_string(),
+            },
+        }
+    }
+
+    pub fn from_utf8(error: FromUtf8Error) -> Self {
+        Self::NonUtf8Output(error.to_string())
+    }
+
+    /// Turns a generic command failure into a more specific error if its
+    /// output contains one of the backend's known `messages`
+    pub fn classify(self, messages: &ErrorMessages) -> Self {
+        let stderr = match self {
+            Self::CommandFailed { ref stderr, .. } => stderr,
+            _ => return self,
+        };
+
+        let contains_any = |patterns: &[&str]| {
+            patterns.iter().any(|pattern| stderr.contains(pattern))
+        };
+        if contains_any(messages.not_a_repository) {
+            Self::NotARepository(stderr.clone())
+        } else if contains_any(messages.merge_conflict) {
+            Self::MergeConflict(stderr.clone())
+        } else if contains_any(messages.auth_failure) {
+            Self::AuthFailure(stderr.clone())
+        } else {
+            self
+        }
+    }
+}
+
+impl fmt::Display for VcsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::MissingExecutable(program) => {
+                write!(f, "could not find the {} executable", program)
+            }
+            Self::NotARepository(message)
+            | Self::MergeConflict(message)
+            | Self::AuthFailure(message)
+            | Self::NonUtf8Output(message) => f.write_str(message.trim_end()),
+            Self::CommandFailed { code, stderr } => {
+                if !stderr.trim().is_empty() {
+                    f.write_str(stderr.trim_end())
+                } else if let Some(code) = code {
+                    write!(f, "command failed with exit code {}", code)
+                } else {
+                    f.write_str("command failed")
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        git_actions::GitActions, hg_actions::HgActions, jj_actions::JjActions,
+        version_control_actions::VersionControlActions,
+    };
+
+    fn failed(stderr: &str) -> VcsError {
+        VcsError::CommandFailed {
+            code: Some(1),
+            stderr: String::from(stderr),
+        }
+    }
+
+    fn kind(error: &VcsError) -> &'static str {
+        match error {
+            VcsError::MissingExecutable(_) => "missing executable",
+            VcsError::NotARepository(_) => "not a repository",
+            VcsError::MergeConflict(_) => "merge conflict",
+            VcsError::AuthFailure(_) => "auth failure",
+            VcsError::NonUtf8Output(_) => "non utf8 output",
+            VcsError::CommandFailed { .. } => "command failed",
+        }
+    }
+
+    #[test]
+    fn backend_stderr_is_classified() {
+        let git = GitActions {
+            current_dir: String::new(),
+        };
+        let hg = HgActions {
+            current_dir: String::new(),
+        };
+        let jj = JjActions {
+            current_dir: String::new(),
+        };
+        let cases: &[(&dyn VersionControlActions, &str, &str)] = &[
+            (
+                &git,
+                "fatal: not a git repository (or any of the parent directories): .git",
+                "not a repository",
+            ),
+            (
+                &git,
+                "CONFLICT (content): Merge conflict in src/lib.rs\nAutomatic merge failed; fix conflicts and then commit the result.",
+                "merge conflict",
+            ),
+            (
+                &git,
+                "error: could not revert 1229608... b\nhint: after resolving the conflicts",
+                "merge conflict",
+            ),
+            (
+                &git,
+                "fatal: could not read Username for 'https://example.com': terminal prompts disabled",
+                "auth failure",
+            ),
+            (
+                &git,
+                "git@example.com: Permission denied (publickey).\nfatal: Could not read from remote repository.",
+                "auth failure",
+            ),
+            (&git, "error: pathspec 'nope' did not match", "command failed"),
+            (
+                &hg,
+                "abort: no repository found in '/tmp' (.hg not found)!",
+                "not a repository",
+            ),
+            (
+                &hg,
+                "abort: unresolved conflicts (see 'hg resolve', then 'hg commit')",
+                "merge conflict",
+            ),
+            (
+                &hg,
+                "abort: outstanding uncommitted merge",
+                "merge conflict",
+            ),
+            (
+                &hg,
+                "abort: authorization failed\nabort: HTTP Error 401: Unauthorized",
+                "auth failure",
+            ),
+            (&hg, "abort: unknown revision 'nope'", "command failed"),
+            (
+                &jj,
+                "Error: There is no jj repo in \".\"",
+                "not a repository",
+            ),
+            (
+                &jj,
+                "Error: failed to authenticate SSH session",
+                "auth failure",
+            ),
+            (
+                &jj,
+                "Error: Revision \"nope\" doesn't exist",
+                "command failed",
+            ),
+        ];
+        for (backend, stderr, expected) in cases {
+            let error = backend.classify_error(failed(stderr));
+            assert_eq!(
+                kind(&error),
+                *expected,
+                "{} stderr {:?}",
+                backend.executable_name(),
+                stderr
+            );
+        }
+    }
+
+    #[test]
+    fn missing_executable_is_not_reclassified() {
+        let error = VcsError::from_spawn(
+            OsStr::new("hg"),
+            io::Error::new(io::ErrorKind::NotFound, "no such file"),
+        );
+        assert_eq!(kind(&error), "missing executable");
+        assert_eq!(error.to_string(), "could not find the hg executable");
+
+        let git = GitActions {
+            current_dir: String::new(),
+        };
+        let error = git.classify_error(error);
+        assert_eq!(kind(&error), "missing executable");
+    }
+
+    #[test]
+    fn other_spawn_errors_fail_the_command() {
+        let error = VcsError::from_spawn(
+            OsStr::new("git"),
+            io::Error::new(io::ErrorKind::PermissionDenied, "denied"),
+        );
+        assert_eq!(kind(&error), "command failed");
+    }
+
+    #[test]
+    fn failures_without_output_show_the_exit_code() {
+        let error = VcsError::CommandFailed {
+            code: Some(128),
+            stderr: String::from("  \n"),
+        };
+        assert_eq!(error.to_string(), "command failed with exit code 128");
+    }
+}
@@ -1,10 +1,82 @@
-use std::process::{Command, Stdio};
+use std::{
+    fmt,
+    process::{Command, Stdio},
+};
 
 use crate::{
-    action::{ActionTask, CommandTask},
+    action::{ready, ActionResult, ActionTask, CommandTask},
+    history_edit::PlanEntry,
+    log_entry::LogEntry,
+    patch::FilePatch,
     select::Entry,
+    vcs_error::VcsError,
 };
 
+/// Operations whose support differs between backends
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Capability {
+    Merge,
+    TakeOther,
+    TakeLocal,
+    Fetch,
+    Pull,
+    Push,
+    ListTags,
+    CreateTag,
+    DeleteTag,
+    ListBranches,
+    CreateBranch,
+    CloseBranch,
+    Stash,
+    CommitHunks,
+    Amend,
+    Rebase,
+    EditHistory,
+    CherryPick,
+    Backout,
+    Blame,
+    Bisect,
+    Reflog,
+    Worktrees,
+    Submodules,
+    Remotes,
+}
+
+/// How a backend supports a `Capability`
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Support {
+    Supported,
+    /// Supported with the backend specific semantics explained
+    Differs(&'static str),
+    /// Not supported for the reason explained
+    Unsupported(&'static str),
+}
+
+/// The branch the current one tracks and how far apart they are
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Upstream {
+    pub name: String,
+    /// Commits only on the current branch
+    pub ahead: usize,
+    /// Commits only on the upstream
+    pub behind: usize,
+}
+
+impl fmt::Display for Upstream {
+    /// Formats like `git branch -vv`, as in `origin/main [ahead 1, behind 2]`
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.name)?;
+        match (self.ahead, self.behind) {
+            (0, 0) => Ok(()),
+            (ahead, 0) => write!(f, " [ahead {}]", ahead),
+            (0, behind) => write!(f, " [behind {}]", behind),
+            (ahead, behind) => {
+                write!(f, " [ahead {}, behind {}]", ahead, behind)
+            }
+        }
+    }
+}
+
 pub trait VersionControlActions: Send {
     fn executable_name(&self) -> &'static str;
     fn current_dir(&self) -> &str;
@@ -18,61 +90,266 @@ pub trait VersionControlActions: Send {
         command
     }
 
+    /// Turns a generic command failure into a more specific error from
+    /// what the executable printed
+    fn classify_error(&self, error: VcsError) -> VcsError {
+        error
+    }
+
+    /// Runs `command` to completion and returns its output
+    fn run_command(&self, command: &mut Command) -> Result<String, VcsError> {
+        handle_command(command).map_err(|e| self.classify_error(e))
+    }
+
+    /// How this backend supports `capability`
+    fn support(&self, _capability: Capability) -> Support {
+        Support::Supported
+    }
+
     /// Sets the root of the current repository
-    fn set_root(&mut self) -> Result<(), String>;
+    fn set_root(&mut self) -> Result<(), VcsError>;
     /// Get the root of the current repository
     fn get_root(&self) -> &str;
+    /// Moves to the root of the repository containing `dir`, relative to
+    /// the current root, keeping the current one if that fails
+    fn switch_root(&mut self, dir: &str) -> Result<(), VcsError>;
 
-    fn get_current_changed_files(&self) -> Result<Vec<Entry>, String>;
+    fn get_current_changed_files(&self) -> Result<Vec<Entry>, VcsError>;
+    /// Gets every file under version control
+    fn get_tracked_files(&self) -> Result<Vec<Entry>, VcsError>;
     fn get_revision_changed_files(
         &self,
         target: &str,
-    ) -> Result<Vec<Entry>, String>;
+    ) -> Result<Vec<Entry>, VcsError>;
 
-    fn version(&self) -> Result<String, String>;
+    fn version(&self) -> Result<String, VcsError>;
 
     fn status(&self) -> Box<dyn ActionTask>;
     /// Shows the header and all diffs for the current revision
     fn current_export(&self) -> Box<dyn ActionTask>;
+    /// Outputs `count` revisions as records in the format parsed by
+    /// `LogEntry::parse`
     fn log(&self, count: usize) -> Box<dyn ActionTask>;
+    /// Parses the output of a successful `log` task
+    fn parse_log(&self, output: &str) -> Vec<LogEntry> {
+        LogEntry::parse_all(output)
+    }
+    /// Lists the revisions the working copy was at, newest first, one per
+    /// line starting with its revision
+    fn reflog(&self) -> Box<dyn ActionTask>;
 
-    fn current_diff_all(&self) -> Box<dyn ActionTask>;
-    fn current_diff_selected(
+    /// Outputs the revision that last changed each line of `filename` as of
+    /// `revision`, or the working copy, as records in the format parsed by
+    /// `BlameLine::parse`
+    fn blame(
         &self,
-        entries: &Vec<Entry>,
+        filename: &str,
+        revision: Option<&str>,
     ) -> Box<dyn ActionTask>;
+    fn get_parent_revision(&self, revision: &str) -> Result<String, VcsError>;
+
+    fn current_diff_all(&self) -> Box<dyn ActionTask>;
+    fn current_diff_selected(&self, entries: &[Entry]) -> Box<dyn ActionTask>;
 
     fn revision_changes(&self, target: &str) -> Box<dyn ActionTask>;
     fn revision_diff_all(&self, target: &str) -> Box<dyn ActionTask>;
     fn revision_diff_selected(
         &self,
         target: &str,
-        entries: &Vec<Entry>,
+        entries: &[Entry],
     ) -> Box<dyn ActionTask>;
 
     fn commit_all(&self, message: &str) -> Box<dyn ActionTask>;
     fn commit_selected(
         &self,
         message: &str,
-        entries: &Vec<Entry>,
+        entries: &[Entry],
     ) -> Box<dyn ActionTask>;
+    /// Gets the uncommitted changes of the selected entries split into hunks
+    fn get_current_patch(
+        &self,
+        entries: &[Entry],
+    ) -> Result<Vec<FilePatch>, VcsError>;
+    /// Commits only the selected changes of `files` leaving the rest of
+    /// them in the working copy
+    fn commit_hunks(
+        &self,
+        message: &str,
+        files: &[FilePatch],
+    ) -> Box<dyn ActionTask>;
+    /// Gets the message of the commit `amend` would change
+    fn get_last_commit_message(&self) -> Result<String, VcsError>;
+    /// Whether the commit `amend` would change is already on a remote
+    fn is_last_commit_published(&self) -> Result<bool, VcsError>;
+    /// Replaces the last commit's message and adds the selected entries
+    /// to it
+    fn amend(&self, message: &str, entries: &[Entry]) -> Box<dyn ActionTask>;
     fn revert_all(&self) -> Box<dyn ActionTask>;
-    fn revert_selected(&self, entries: &Vec<Entry>) -> Box<dyn ActionTask>;
+    fn revert_selected(&self, entries: &[Entry]) -> Box<dyn ActionTask>;
     fn update(&self, target: &str) -> Box<dyn ActionTask>;
     fn merge(&self, target: &str) -> Box<dyn ActionTask>;
+    fn rebase(&self, target: &str) -> Box<dyn ActionTask>;
+    /// Marks conflicts as resolved and resumes a stopped rebase
+    fn rebase_continue(&self) -> Box<dyn ActionTask>;
+    fn rebase_skip(&self) -> Box<dyn ActionTask>;
+    fn rebase_abort(&self) -> Box<dyn ActionTask>;
+    /// Whether a rebase stopped and is waiting to be continued or aborted
+    fn is_rebase_in_progress(&self) -> bool {
+        false
+    }
+    /// Applies the changes of `revisions` onto the current revision in order
+    fn cherry_pick(&self, revisions: &[String]) -> Box<dyn ActionTask>;
+    /// Marks conflicts as resolved and resumes a stopped cherry-pick
+    fn cherry_pick_continue(&self) -> Box<dyn ActionTask>;
+    fn cherry_pick_abort(&self) -> Box<dyn ActionTask>;
+    /// Whether a cherry-pick stopped and is waiting to be continued or
+    /// aborted
+    fn is_cherry_pick_in_progress(&self) -> bool {
+        false
+    }
+    /// Commits the inverse of `revision` with `message`, stopping on
+    /// conflicts for them to be resolved and the result committed
+    fn backout(&self, revision: &str, message: &str) -> Box<dyn ActionTask>;
+    /// Whether a backout stopped on conflicts and is waiting to be committed
+    fn is_backout_in_progress(&self) -> bool {
+        false
+    }
+    /// The message a backout stopped on conflicts is to be committed with
+    fn get_backout_message(&self) -> Option<String> {
+        None
+    }
+    /// Starts a bisect between a known `bad` and `good` revision and checks
+    /// out the first revision to test
+    fn bisect_start(&self, bad: &str, good: &str) -> Box<dyn ActionTask>;
+    fn bisect_good(&self) -> Box<dyn ActionTask>;
+    fn bisect_bad(&self) -> Box<dyn ActionTask>;
+    fn bisect_skip(&self) -> Box<dyn ActionTask>;
+    /// Marks each revision to test by running `command` with `args` until
+    /// the first bad revision is found
+    fn bisect_run(&self, command: &str, args: &[String])
+        -> Box<dyn ActionTask>;
+    fn bisect_reset(&self) -> Box<dyn ActionTask>;
+    fn is_bisect_in_progress(&self) -> bool {
+        false
+    }
+    /// Gets the commits after `base` up to the current one, oldest first
+    fn get_history(&self, base: &str) -> Result<Vec<PlanEntry>, VcsError>;
+    /// Rewrites the commits after `base` following `plan`
+    fn edit_history(
+        &self,
+        base: &str,
+        plan: &[PlanEntry],
+    ) -> Box<dyn ActionTask>;
 
     fn conflicts(&self) -> Box<dyn ActionTask>;
     fn take_other(&self) -> Box<dyn ActionTask>;
     fn take_local(&self) -> Box<dyn ActionTask>;
 
-    fn fetch(&self) -> Box<dyn ActionTask>;
-    fn pull(&self) -> Box<dyn ActionTask>;
-    fn push(&self) -> Box<dyn ActionTask>;
+    /// Fetches from `remote` or else from every remote
+    fn fetch(&self, remote: Option<&str>) -> Box<dyn ActionTask>;
+    /// Pulls from `remote` or else from the default one
+    fn pull(&self, remote: Option<&str>) -> Box<dyn ActionTask>;
+    /// Pushes to `remote` or else to the default one
+    fn push(&self, remote: Option<&str>) -> Box<dyn ActionTask>;
+
+    /// Lists the remotes, one per line as its name followed by its url
+    fn list_remotes(&self) -> Box<dyn ActionTask>;
+    fn add_remote(&self, name: &str, url: &str) -> Box<dyn ActionTask>;
+    fn rename_remote(&self, name: &str, new_name: &str) -> Box<dyn ActionTask>;
+    fn remove_remote(&self, name: &str) -> Box<dyn ActionTask>;
+    fn set_remote_url(&self, name: &str, url: &str) -> Box<dyn ActionTask>;
+    /// The remote the current branch tracks, if any
+    fn get_upstream_remote(&self) -> Option<String> {
+        None
+    }
+    /// The remote refs are published to by default, which is the upstream
+    /// one if there is any
+    fn get_default_remote(&self) -> Option<String> {
+        self.get_upstream_remote()
+    }
+    /// Outputs the branch the current one tracks as formatted by `Upstream`,
+    /// or nothing if there is none
+    fn upstream(&self) -> Box<dyn ActionTask> {
+        ready(ActionResult::from_ok(String::new()))
+    }
+    /// Whether creating and closing tags and branches also updates a remote
+    fn publishes_refs(&self) -> bool {
+        false
+    }
 
-    fn create_tag(&self, name: &str) -> Box<dyn ActionTask>;
+    /// Lists the tags, one per line as its name followed by its target
+    /// revision, its date and its annotation if any
+    fn list_tags(&self) -> Box<dyn ActionTask>;
+    fn get_tags(&self) -> Result<Vec<String>, VcsError>;
+    /// Creates a tag annotated with `message` if any, moving an existing one
+    /// if `force` is set, and publishes it to `remote` where tags are pushed
+    fn create_tag(
+        &self,
+        name: &str,
+        message: Option<&str>,
+        force: bool,
+        remote: Option<&str>,
+    ) -> Box<dyn ActionTask>;
+    /// Deletes a tag, also from `remote` where tags are pushed
+    fn delete_tag(
+        &self,
+        name: &str,
+        remote: Option<&str>,
+    ) -> Box<dyn ActionTask>;
+    /// Lists the submodules of the repository, one per line as its path
+    /// followed by its checked out revision, its recorded revision and a
+    /// word for its state
+    fn list_submodules(&self) -> Box<dyn ActionTask>;
+    fn init_submodules(&self) -> Box<dyn ActionTask>;
+    /// Checks out the recorded revision of every submodule
+    fn update_submodules(&self) -> Box<dyn ActionTask>;
+    /// Copies the submodule urls from the repository to its configuration
+    fn sync_submodules(&self) -> Box<dyn ActionTask>;
+    /// The root of the repository the current one is a submodule of
+    fn get_superproject_root(&self) -> Result<String, VcsError>;
+    /// Lists the worktrees of the repository, one per line as its path
+    /// followed by a word for its branch or revision
+    fn list_worktrees(&self) -> Box<dyn ActionTask>;
+    fn add_worktree(&self, path: &str, target: &str) -> Box<dyn ActionTask>;
+    fn remove_worktree(&self, path: &str) -> Box<dyn ActionTask>;
+    /// Forgets worktrees whose directory was deleted
+    fn prune_worktrees(&self) -> Box<dyn ActionTask>;
+    /// Lists the branches, one per line as its name followed by its
+    /// upstream and how far apart they are if it tracks one
     fn list_branches(&self) -> Box<dyn ActionTask>;
-    fn create_branch(&self, name: &str) -> Box<dyn ActionTask>;
-    fn close_branch(&self, name: &str) -> Box<dyn ActionTask>;
+    /// Creates a branch and publishes it to `remote` where branches are
+    /// pushed
+    fn create_branch(
+        &self,
+        name: &str,
+        remote: Option<&str>,
+    ) -> Box<dyn ActionTask>;
+    /// Creates a branch at `revision` without touching the working copy
+    fn create_branch_at(
+        &self,
+        name: &str,
+        revision: &str,
+    ) -> Box<dyn ActionTask>;
+    /// Closes a branch and deletes it from `remote` where branches are
+    /// pushed
+    fn close_branch(
+        &self,
+        name: &str,
+        remote: Option<&str>,
+    ) -> Box<dyn ActionTask>;
+
+    /// Outputs one stash per line starting with its name
+    fn list_stashes(&self) -> Box<dyn ActionTask>;
+    fn stash_all(&self, message: &str) -> Box<dyn ActionTask>;
+    fn stash_selected(
+        &self,
+        message: &str,
+        entries: &[Entry],
+    ) -> Box<dyn ActionTask>;
+    fn show_stash(&self, name: &str) -> Box<dyn ActionTask>;
+    fn apply_stash(&self, name: &str) -> Box<dyn ActionTask>;
+    fn pop_stash(&self, name: &str) -> Box<dyn ActionTask>;
+    fn drop_stash(&self, name: &str) -> Box<dyn ActionTask>;
 }
 
 pub fn task<F>(
@@ -87,17 +364,80 @@ where
     Box::new(CommandTask::Waiting(command))
 }
 
-pub fn handle_command(command: &mut Command) -> Result<String, String> {
-    match command.output() {
-        Ok(output) => {
-            if output.status.success() {
-                String::from_utf8(output.stdout).map_err(|e| e.to_string())
-            } else {
-                String::from_utf8(output.stderr)
-                    .map_err(|e| e.to_string())
-                    .and_then(|o| Err(o))
-            }
+/// Like `task` but the command reads `input` from its stdin
+pub fn task_with_input<F>(
+    version_control: &dyn VersionControlActions,
+    input: String,
+    builder: F,
+) -> Box<dyn ActionTask>
+where
+    F: FnOnce(&mut Command),
+{
+    let mut command = version_control.command();
+    (builder)(&mut command);
+    Box::new(CommandTask::WaitingWithInput(command, input))
+}
+
+pub fn unsupported(
+    version_control: &dyn VersionControlActions,
+    action: &str,
+) -> Box<dyn ActionTask> {
+    ready(ActionResult::from_err(format!(
+        "{} does not support {}",
+        version_control.executable_name(),
+        action
+    )))
+}
+
+/// The error for a synchronous query the backend does not support
+pub fn unsupported_error(
+    version_control: &dyn VersionControlActions,
+    action: &str,
+) -> VcsError {
+    VcsError::CommandFailed {
+        code: None,
+        stderr: format!(
+            "{} does not support {}",
+            version_control.executable_name(),
+            action
+        ),
+    }
+}
+
+pub fn handle_command(command: &mut Command) -> Result<String, VcsError> {
+    let output = match command.output() {
+        Ok(output) => output,
+        Err(error) => {
+            return Err(VcsError::from_spawn(command.get_program(), error))
         }
-        Err(error) => Err(error.to_string()),
+    };
+
+    if output.status.success() {
+        String::from_utf8(output.stdout).map_err(VcsError::from_utf8)
+    } else {
+        let bytes = if output.stderr.iter().all(u8::is_ascii_whitespace) {
+            output.stdout
+        } else {
+            output.stderr
+        };
+        let stderr = String::from_utf8(bytes).map_err(VcsError::from_utf8)?;
+        Err(VcsError::CommandFailed {
+            code: output.status.code(),
+            stderr,
+        })
+    }
+}
+
+/// Pads or truncates a name to `width` chars, like git's `%<(width,trunc)`
+pub fn format_truncated(text: &str, width: usize) -> String {
+    let len = text.chars().count();
+    if len > width {
+        let mut truncated: String = text.chars().take(width - 2).collect();
+        truncated.push_str("..");
+        truncated
+    } else {
+        let mut padded = String::from(text);
+        padded.extend(std::iter::repeat_n(' ', width - len));
+        padded
     }
 }